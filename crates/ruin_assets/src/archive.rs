@@ -0,0 +1,325 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+use crate::asset::normalize_asset_id;
+use crate::source::AssetSource;
+
+// Layout, all integers little endian:
+//   magic "RUINPAK\0", version u32, entry count u32
+//   per entry: name length u16, name (utf8), offset u64, stored size u64, size u64, compression u8
+//   entry data, back to back
+const MAGIC: &[u8; 8] = b"RUINPAK\0";
+const VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None = 0,
+    Deflate = 1,
+}
+
+impl Compression {
+    fn from_u8(value: u8) -> io::Result<Self> {
+        match value {
+            0 => Ok(Compression::None),
+            1 => Ok(Compression::Deflate),
+            _ => Err(invalid_data(format!("unknown compression {}", value))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ArchiveEntry {
+    offset: u64,
+    stored_size: u64,
+    size: u64,
+    compression: Compression,
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+pub struct ArchiveWriter {
+    files: Vec<(String, Vec<u8>)>,
+    compression: Compression,
+}
+
+impl ArchiveWriter {
+    pub fn new(compression: Compression) -> Self {
+        Self {
+            files: Vec::new(),
+            compression,
+        }
+    }
+
+    pub fn add(&mut self, id: &str, data: Vec<u8>) {
+        self.files.push((normalize_asset_id(id), data));
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    pub fn write(self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut blobs = Vec::with_capacity(self.files.len());
+        for (id, data) in self.files {
+            let size = data.len() as u64;
+            let (stored, compression) = match self.compression {
+                Compression::Deflate => {
+                    let packed = miniz_oxide::deflate::compress_to_vec(&data, 6);
+                    // already compressed formats (png) rarely shrink, keep whichever is smaller
+                    if packed.len() < data.len() {
+                        (packed, Compression::Deflate)
+                    } else {
+                        (data, Compression::None)
+                    }
+                }
+                Compression::None => (data, Compression::None),
+            };
+            blobs.push((id, stored, size, compression));
+        }
+
+        let header_size: u64 = (MAGIC.len() + 8) as u64
+            + blobs
+                .iter()
+                .map(|(id, ..)| (2 + id.len() + 8 * 3 + 1) as u64)
+                .sum::<u64>();
+
+        let mut out = BufWriter::new(File::create(path)?);
+        out.write_all(MAGIC)?;
+        out.write_all(&VERSION.to_le_bytes())?;
+        out.write_all(&(blobs.len() as u32).to_le_bytes())?;
+
+        let mut offset = header_size;
+        for (id, stored, size, compression) in &blobs {
+            let name_len = u16::try_from(id.len())
+                .map_err(|_| invalid_data(format!("asset id too long: {}", id)))?;
+            out.write_all(&name_len.to_le_bytes())?;
+            out.write_all(id.as_bytes())?;
+            out.write_all(&offset.to_le_bytes())?;
+            out.write_all(&(stored.len() as u64).to_le_bytes())?;
+            out.write_all(&size.to_le_bytes())?;
+            out.write_all(&[*compression as u8])?;
+            offset += stored.len() as u64;
+        }
+
+        for (_, stored, ..) in &blobs {
+            out.write_all(stored)?;
+        }
+        out.flush()
+    }
+}
+
+pub struct ArchiveSource {
+    file: Mutex<File>,
+    len: u64,
+    index: HashMap<String, ArchiveEntry>,
+}
+
+impl ArchiveSource {
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let len = file.metadata()?.len();
+        let mut file = BufReader::new(file);
+
+        let mut magic = [0u8; 8];
+        file.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_data("not a ruin archive".to_string()));
+        }
+        let version = read_u32(&mut file)?;
+        if version != VERSION {
+            return Err(invalid_data(format!(
+                "unsupported archive version {}",
+                version
+            )));
+        }
+
+        let count = read_u32(&mut file)?;
+        // the count comes from the file, don't trust it for more than a hint
+        let mut index = HashMap::with_capacity((count as usize).min(1024));
+        for _ in 0..count {
+            let mut name_len = [0u8; 2];
+            file.read_exact(&mut name_len)?;
+            let mut name = vec![0u8; u16::from_le_bytes(name_len) as usize];
+            file.read_exact(&mut name)?;
+            let name = String::from_utf8(name).map_err(|err| invalid_data(err.to_string()))?;
+
+            let offset = read_u64(&mut file)?;
+            let stored_size = read_u64(&mut file)?;
+            let size = read_u64(&mut file)?;
+            let mut compression = [0u8; 1];
+            file.read_exact(&mut compression)?;
+
+            index.insert(
+                name,
+                ArchiveEntry {
+                    offset,
+                    stored_size,
+                    size,
+                    compression: Compression::from_u8(compression[0])?,
+                },
+            );
+        }
+
+        Ok(Self {
+            file: Mutex::new(file.into_inner()),
+            len,
+            index,
+        })
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item = &str> {
+        self.index.keys().map(|id| id.as_str())
+    }
+}
+
+impl AssetSource for ArchiveSource {
+    fn read(&self, id: &str) -> io::Result<Vec<u8>> {
+        let id = normalize_asset_id(id);
+        let entry = *self.index.get(&id).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} is not in the archive", id),
+            )
+        })?;
+
+        // a corrupt or truncated archive can claim any size, check it before allocating
+        let end = entry.offset.checked_add(entry.stored_size);
+        if end.is_none_or(|end| end > self.len) {
+            return Err(invalid_data(format!("{}: entry runs past the archive", id)));
+        }
+
+        let mut stored = vec![0u8; entry.stored_size as usize];
+        {
+            let mut file = self.file.lock().unwrap();
+            file.seek(SeekFrom::Start(entry.offset))?;
+            file.read_exact(&mut stored)?;
+        }
+
+        match entry.compression {
+            Compression::None => Ok(stored),
+            Compression::Deflate => {
+                let data = miniz_oxide::inflate::decompress_to_vec_with_limit(
+                    &stored,
+                    entry.size as usize,
+                )
+                .map_err(|err| invalid_data(format!("{}: {:?}", id, err)))?;
+                if data.len() as u64 != entry.size {
+                    return Err(invalid_data(format!("{}: size mismatch", id)));
+                }
+                Ok(data)
+            }
+        }
+    }
+
+    fn exists(&self, id: &str) -> bool {
+        self.index.contains_key(&normalize_asset_id(id))
+    }
+}
+
+fn read_u32(file: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0u8; 4];
+    file.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64(file: &mut impl Read) -> io::Result<u64> {
+    let mut bytes = [0u8; 8];
+    file.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("ruin_archive_{}_{}.pak", std::process::id(), name))
+    }
+
+    fn write_archive(name: &str, compression: Compression) -> PathBuf {
+        let mut writer = ArchiveWriter::new(compression);
+        writer.add("./maps/level.tmx", b"<map>".repeat(100));
+        writer.add("skelly/idle.png", (0..=255).collect());
+        writer.add("empty.txt", Vec::new());
+        let path = temp_path(name);
+        writer.write(&path).unwrap();
+        path
+    }
+
+    #[test]
+    fn round_trips_stored_and_deflated() {
+        for (name, compression) in [
+            ("stored", Compression::None),
+            ("deflated", Compression::Deflate),
+        ] {
+            let path = write_archive(name, compression);
+            let archive = ArchiveSource::open(&path).unwrap();
+            assert_eq!(
+                archive.read("maps/level.tmx").unwrap(),
+                b"<map>".repeat(100)
+            );
+            assert_eq!(
+                archive.read("skelly/idle.png").unwrap(),
+                (0..=255).collect::<Vec<u8>>()
+            );
+            assert!(archive.read("empty.txt").unwrap().is_empty());
+            assert!(archive.exists("/skelly/../skelly/idle.png"));
+            assert_eq!(
+                archive.read("missing.png").unwrap_err().kind(),
+                io::ErrorKind::NotFound
+            );
+            let mut ids: Vec<&str> = archive.ids().collect();
+            ids.sort();
+            assert_eq!(ids, ["empty.txt", "maps/level.tmx", "skelly/idle.png"]);
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn truncated_archive_fails_to_read_instead_of_panicking() {
+        let path = write_archive("truncated", Compression::None);
+        let len = std::fs::metadata(&path).unwrap().len();
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_len(len - 10)
+            .unwrap();
+
+        let archive = ArchiveSource::open(&path).unwrap();
+        let err = archive.read("skelly/idle.png").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn truncated_index_fails_to_open() {
+        let path = write_archive("truncated_index", Compression::None);
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_len(20)
+            .unwrap();
+        assert!(ArchiveSource::open(&path).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        let path = temp_path("not_an_archive");
+        std::fs::write(&path, b"PK\x03\x04 definitely a zip").unwrap();
+        assert!(ArchiveSource::open(&path).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+}
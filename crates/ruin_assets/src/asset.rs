@@ -1,6 +1,23 @@
 use std::fmt;
 use std::hash::{Hash, Hasher};
 
+// Asset ids are paths relative to the assets root, '/' separated with no empty or '.' parts
+// and '..' applied, so "/bricks.png", "./bricks.png" and "maps/../bricks.png" all name the
+// same asset. A '..' that would go above the root is dropped, ids never leave it.
+pub fn normalize_asset_id(id: &str) -> String {
+    let mut parts: Vec<&str> = Vec::new();
+    for part in id.split(['/', '\\']) {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            _ => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
 // Static and Thrad-Safe
 pub trait Asset: 'static + Send + Sync {}
 
@@ -39,3 +56,33 @@ impl Hash for AssetPath {
         self.path.hash(state);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_separators_and_dots() {
+        assert_eq!(normalize_asset_id("/bricks.png"), "bricks.png");
+        assert_eq!(normalize_asset_id("./bricks.png"), "bricks.png");
+        assert_eq!(normalize_asset_id("maps//./level.tmx"), "maps/level.tmx");
+        assert_eq!(
+            normalize_asset_id("maps\\tiles\\grass.png"),
+            "maps/tiles/grass.png"
+        );
+        assert_eq!(normalize_asset_id(""), "");
+    }
+
+    #[test]
+    fn applies_parent_dirs() {
+        assert_eq!(normalize_asset_id("maps/../bricks.png"), "bricks.png");
+        assert_eq!(normalize_asset_id("maps/tiles/../../a/b.png"), "a/b.png");
+    }
+
+    #[test]
+    fn parent_dirs_never_leave_the_root() {
+        assert_eq!(normalize_asset_id("../bricks.png"), "bricks.png");
+        assert_eq!(normalize_asset_id("maps/../../../etc/passwd"), "etc/passwd");
+        assert_eq!(normalize_asset_id(".."), "");
+    }
+}
@@ -33,6 +33,7 @@ impl<T: Asset> AssetCache<T> {
             AssetData {
                 asset,
                 path: path.clone(),
+                ref_count: 0,
             },
         );
 
@@ -54,10 +55,125 @@ impl<T: Asset> AssetCache<T> {
     pub fn get_handle_for_path(&self, path: &AssetPath) -> Option<Handle<T>> {
         self.path_lookup.get(path).copied()
     }
+
+    // Assets start unreferenced. Owners (e.g. a loaded scene) acquire what they use and
+    // release it when done, unload_unused then drops anything nobody holds.
+    pub fn acquire(&mut self, handle: Handle<T>) {
+        if let Some(entry) = self.assets.get_mut(&handle) {
+            entry.ref_count += 1;
+        }
+    }
+
+    pub fn release(&mut self, handle: Handle<T>) {
+        if let Some(entry) = self.assets.get_mut(&handle) {
+            entry.ref_count = entry.ref_count.saturating_sub(1);
+        }
+    }
+
+    pub fn ref_count(&self, handle: Handle<T>) -> u32 {
+        self.assets
+            .get(&handle)
+            .map(|entry| entry.ref_count)
+            .unwrap_or(0)
+    }
+
+    // Swaps the asset behind an existing handle, keeping its path and references.
+    pub fn replace(&mut self, handle: Handle<T>, asset: T) -> Option<T> {
+        let entry = self.assets.get_mut(&handle)?;
+        Some(std::mem::replace(&mut entry.asset, asset))
+    }
+
+    pub fn remove(&mut self, handle: Handle<T>) -> Option<T> {
+        let entry = self.assets.remove(&handle)?;
+        if let Some(path) = &entry.path {
+            self.path_lookup.remove(path);
+        }
+        Some(entry.asset)
+    }
+
+    // Removes every asset with no references and returns their handles so callers can drop
+    // anything they derived from them. Handles are never reused, so stale ones just miss.
+    pub fn unload_unused(&mut self) -> Vec<Handle<T>> {
+        let unused: Vec<Handle<T>> = self
+            .assets
+            .iter()
+            .filter(|(_, entry)| entry.ref_count == 0)
+            .map(|(handle, _)| *handle)
+            .collect();
+
+        for handle in &unused {
+            self.remove(*handle);
+        }
+
+        unused
+    }
 }
 
 pub struct AssetData<T: Asset> {
     pub asset: T,
     pub path: Option<AssetPath>,
+    pub ref_count: u32,
     // metadata can go in here
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct Dummy(u32);
+
+    impl Asset for Dummy {}
+
+    #[test]
+    fn insert_reuses_the_handle_for_a_path() {
+        let mut cache = AssetCache::new();
+        let a = cache.insert(Dummy(1), Some(AssetPath::new("a.png")));
+        let again = cache.insert(Dummy(2), Some(AssetPath::new("a.png")));
+        assert_eq!(a, again);
+        assert_eq!(cache.get(a).unwrap().0, 1);
+        assert_ne!(cache.insert(Dummy(3), None), cache.insert(Dummy(4), None));
+    }
+
+    #[test]
+    fn unload_unused_keeps_acquired_assets() {
+        let mut cache = AssetCache::new();
+        let held = cache.insert(Dummy(1), Some(AssetPath::new("held.png")));
+        let unused = cache.insert(Dummy(2), Some(AssetPath::new("unused.png")));
+        cache.acquire(held);
+        cache.acquire(held);
+        cache.release(held);
+        assert_eq!(cache.ref_count(held), 1);
+
+        assert_eq!(cache.unload_unused(), vec![unused]);
+        assert!(cache.get(held).is_some());
+        assert!(cache.get(unused).is_none());
+        assert!(cache
+            .get_handle_for_path(&AssetPath::new("unused.png"))
+            .is_none());
+
+        cache.release(held);
+        assert_eq!(cache.unload_unused(), vec![held]);
+        assert!(cache.unload_unused().is_empty());
+    }
+
+    #[test]
+    fn release_never_goes_below_zero() {
+        let mut cache = AssetCache::new();
+        let handle = cache.insert(Dummy(1), None);
+        cache.release(handle);
+        cache.acquire(handle);
+        assert_eq!(cache.ref_count(handle), 1);
+    }
+
+    #[test]
+    fn handles_are_not_reused_after_unloading() {
+        let mut cache = AssetCache::new();
+        let old = cache.insert(Dummy(1), Some(AssetPath::new("a.png")));
+        cache.unload_unused();
+        let new = cache.insert(Dummy(2), Some(AssetPath::new("a.png")));
+        assert_ne!(old, new);
+        assert!(cache.get(old).is_none());
+        assert_eq!(cache.get(new).unwrap().0, 2);
+    }
+}
@@ -0,0 +1,358 @@
+use crate::{Handle, ImageTexture};
+
+// Where a loaded sprite sheet lives on the GPU. Sheets packed into an atlas page share the
+// page's texture handle, standalone textures cover their whole texture.
+#[derive(Debug, Clone, Copy)]
+pub struct TextureRegion {
+    pub texture: Handle<ImageTexture>,
+    pub rect: [u32; 4], // x, y, w, h in texels of `texture`
+    pub texture_size: [u32; 2],
+}
+
+impl TextureRegion {
+    pub fn full(texture: Handle<ImageTexture>, texture_size: [u32; 2]) -> Self {
+        Self {
+            texture,
+            rect: [0, 0, texture_size[0], texture_size[1]],
+            texture_size,
+        }
+    }
+
+    // Size of the sheet itself, not the page it was packed into.
+    pub fn size(&self) -> [u32; 2] {
+        [self.rect[2], self.rect[3]]
+    }
+
+    // Maps a UV relative to the sheet into a UV on the backing texture.
+    pub fn remap_uv(&self, uv: [f32; 2]) -> [f32; 2] {
+        let [x, y, w, h] = self.rect.map(|v| v as f32);
+        [
+            (x + uv[0] * w) / self.texture_size[0] as f32,
+            (y + uv[1] * h) / self.texture_size[1] as f32,
+        ]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AtlasSlot {
+    pub page: usize,
+    pub x: u32,
+    pub y: u32,
+}
+
+#[derive(Debug)]
+struct Shelf {
+    y: u32,
+    height: u32,
+    next_x: u32,
+    free: Vec<[u32; 2]>, // x, w of gaps left by sheets that were freed, sorted by x
+}
+
+impl Shelf {
+    fn fits(&self, w: u32, page_size: u32) -> bool {
+        self.free.iter().any(|[_, free_w]| *free_w >= w) || page_size - self.next_x >= w
+    }
+
+    fn place(&mut self, w: u32) -> u32 {
+        // tightest gap first, the end of the shelf after that
+        let gap = (0..self.free.len())
+            .filter(|&i| self.free[i][1] >= w)
+            .min_by_key(|&i| self.free[i][1]);
+        if let Some(i) = gap {
+            let [x, free_w] = self.free[i];
+            if free_w == w {
+                self.free.remove(i);
+            } else {
+                self.free[i] = [x + w, free_w - w];
+            }
+            return x;
+        }
+        let x = self.next_x;
+        self.next_x += w;
+        x
+    }
+
+    fn free(&mut self, x: u32, w: u32) {
+        self.free.push([x, w]);
+        self.free.sort_by_key(|[x, _]| *x);
+        let mut merged: Vec<[u32; 2]> = Vec::new();
+        for [x, w] in self.free.drain(..) {
+            match merged.last_mut() {
+                Some(last) if last[0] + last[1] == x => last[1] += w,
+                _ => merged.push([x, w]),
+            }
+        }
+        // a gap at the end of the shelf is just more room at the end
+        if let Some(&[x, w]) = merged.last() {
+            if x + w == self.next_x {
+                merged.pop();
+                self.next_x = x;
+            }
+        }
+        self.free = merged;
+    }
+}
+
+#[derive(Debug, Default)]
+struct AtlasPage {
+    shelves: Vec<Shelf>,
+    next_y: u32,
+    live: usize, // sheets packed and not freed yet
+}
+
+// Shelf packer for square atlas pages. Sheets arrive one at a time as scripts load them,
+// so this packs online rather than sorting up front.
+#[derive(Debug)]
+pub struct AtlasPacker {
+    page_size: u32,
+    padding: u32,
+    pages: Vec<AtlasPage>,
+}
+
+impl AtlasPacker {
+    pub fn new(page_size: u32, padding: u32) -> Self {
+        Self {
+            page_size,
+            padding,
+            pages: Vec::new(),
+        }
+    }
+
+    pub fn page_size(&self) -> u32 {
+        self.page_size
+    }
+
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    // Returns None when the sheet can't fit even on an empty page.
+    pub fn pack(&mut self, width: u32, height: u32) -> Option<AtlasSlot> {
+        let w = width + self.padding;
+        let h = height + self.padding;
+        if w > self.page_size || h > self.page_size {
+            return None;
+        }
+
+        for (index, page) in self.pages.iter_mut().enumerate() {
+            if let Some((x, y)) = Self::pack_into_page(page, self.page_size, w, h) {
+                return Some(AtlasSlot { page: index, x, y });
+            }
+        }
+
+        let mut page = AtlasPage::default();
+        let (x, y) = Self::pack_into_page(&mut page, self.page_size, w, h)?;
+        self.pages.push(page);
+        Some(AtlasSlot {
+            page: self.pages.len() - 1,
+            x,
+            y,
+        })
+    }
+
+    // Gives a packed sheet's space back. Returns true when that was the last sheet on its page,
+    // which starts over empty.
+    pub fn free(&mut self, slot: AtlasSlot, width: u32, height: u32) -> bool {
+        let page = match self.pages.get_mut(slot.page) {
+            Some(page) => page,
+            None => return false,
+        };
+        let shelf = match page.shelves.iter_mut().find(|shelf| shelf.y == slot.y) {
+            Some(shelf) if shelf.height >= height + self.padding => shelf,
+            _ => return false,
+        };
+        shelf.free(slot.x, width + self.padding);
+
+        page.live = page.live.saturating_sub(1);
+        if page.live == 0 {
+            *page = AtlasPage::default();
+            return true;
+        }
+        // an empty shelf at the bottom goes back to the page
+        while page.shelves.last().is_some_and(|shelf| shelf.next_x == 0) {
+            page.next_y = page.shelves.pop().unwrap().y;
+        }
+        false
+    }
+
+    fn pack_into_page(page: &mut AtlasPage, page_size: u32, w: u32, h: u32) -> Option<(u32, u32)> {
+        // tightest existing shelf that still has room
+        let best = page
+            .shelves
+            .iter_mut()
+            .filter(|shelf| shelf.height >= h && shelf.fits(w, page_size))
+            .min_by_key(|shelf| shelf.height);
+
+        if let Some(shelf) = best {
+            page.live += 1;
+            return Some((shelf.place(w), shelf.y));
+        }
+
+        if page_size - page.next_y < h {
+            return None;
+        }
+
+        let y = page.next_y;
+        page.next_y += h;
+        page.shelves.push(Shelf {
+            y,
+            height: h,
+            next_x: w,
+            free: Vec::new(),
+        });
+        page.live += 1;
+        Some((0, y))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packs_along_a_shelf_then_starts_a_new_one() {
+        let mut atlas = AtlasPacker::new(64, 0);
+        assert_eq!(atlas.page_count(), 0);
+        let slots: Vec<AtlasSlot> = (0..5).map(|_| atlas.pack(16, 16).unwrap()).collect();
+        assert_eq!(
+            slots[3],
+            AtlasSlot {
+                page: 0,
+                x: 48,
+                y: 0
+            }
+        );
+        assert_eq!(
+            slots[4],
+            AtlasSlot {
+                page: 0,
+                x: 0,
+                y: 16
+            }
+        );
+        assert_eq!(atlas.page_count(), 1);
+    }
+
+    #[test]
+    fn picks_the_tightest_shelf_and_pads_sheets() {
+        let mut atlas = AtlasPacker::new(64, 2);
+        assert_eq!(
+            atlas.pack(60, 30).unwrap(),
+            AtlasSlot {
+                page: 0,
+                x: 0,
+                y: 0
+            }
+        );
+        assert_eq!(
+            atlas.pack(8, 6).unwrap(),
+            AtlasSlot {
+                page: 0,
+                x: 0,
+                y: 32
+            }
+        );
+        assert_eq!(
+            atlas.pack(8, 20).unwrap(),
+            AtlasSlot {
+                page: 0,
+                x: 0,
+                y: 40
+            }
+        );
+        // both shelves below the first have room, the short one wastes less
+        assert_eq!(
+            atlas.pack(8, 6).unwrap(),
+            AtlasSlot {
+                page: 0,
+                x: 10,
+                y: 32
+            }
+        );
+    }
+
+    #[test]
+    fn opens_a_page_when_full_and_rejects_oversized_sheets() {
+        let mut atlas = AtlasPacker::new(32, 0);
+        assert_eq!(atlas.pack(32, 32).unwrap().page, 0);
+        assert_eq!(atlas.pack(1, 1).unwrap().page, 1);
+        assert!(atlas.pack(33, 1).is_none());
+        // padding counts towards the page size
+        assert!(AtlasPacker::new(32, 2).pack(31, 8).is_none());
+    }
+
+    #[test]
+    fn freed_slots_are_reused() {
+        let mut atlas = AtlasPacker::new(64, 0);
+        let a = atlas.pack(16, 16).unwrap();
+        let b = atlas.pack(16, 16).unwrap();
+        let c = atlas.pack(16, 16).unwrap();
+        assert!(!atlas.free(b, 16, 16));
+        assert_eq!(atlas.pack(12, 8).unwrap(), b);
+
+        // freeing the end of a shelf gives the room back to it
+        assert!(!atlas.free(c, 16, 16));
+        assert_eq!(
+            atlas.pack(36, 16).unwrap(),
+            AtlasSlot {
+                page: 0,
+                x: 28,
+                y: 0
+            }
+        );
+        assert!(!atlas.free(a, 16, 16));
+    }
+
+    #[test]
+    fn freeing_the_last_sheet_empties_the_page() {
+        let mut atlas = AtlasPacker::new(64, 0);
+        let a = atlas.pack(16, 16).unwrap();
+        let b = atlas.pack(40, 40).unwrap();
+        assert!(!atlas.free(a, 16, 16));
+        assert!(atlas.free(b, 40, 40));
+        assert_eq!(
+            atlas.pack(64, 64).unwrap(),
+            AtlasSlot {
+                page: 0,
+                x: 0,
+                y: 0
+            }
+        );
+    }
+
+    #[test]
+    fn freeing_on_an_empty_or_unknown_page_does_nothing() {
+        let mut atlas = AtlasPacker::new(64, 0);
+        assert!(!atlas.free(
+            AtlasSlot {
+                page: 0,
+                x: 0,
+                y: 0
+            },
+            16,
+            16
+        ));
+        let slot = atlas.pack(16, 16).unwrap();
+        assert!(!atlas.free(AtlasSlot { page: 3, ..slot }, 16, 16));
+        assert!(!atlas.free(AtlasSlot { y: 48, ..slot }, 16, 16));
+        assert!(atlas.free(slot, 16, 16));
+        assert!(!atlas.free(slot, 16, 16));
+    }
+
+    #[test]
+    fn remaps_uvs_into_the_page() {
+        let region = TextureRegion {
+            texture: Handle::new(0),
+            rect: [64, 32, 32, 16],
+            texture_size: [128, 64],
+        };
+        assert_eq!(region.size(), [32, 16]);
+        assert_eq!(region.remap_uv([0.0, 0.0]), [0.5, 0.5]);
+        assert_eq!(region.remap_uv([1.0, 1.0]), [0.75, 0.75]);
+        assert_eq!(region.remap_uv([0.5, 0.5]), [0.625, 0.625]);
+
+        let full = TextureRegion::full(Handle::new(0), [16, 8]);
+        assert_eq!(full.rect, [0, 0, 16, 8]);
+        assert_eq!(full.remap_uv([1.0, 0.5]), [1.0, 0.5]);
+    }
+}
@@ -0,0 +1,235 @@
+use anyhow::{anyhow, Result};
+use image::DynamicImage;
+use serde_json::Value;
+
+use crate::asset::Asset;
+use crate::loader::decode_image;
+use crate::server::{AssetLoader, LoadContext};
+
+// Decoded pixels, flipped to match the UV convention the renderer uses. The GPU side lives in
+// ImageTexture, which the renderer creates from these.
+impl Asset for DynamicImage {}
+
+pub struct ImageAssetLoader;
+
+impl AssetLoader for ImageAssetLoader {
+    type Asset = DynamicImage;
+
+    fn extensions(&self) -> &[&str] {
+        &["png", "jpg", "jpeg"]
+    }
+
+    fn load(&self, bytes: Vec<u8>, _context: &mut LoadContext) -> Result<DynamicImage> {
+        decode_image(&bytes)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SpriteSheetFrame {
+    pub name: String,   // its key in a hash export, its filename in an array export
+    pub rect: [u32; 4], // x, y, w, h in the sheet
+    pub source_size: [u32; 2], // the frame before Aseprite trimmed it
+    pub trim: [u32; 2], // where `rect` sits in the untrimmed frame
+    pub duration_ms: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct SpriteSheetTag {
+    pub name: String,
+    pub from: usize,
+    pub to: usize,
+    pub direction: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct SpriteSheetSliceKey {
+    pub frame: u32,
+    pub bounds: [u32; 4],
+    pub pivot: Option<[i32; 2]>, // relative to the bounds
+}
+
+#[derive(Debug, Clone)]
+pub struct SpriteSheetSlice {
+    pub name: String,
+    pub data: Option<String>, // the slice's user data
+    pub keys: Vec<SpriteSheetSliceKey>,
+}
+
+// Frame layout from an Aseprite json export. The sheet's image is a dependency, so it gets
+// reloaded along with the png.
+#[derive(Debug, Clone)]
+pub struct SpriteSheet {
+    pub image: String,
+    pub size: [u32; 2],
+    pub frames: Vec<SpriteSheetFrame>,
+    pub tags: Vec<SpriteSheetTag>,
+    pub slices: Vec<SpriteSheetSlice>,
+}
+
+impl Asset for SpriteSheet {}
+
+pub struct SpriteSheetLoader;
+
+impl AssetLoader for SpriteSheetLoader {
+    type Asset = SpriteSheet;
+
+    fn extensions(&self) -> &[&str] {
+        &["json"]
+    }
+
+    fn load(&self, bytes: Vec<u8>, context: &mut LoadContext) -> Result<SpriteSheet> {
+        let data: Value = serde_json::from_slice(&bytes)?;
+        let meta = &data["meta"];
+
+        let image = meta["image"]
+            .as_str()
+            .ok_or_else(|| anyhow!("missing meta.image"))?;
+        let image = context.resolve(image);
+        context.add_dependency(&image);
+
+        // Aseprite exports frames either as an array or as a map keyed by frame name
+        let frames: Vec<(String, &Value)> = match &data["frames"] {
+            Value::Array(frames) => frames
+                .iter()
+                .map(|frame| (json_str(&frame["filename"]), frame))
+                .collect(),
+            Value::Object(frames) => frames
+                .iter()
+                .map(|(name, frame)| (name.clone(), frame))
+                .collect(),
+            _ => return Err(anyhow!("missing frames")),
+        };
+        let frames = frames
+            .into_iter()
+            .map(|(name, frame)| {
+                let rect = json_rect(&frame["frame"]);
+                let size = &frame["sourceSize"];
+                let source_size = if size.is_object() {
+                    [json_u32(&size["w"]), json_u32(&size["h"])]
+                } else {
+                    [rect[2], rect[3]]
+                };
+                SpriteSheetFrame {
+                    name,
+                    rect,
+                    source_size,
+                    trim: [
+                        json_u32(&frame["spriteSourceSize"]["x"]),
+                        json_u32(&frame["spriteSourceSize"]["y"]),
+                    ],
+                    duration_ms: frame["duration"].as_u64().unwrap_or(100) as u32,
+                }
+            })
+            .collect();
+
+        let tags = meta["frameTags"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|tag| SpriteSheetTag {
+                name: json_str(&tag["name"]),
+                from: json_u32(&tag["from"]) as usize,
+                to: json_u32(&tag["to"]) as usize,
+                direction: tag["direction"].as_str().unwrap_or("forward").to_string(),
+            })
+            .collect();
+
+        let slices = meta["slices"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|slice| SpriteSheetSlice {
+                name: json_str(&slice["name"]),
+                data: slice["data"].as_str().map(str::to_string),
+                keys: slice["keys"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .map(|key| {
+                        let pivot = &key["pivot"];
+                        SpriteSheetSliceKey {
+                            frame: json_u32(&key["frame"]),
+                            bounds: json_rect(&key["bounds"]),
+                            pivot: pivot
+                                .is_object()
+                                .then(|| [json_i32(&pivot["x"]), json_i32(&pivot["y"])]),
+                        }
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        Ok(SpriteSheet {
+            image,
+            size: [json_u32(&meta["size"]["w"]), json_u32(&meta["size"]["h"])],
+            frames,
+            tags,
+            slices,
+        })
+    }
+}
+
+fn json_u32(value: &Value) -> u32 {
+    value.as_u64().unwrap_or(0) as u32
+}
+
+fn json_i32(value: &Value) -> i32 {
+    value.as_i64().unwrap_or(0) as i32
+}
+
+fn json_str(value: &Value) -> String {
+    value.as_str().unwrap_or_default().to_string()
+}
+
+fn json_rect(value: &Value) -> [u32; 4] {
+    [
+        json_u32(&value["x"]),
+        json_u32(&value["y"]),
+        json_u32(&value["w"]),
+        json_u32(&value["h"]),
+    ]
+}
+
+// Raw font file bytes, parsed by whatever draws text.
+pub struct Font {
+    pub data: Vec<u8>,
+}
+
+impl Asset for Font {}
+
+pub struct FontLoader;
+
+impl AssetLoader for FontLoader {
+    type Asset = Font;
+
+    fn extensions(&self) -> &[&str] {
+        &["ttf", "otf"]
+    }
+
+    fn load(&self, bytes: Vec<u8>, _context: &mut LoadContext) -> Result<Font> {
+        Ok(Font { data: bytes })
+    }
+}
+
+// Lua source for data files (levels, tuning tables) read outside of require.
+pub struct LuaData {
+    pub source: String,
+}
+
+impl Asset for LuaData {}
+
+pub struct LuaDataLoader;
+
+impl AssetLoader for LuaDataLoader {
+    type Asset = LuaData;
+
+    fn extensions(&self) -> &[&str] {
+        &["lua"]
+    }
+
+    fn load(&self, bytes: Vec<u8>, _context: &mut LoadContext) -> Result<LuaData> {
+        Ok(LuaData {
+            source: String::from_utf8(bytes)?,
+        })
+    }
+}
@@ -47,7 +47,7 @@ impl ImageBindGroup {
 
 #[derive(Debug)]
 pub struct ImageTexture {
-    _texture: Texture, // store this so child references stay alive
+    texture: Texture, // store this so child references stay alive
     sampler: Sampler,
     view: TextureView,
 }
@@ -63,37 +63,123 @@ impl ImageTexture {
         &self.view
     }
 
+    /// Pixel dimensions of the uploaded texture as `[width, height]`.
+    pub fn size(&self) -> [u32; 2] {
+        [self.texture.width(), self.texture.height()]
+    }
+
     pub fn from_image(
         device: &Device,
         queue: &Queue,
         img: &image::DynamicImage,
         label: Option<&str>,
     ) -> Result<Self> {
-        let rgba = img.to_rgba8();
         let dimensions = img.dimensions();
+        let texture = Self::empty(device, [dimensions.0, dimensions.1], label);
+        texture.write_image(queue, img, [0, 0]);
+        Ok(texture)
+    }
 
-        let size = Extent3d {
-            width: dimensions.0,
-            height: dimensions.1,
-            depth_or_array_layers: 1,
-        };
-        let texture = device.create_texture(&TextureDescriptor {
+    // Blank (transparent) texture, used for atlas pages that sheets get written into.
+    pub fn empty(device: &Device, size: [u32; 2], label: Option<&str>) -> Self {
+        Self::create(
+            device,
+            size,
             label,
+            AddressMode::ClampToEdge,
+            TextureFormat::Rgba8UnormSrgb,
+        )
+    }
+
+    // Blank texture holding data rather than colour, like normal maps, so it's sampled as is.
+    pub fn empty_linear(device: &Device, size: [u32; 2], label: Option<&str>) -> Self {
+        Self::create(
+            device,
             size,
+            label,
+            AddressMode::ClampToEdge,
+            TextureFormat::Rgba8Unorm,
+        )
+    }
+
+    // Magenta checkerboard drawn in place of textures that failed to load. It repeats, so
+    // frames cut from a sheet of any size still come out checkered.
+    pub fn missing(device: &Device, queue: &Queue) -> Self {
+        const SIZE: u32 = 16;
+        const CELL: u32 = 8;
+        let img = image::RgbaImage::from_fn(SIZE, SIZE, |x, y| {
+            if (x / CELL + y / CELL).is_multiple_of(2) {
+                image::Rgba([255, 0, 255, 255])
+            } else {
+                image::Rgba([0, 0, 0, 255])
+            }
+        });
+
+        let texture = Self::create(
+            device,
+            [SIZE, SIZE],
+            Some("Missing Texture"),
+            AddressMode::Repeat,
+            TextureFormat::Rgba8UnormSrgb,
+        );
+        texture.write_image(queue, &image::DynamicImage::ImageRgba8(img), [0, 0]);
+        texture
+    }
+
+    fn create(
+        device: &Device,
+        size: [u32; 2],
+        label: Option<&str>,
+        address_mode: AddressMode,
+        format: TextureFormat,
+    ) -> Self {
+        let texture = device.create_texture(&TextureDescriptor {
+            label,
+            size: Extent3d {
+                width: size[0],
+                height: size[1],
+                depth_or_array_layers: 1,
+            },
             mip_level_count: 1,
             sample_count: 1,
             dimension: TextureDimension::D2,
-            format: TextureFormat::Rgba8UnormSrgb,
+            format,
             usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
             view_formats: &[],
         });
 
+        let view = texture.create_view(&TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            address_mode_u: address_mode,
+            address_mode_v: address_mode,
+            address_mode_w: address_mode,
+            mag_filter: FilterMode::Nearest,
+            min_filter: FilterMode::Nearest,
+            mipmap_filter: FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        Self {
+            texture,
+            view,
+            sampler,
+        }
+    }
+
+    pub fn write_image(&self, queue: &Queue, img: &image::DynamicImage, origin: [u32; 2]) {
+        let rgba = img.to_rgba8();
+        let dimensions = img.dimensions();
+
         queue.write_texture(
             TexelCopyTextureInfo {
                 aspect: TextureAspect::All,
-                texture: &texture,
+                texture: &self.texture,
                 mip_level: 0,
-                origin: Origin3d::ZERO,
+                origin: Origin3d {
+                    x: origin[0],
+                    y: origin[1],
+                    z: 0,
+                },
             },
             &rgba,
             TexelCopyBufferLayout {
@@ -101,24 +187,11 @@ impl ImageTexture {
                 bytes_per_row: Some(4 * dimensions.0),
                 rows_per_image: Some(dimensions.1),
             },
-            size,
+            Extent3d {
+                width: dimensions.0,
+                height: dimensions.1,
+                depth_or_array_layers: 1,
+            },
         );
-
-        let view = texture.create_view(&TextureViewDescriptor::default());
-        let sampler = device.create_sampler(&SamplerDescriptor {
-            address_mode_u: AddressMode::ClampToEdge,
-            address_mode_v: AddressMode::ClampToEdge,
-            address_mode_w: AddressMode::ClampToEdge,
-            mag_filter: FilterMode::Nearest,
-            min_filter: FilterMode::Nearest,
-            mipmap_filter: FilterMode::Nearest,
-            ..Default::default()
-        });
-
-        Ok(Self {
-            _texture: texture,
-            view,
-            sampler,
-        })
     }
 }
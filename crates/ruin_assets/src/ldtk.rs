@@ -0,0 +1,335 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+
+use crate::level::{
+    add_object, is_collision_layer, Level, LevelFile, LevelObject, LevelTileLayer, LevelTileset,
+    Properties, PropertyValue,
+};
+use crate::server::{AssetLoader, LoadContext};
+
+// LDtk projects. Every level in the project is loaded, levels saved as separate files are
+// read as dependencies of the project.
+pub struct LdtkLoader;
+
+impl AssetLoader for LdtkLoader {
+    type Asset = LevelFile;
+
+    fn extensions(&self) -> &[&str] {
+        &["ldtk"]
+    }
+
+    fn load(&self, bytes: Vec<u8>, context: &mut LoadContext) -> Result<LevelFile> {
+        let project: Value = serde_json::from_slice(&bytes)?;
+        let grid_size = json_u32(&project["defaultGridSize"]);
+
+        // tile ids are made global over all tilesets, like Tiled does
+        let mut tilesets = Vec::new();
+        let mut tileset_uids = HashMap::new();
+        let mut next_id = 1;
+        for tileset in project["defs"]["tilesets"].as_array().into_iter().flatten() {
+            let image = match tileset["relPath"].as_str() {
+                Some(path) => context.resolve(path),
+                None => continue, // embedded LDtk icons
+            };
+            let tile_count = json_u32(&tileset["__cWid"]) * json_u32(&tileset["__cHei"]);
+            tileset_uids.insert(json_u32(&tileset["uid"]), tilesets.len());
+            tilesets.push(LevelTileset {
+                image,
+                first_id: next_id,
+                tile_count,
+                tile_size: [json_u32(&tileset["tileGridSize"]); 2],
+                margin: json_u32(&tileset["padding"]),
+                spacing: json_u32(&tileset["spacing"]),
+            });
+            next_id += tile_count;
+        }
+
+        let mut levels = Vec::new();
+        for level in project["levels"].as_array().into_iter().flatten() {
+            // projects can save each level to a file of its own
+            let external;
+            let level = match level["externalRelPath"].as_str() {
+                Some(path) if level["layerInstances"].is_null() => {
+                    let path = context.resolve(path);
+                    external = serde_json::from_slice::<Value>(&context.read(&path)?)?;
+                    &external
+                }
+                _ => level,
+            };
+            levels.push(parse_level(level, grid_size, &tilesets, &tileset_uids)?);
+        }
+        Ok(LevelFile { levels })
+    }
+}
+
+fn parse_level(
+    data: &Value,
+    grid_size: u32,
+    tilesets: &[LevelTileset],
+    tileset_uids: &HashMap<u32, usize>,
+) -> Result<Level> {
+    let mut level = Level {
+        name: data["identifier"].as_str().unwrap_or_default().to_string(),
+        size: [json_u32(&data["pxWid"]), json_u32(&data["pxHei"])],
+        tile_size: [grid_size; 2],
+        tilesets: tilesets.to_vec(),
+        tile_layers: Vec::new(),
+        objects: Vec::new(),
+        colliders: Vec::new(),
+    };
+
+    // LDtk lists the top layer first
+    let layers = data["layerInstances"]
+        .as_array()
+        .ok_or_else(|| anyhow!("level {} has no layers", level.name))?;
+    for layer in layers.iter().rev() {
+        let name = layer["__identifier"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string();
+        let offset = [
+            json_f32(&layer["__pxTotalOffsetX"]),
+            json_f32(&layer["__pxTotalOffsetY"]),
+        ];
+        let visible = layer["visible"].as_bool().unwrap_or(true);
+        let width = json_u32(&layer["__cWid"]);
+        let height = json_u32(&layer["__cHei"]);
+        let cell_size = json_u32(&layer["__gridSize"]).max(1);
+        let collision = is_collision_layer(&name, &Properties::new());
+
+        let new_layer = |tiles: Vec<u32>, visible: bool, collision: bool| LevelTileLayer {
+            name: name.clone(),
+            width,
+            height,
+            tiles,
+            offset,
+            visible,
+            collision,
+            properties: Properties::new(),
+        };
+
+        // IntGrid layers are only solid on collision layers, their auto tiles are drawn either way
+        if layer["__type"] == "IntGrid" && collision {
+            let tiles: Vec<u32> = layer["intGridCsv"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .map(json_u32)
+                .collect();
+            if tiles.len() == (width * height) as usize {
+                level.tile_layers.push(new_layer(tiles, false, true));
+            }
+        }
+
+        let first_id = layer["__tilesetDefUid"]
+            .as_u64()
+            .and_then(|uid| tileset_uids.get(&(uid as u32)))
+            .map(|index| tilesets[*index].first_id);
+        if let Some(first_id) = first_id {
+            let mut tiles = vec![0; (width * height) as usize];
+            let placed = layer["gridTiles"]
+                .as_array()
+                .into_iter()
+                .chain(layer["autoLayerTiles"].as_array())
+                .flatten();
+            for tile in placed {
+                let x = json_u32(&tile["px"][0]) / cell_size;
+                let y = json_u32(&tile["px"][1]) / cell_size;
+                if x < width && y < height {
+                    tiles[(y * width + x) as usize] = first_id + json_u32(&tile["t"]);
+                }
+            }
+            if tiles.iter().any(|tile| *tile != 0) {
+                level.tile_layers.push(new_layer(
+                    tiles,
+                    visible,
+                    collision && layer["__type"] == "Tiles",
+                ));
+            }
+        }
+
+        for entity in layer["entityInstances"].as_array().into_iter().flatten() {
+            let size = [json_f32(&entity["width"]), json_f32(&entity["height"])];
+            // px is the entity's pivot
+            let position = [
+                json_f32(&entity["px"][0]) - json_f32(&entity["__pivot"][0]) * size[0] + offset[0],
+                json_f32(&entity["px"][1]) - json_f32(&entity["__pivot"][1]) * size[1] + offset[1],
+            ];
+            add_object(
+                &mut level,
+                &name,
+                collision,
+                LevelObject {
+                    name: entity["iid"].as_str().unwrap_or_default().to_string(),
+                    kind: entity["__identifier"]
+                        .as_str()
+                        .unwrap_or_default()
+                        .to_string(),
+                    layer: String::new(),
+                    position,
+                    size,
+                    properties: field_instances(&entity["fieldInstances"]),
+                },
+            );
+        }
+    }
+    Ok(level)
+}
+
+fn field_instances(fields: &Value) -> Properties {
+    fields
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|field| {
+            let name = field["__identifier"].as_str()?.to_string();
+            let value = match &field["__value"] {
+                Value::Null => return None,
+                Value::Bool(value) => PropertyValue::Bool(*value),
+                Value::Number(value) => match value.as_i64() {
+                    Some(value) if field["__type"] == "Int" => PropertyValue::Int(value),
+                    _ => PropertyValue::Float(value.as_f64().unwrap_or(0.0)),
+                },
+                Value::String(value) => PropertyValue::String(value.clone()),
+                other => PropertyValue::String(other.to_string()),
+            };
+            Some((name, value))
+        })
+        .collect()
+}
+
+fn json_u32(value: &Value) -> u32 {
+    value.as_u64().unwrap_or(0) as u32
+}
+
+fn json_f32(value: &Value) -> f32 {
+    value.as_f64().unwrap_or(0.0) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::server::AssetServer;
+    use crate::source::MemorySource;
+
+    const PROJECT: &str = r#"{
+        "defaultGridSize": 8,
+        "defs": { "tilesets": [
+            { "uid": 10, "identifier": "Icons", "relPath": null },
+            { "uid": 1, "relPath": "tiles/cave.png", "__cWid": 4, "__cHei": 2,
+              "tileGridSize": 8, "padding": 1, "spacing": 2 },
+            { "uid": 2, "relPath": "../shared/props.png", "__cWid": 2, "__cHei": 2,
+              "tileGridSize": 8 }
+        ] },
+        "levels": [
+            { "identifier": "Cave", "pxWid": 16, "pxHei": 16, "layerInstances": [
+                { "__identifier": "Entities", "__type": "Entities", "__cWid": 2, "__cHei": 2,
+                  "__gridSize": 8, "__pxTotalOffsetX": 2, "__pxTotalOffsetY": 0,
+                  "entityInstances": [
+                    { "iid": "a1", "__identifier": "Player", "px": [8, 16], "__pivot": [0.5, 1],
+                      "width": 8, "height": 16, "fieldInstances": [
+                        { "__identifier": "hp", "__type": "Int", "__value": 3 },
+                        { "__identifier": "speed", "__type": "Float", "__value": 2 },
+                        { "__identifier": "boss", "__type": "Bool", "__value": false },
+                        { "__identifier": "item", "__type": "String", "__value": null }
+                    ] }
+                ] },
+                { "__identifier": "Props", "__type": "Tiles", "__cWid": 2, "__cHei": 2,
+                  "__gridSize": 8, "__tilesetDefUid": 2, "visible": false,
+                  "gridTiles": [ { "px": [8, 0], "t": 3, "f": 1 }, { "px": [64, 64], "t": 0 } ] },
+                { "__identifier": "Collision", "__type": "IntGrid", "__cWid": 2, "__cHei": 2,
+                  "__gridSize": 8, "__tilesetDefUid": 1, "intGridCsv": [1, 0, 0, 2],
+                  "autoLayerTiles": [ { "px": [0, 0], "t": 5, "f": 3 } ] }
+            ] },
+            { "identifier": "Shop", "externalRelPath": "levels/shop.ldtkl",
+              "layerInstances": null }
+        ]
+    }"#;
+
+    const SHOP: &str = r#"{ "identifier": "Shop", "pxWid": 8, "pxHei": 8, "layerInstances": [] }"#;
+
+    fn load(files: &[(&str, &str)]) -> Result<LevelFile> {
+        let mut server = AssetServer::new(Arc::new(MemorySource::new(files)));
+        let handle = server.load::<LevelFile>("maps/world.ldtk")?;
+        Ok(server.get(handle).unwrap().clone())
+    }
+
+    #[test]
+    fn loads_every_level() {
+        let file = load(&[
+            ("maps/world.ldtk", PROJECT),
+            ("maps/levels/shop.ldtkl", SHOP),
+        ])
+        .unwrap();
+        assert_eq!(file.levels.len(), 2);
+        assert_eq!(file.level(Some("Shop")).unwrap().size, [8, 8]);
+        assert_eq!(file.level(None).unwrap().name, "Cave");
+        assert!(file.level(Some("Attic")).is_err());
+    }
+
+    #[test]
+    fn tile_ids_are_global_over_tilesets() {
+        let file = load(&[
+            ("maps/world.ldtk", PROJECT),
+            ("maps/levels/shop.ldtkl", SHOP),
+        ])
+        .unwrap();
+        let level = &file.levels[0];
+        // the embedded icons are skipped
+        assert_eq!(level.tilesets.len(), 2);
+        assert_eq!(level.tilesets[0].image, "maps/tiles/cave.png");
+        assert_eq!(level.tilesets[0].margin, 1);
+        assert_eq!(level.tilesets[1].image, "shared/props.png");
+        assert_eq!(level.tilesets[1].first_id, 9);
+        assert_eq!(level.tileset_for(12), Some((1, 4)));
+        assert_eq!(level.tileset_for(13), None);
+
+        // bottom layer first: the IntGrid's collision, its auto tiles, then the props
+        let names: Vec<&str> = level.tile_layers.iter().map(|l| l.name.as_str()).collect();
+        assert_eq!(names, ["Collision", "Collision", "Props"]);
+        let solid = &level.tile_layers[0];
+        assert_eq!(solid.tiles, [1, 0, 0, 2]);
+        assert!(solid.collision && !solid.visible);
+        let auto_tiles = &level.tile_layers[1];
+        assert_eq!(auto_tiles.tiles, [6, 0, 0, 0]);
+        assert!(!auto_tiles.collision && auto_tiles.visible);
+        // flip flags don't change the id, tiles outside the layer are dropped
+        let props = &level.tile_layers[2];
+        assert_eq!(props.tiles, [0, 12, 0, 0]);
+        assert!(!props.visible);
+    }
+
+    #[test]
+    fn entities_are_placed_by_their_pivot() {
+        let file = load(&[
+            ("maps/world.ldtk", PROJECT),
+            ("maps/levels/shop.ldtkl", SHOP),
+        ])
+        .unwrap();
+        let player = &file.levels[0].objects[0];
+        assert_eq!(
+            (player.name.as_str(), player.kind.as_str()),
+            ("a1", "Player")
+        );
+        assert_eq!(player.layer, "Entities");
+        assert_eq!(player.position, [6.0, 0.0]);
+        assert_eq!(player.size, [8.0, 16.0]);
+        assert_eq!(
+            player.properties,
+            [
+                ("hp".to_string(), PropertyValue::Int(3)),
+                ("speed".to_string(), PropertyValue::Float(2.0)),
+                ("boss".to_string(), PropertyValue::Bool(false)),
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_external_level_fails() {
+        assert!(load(&[("maps/world.ldtk", PROJECT)]).is_err());
+    }
+}
@@ -0,0 +1,134 @@
+use anyhow::{anyhow, Result};
+
+use crate::asset::Asset;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PropertyValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+}
+
+pub type Properties = Vec<(String, PropertyValue)>;
+
+#[derive(Debug, Clone)]
+pub struct LevelTileset {
+    pub image: String, // asset id of the tileset image
+    // global id of the first tile, layers index every tileset of the level through one range
+    pub first_id: u32,
+    pub tile_count: u32,
+    pub tile_size: [u32; 2],
+    pub margin: u32,
+    pub spacing: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct LevelTileLayer {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    pub tiles: Vec<u32>, // row major, top row first, 0 is empty
+    // in pixels, relative to the level's top-left corner
+    pub offset: [f32; 2],
+    pub visible: bool,
+    // solid wherever a tile is set
+    pub collision: bool,
+    pub properties: Properties,
+}
+
+// Something placed in an object or entity layer. Position and size are in pixels with the
+// origin at the level's top-left corner and y pointing down, like the editors show them.
+#[derive(Debug, Clone)]
+pub struct LevelObject {
+    pub name: String,
+    pub kind: String,
+    pub layer: String,
+    pub position: [f32; 2], // top-left corner
+    pub size: [f32; 2],
+    pub properties: Properties,
+}
+
+#[derive(Debug, Clone)]
+pub struct Level {
+    pub name: String,
+    pub size: [u32; 2], // in pixels
+    pub tile_size: [u32; 2],
+    pub tilesets: Vec<LevelTileset>,
+    pub tile_layers: Vec<LevelTileLayer>, // bottom layer first
+    pub objects: Vec<LevelObject>,
+    // rectangles from collision object layers, x, y, w, h in pixels
+    pub colliders: Vec<[f32; 4]>,
+}
+
+// Every level in a Tiled map (always one) or an LDtk project.
+#[derive(Debug, Clone)]
+pub struct LevelFile {
+    pub levels: Vec<Level>,
+}
+
+impl Asset for LevelFile {}
+
+impl LevelFile {
+    // The level called `name`, or the first one.
+    pub fn level(&self, name: Option<&str>) -> Result<&Level> {
+        match name {
+            Some(name) => self
+                .levels
+                .iter()
+                .find(|level| level.name == name)
+                .ok_or_else(|| anyhow!("no level named {}", name)),
+            None => self.levels.first().ok_or_else(|| anyhow!("no levels")),
+        }
+    }
+}
+
+impl Level {
+    // Tileset a global tile id belongs to, with the id made 1-based within it.
+    pub fn tileset_for(&self, id: u32) -> Option<(usize, u32)> {
+        if id == 0 {
+            return None;
+        }
+        self.tilesets
+            .iter()
+            .enumerate()
+            .rev()
+            .find(|(_, tileset)| id >= tileset.first_id)
+            .filter(|(_, tileset)| id - tileset.first_id < tileset.tile_count)
+            .map(|(index, tileset)| (index, id - tileset.first_id + 1))
+    }
+}
+
+impl LevelTileLayer {
+    pub fn tile(&self, x: u32, y: u32) -> u32 {
+        if x >= self.width || y >= self.height {
+            return 0;
+        }
+        self.tiles[(y * self.width + x) as usize]
+    }
+}
+
+// Layers and object layers count as collision when named "collision" or given a true
+// "collision" property.
+pub(crate) fn is_collision_layer(name: &str, properties: &Properties) -> bool {
+    name.eq_ignore_ascii_case("collision")
+        || properties
+            .iter()
+            .any(|(key, value)| key == "collision" && *value == PropertyValue::Bool(true))
+}
+
+// Objects on collision layers only keep their rectangle.
+pub(crate) fn add_object(level: &mut Level, layer: &str, collision: bool, object: LevelObject) {
+    if collision {
+        let [x, y] = object.position;
+        let [w, h] = object.size;
+        if w > 0.0 && h > 0.0 {
+            level.colliders.push([x, y, w, h]);
+        }
+    } else {
+        level.objects.push(LevelObject {
+            layer: layer.to_string(),
+            ..object
+        });
+    }
+}
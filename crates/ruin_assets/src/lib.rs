@@ -1,9 +1,34 @@
+mod archive;
 mod asset;
 mod assets;
+mod atlas;
+mod formats;
 mod handle;
 mod image;
+mod ldtk;
+mod level;
+mod loader;
+mod server;
+mod source;
+mod tiled;
+mod watcher;
 
-pub use crate::asset::{Asset, AssetPath};
+pub use crate::archive::{ArchiveSource, ArchiveWriter, Compression};
+pub use crate::asset::{normalize_asset_id, Asset, AssetPath};
 pub use crate::assets::{AssetCache, AssetData};
+pub use crate::atlas::{AtlasPacker, AtlasSlot, TextureRegion};
+pub use crate::formats::{
+    Font, FontLoader, ImageAssetLoader, LuaData, LuaDataLoader, SpriteSheet, SpriteSheetFrame,
+    SpriteSheetLoader, SpriteSheetSlice, SpriteSheetSliceKey, SpriteSheetTag,
+};
 pub use crate::handle::{Handle, Index};
 pub use crate::image::{ImageBindGroup, ImageTexture};
+pub use crate::ldtk::LdtkLoader;
+pub use crate::level::{
+    Level, LevelFile, LevelObject, LevelTileLayer, LevelTileset, Properties, PropertyValue,
+};
+pub use crate::loader::load_image;
+pub use crate::server::{AssetLoader, AssetServer, LoadContext};
+pub use crate::source::{open_default_source, AssetSource, DirectorySource, DEFAULT_ARCHIVE};
+pub use crate::tiled::TiledLoader;
+pub use crate::watcher::AssetWatcher;
@@ -0,0 +1,119 @@
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use anyhow::Result;
+use image::DynamicImage;
+
+use crate::AssetSource;
+
+const MAX_WORKERS: usize = 4;
+
+pub(crate) type Job<R> = Box<dyn FnOnce() -> R + Send>;
+
+// Runs load jobs on worker threads. Whatever needs the main thread (uploading, inserting into
+// caches) happens on the results, handed back to whoever polls, usually once per frame.
+pub(crate) struct BackgroundLoader<R: Send + 'static> {
+    jobs: Option<Sender<Job<R>>>,
+    results: Sender<R>,
+    finished: Receiver<R>,
+    workers: Vec<thread::JoinHandle<()>>,
+    in_flight: usize,
+    requested: usize,
+    completed: usize,
+}
+
+impl<R: Send + 'static> BackgroundLoader<R> {
+    pub fn new() -> Self {
+        let (results, finished) = channel();
+        Self {
+            jobs: None,
+            results,
+            finished,
+            workers: Vec::new(),
+            in_flight: 0,
+            requested: 0,
+            completed: 0,
+        }
+    }
+
+    // Workers are started with the first job, so servers that never load in the background
+    // don't hold any threads.
+    fn start_workers(&mut self) -> &Sender<Job<R>> {
+        if self.jobs.is_none() {
+            let (jobs, job_receiver) = channel::<Job<R>>();
+            let job_receiver = Arc::new(Mutex::new(job_receiver));
+            let worker_count = thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1)
+                .min(MAX_WORKERS);
+            self.workers = (0..worker_count)
+                .map(|i| {
+                    let job_receiver = Arc::clone(&job_receiver);
+                    let results = self.results.clone();
+                    thread::Builder::new()
+                        .name(format!("ruin asset loader {}", i))
+                        .spawn(move || Self::work(job_receiver, results))
+                        .expect("Failed to spawn asset loader thread")
+                })
+                .collect();
+            self.jobs = Some(jobs);
+        }
+        self.jobs.as_ref().unwrap()
+    }
+
+    fn work(jobs: Arc<Mutex<Receiver<Job<R>>>>, results: Sender<R>) {
+        loop {
+            // only hold the lock while waiting for the next job, not while running it
+            let job = jobs.lock().unwrap().recv();
+            let job = match job {
+                Ok(job) => job,
+                Err(_) => return, // loader dropped
+            };
+            if results.send(job()).is_err() {
+                return;
+            }
+        }
+    }
+
+    pub fn request(&mut self, job: Job<R>) {
+        // progress covers the current batch, starting over once everything has landed
+        if self.in_flight == 0 {
+            self.requested = 0;
+            self.completed = 0;
+        }
+        self.in_flight += 1;
+        self.requested += 1;
+        let _ = self.start_workers().send(job);
+    }
+
+    pub fn poll_finished(&mut self) -> Vec<R> {
+        let finished: Vec<R> = self.finished.try_iter().collect();
+        self.in_flight -= finished.len();
+        self.completed += finished.len();
+        finished
+    }
+
+    // (completed, requested) for the current batch
+    pub fn progress(&self) -> (usize, usize) {
+        (self.completed, self.requested)
+    }
+}
+
+impl<R: Send + 'static> Drop for BackgroundLoader<R> {
+    fn drop(&mut self) {
+        self.jobs.take();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+// Decodes an image from the source, flipped to match the UV convention the renderer uses.
+pub fn load_image(source: &dyn AssetSource, id: &str) -> Result<DynamicImage> {
+    decode_image(&source.read(id)?)
+}
+
+pub(crate) fn decode_image(bytes: &[u8]) -> Result<DynamicImage> {
+    Ok(image::load_from_memory(bytes)?.flipv())
+}
@@ -0,0 +1,425 @@
+use std::any::{type_name, Any, TypeId};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail, Result};
+
+use crate::asset::{normalize_asset_id, Asset, AssetPath};
+use crate::assets::AssetCache;
+use crate::formats::{FontLoader, ImageAssetLoader, LuaDataLoader, SpriteSheetLoader};
+use crate::handle::Handle;
+use crate::ldtk::LdtkLoader;
+use crate::loader::BackgroundLoader;
+use crate::source::AssetSource;
+use crate::tiled::TiledLoader;
+
+// Turns the bytes of one file into an asset. Loaders are picked by file extension, so other
+// crates (audio, tilemaps) plug their formats in with AssetServer::register_loader.
+pub trait AssetLoader: Send + Sync + 'static {
+    type Asset: Asset;
+
+    // lowercase, without the dot
+    fn extensions(&self) -> &[&str];
+
+    fn load(&self, bytes: Vec<u8>, context: &mut LoadContext) -> Result<Self::Asset>;
+}
+
+// What a loader can see while loading `id`. Anything it reads or names through the context is
+// recorded as a dependency, so a change to that file also reaches this asset.
+pub struct LoadContext<'a> {
+    id: &'a str,
+    source: &'a dyn AssetSource,
+    dependencies: Vec<String>,
+}
+
+impl LoadContext<'_> {
+    pub fn id(&self) -> &str {
+        self.id
+    }
+
+    // Resolves a path written inside the asset (e.g. a sheet's image) against its directory.
+    pub fn resolve(&self, relative: &str) -> String {
+        match Path::new(self.id).parent() {
+            Some(dir) => normalize_asset_id(&format!("{}/{}", dir.to_string_lossy(), relative)),
+            None => normalize_asset_id(relative),
+        }
+    }
+
+    pub fn read(&mut self, id: &str) -> io::Result<Vec<u8>> {
+        self.add_dependency(id);
+        self.source.read(id)
+    }
+
+    pub fn add_dependency(&mut self, id: &str) {
+        let id = normalize_asset_id(id);
+        if !self.dependencies.contains(&id) {
+            self.dependencies.push(id);
+        }
+    }
+}
+
+trait ErasedLoader: Send + Sync {
+    fn asset_type(&self) -> TypeId;
+    fn asset_type_name(&self) -> &'static str;
+    fn new_cache(&self) -> Box<dyn ErasedCache>;
+    fn load_boxed(&self, bytes: Vec<u8>, context: &mut LoadContext) -> Result<Box<dyn Any + Send>>;
+}
+
+impl<L: AssetLoader> ErasedLoader for L {
+    fn asset_type(&self) -> TypeId {
+        TypeId::of::<L::Asset>()
+    }
+
+    fn asset_type_name(&self) -> &'static str {
+        type_name::<L::Asset>()
+    }
+
+    fn new_cache(&self) -> Box<dyn ErasedCache> {
+        Box::new(AssetCache::<L::Asset>::new())
+    }
+
+    fn load_boxed(&self, bytes: Vec<u8>, context: &mut LoadContext) -> Result<Box<dyn Any + Send>> {
+        Ok(Box::new(self.load(bytes, context)?))
+    }
+}
+
+trait ErasedCache: Send + Sync {
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+    fn contains_path(&self, path: &AssetPath) -> bool;
+    fn insert_boxed(&mut self, path: AssetPath, asset: Box<dyn Any + Send>);
+    fn replace_boxed(&mut self, path: &AssetPath, asset: Box<dyn Any + Send>) -> bool;
+}
+
+impl<T: Asset> ErasedCache for AssetCache<T> {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn contains_path(&self, path: &AssetPath) -> bool {
+        self.get_handle_for_path(path).is_some()
+    }
+
+    fn insert_boxed(&mut self, path: AssetPath, asset: Box<dyn Any + Send>) {
+        if let Ok(asset) = asset.downcast::<T>() {
+            self.insert(*asset, Some(path));
+        }
+    }
+
+    fn replace_boxed(&mut self, path: &AssetPath, asset: Box<dyn Any + Send>) -> bool {
+        let (handle, asset) = match (self.get_handle_for_path(path), asset.downcast::<T>()) {
+            (Some(handle), Ok(asset)) => (handle, asset),
+            _ => return false,
+        };
+        self.replace(handle, *asset).is_some()
+    }
+}
+
+// A load run on a worker thread: the asset and the files it was built from.
+struct FinishedLoad {
+    id: String,
+    loader: usize,
+    result: Result<(Box<dyn Any + Send>, Vec<String>)>,
+}
+
+// Owns one AssetCache per asset type and the loaders that fill them. Ids are asset source ids,
+// e.g. "assets/skelly/skelly.json".
+pub struct AssetServer {
+    source: Arc<dyn AssetSource>,
+    loaders: Vec<Arc<dyn ErasedLoader>>,
+    loaders_by_extension: HashMap<String, usize>,
+    caches: HashMap<TypeId, Box<dyn ErasedCache>>,
+    // ids loaded through a loader, so they can be loaded again when the file changes
+    loaded: HashMap<String, usize>,
+    dependencies: HashMap<String, HashSet<String>>,
+    dependents: HashMap<String, HashSet<String>>,
+    background: BackgroundLoader<FinishedLoad>,
+    // ids loading in the background, so asking again doesn't queue them twice
+    pending: HashSet<String>,
+}
+
+impl AssetServer {
+    pub fn new(source: Arc<dyn AssetSource>) -> Self {
+        let mut server = Self {
+            source,
+            loaders: Vec::new(),
+            loaders_by_extension: HashMap::new(),
+            caches: HashMap::new(),
+            loaded: HashMap::new(),
+            dependencies: HashMap::new(),
+            dependents: HashMap::new(),
+            background: BackgroundLoader::new(),
+            pending: HashSet::new(),
+        };
+        server.register_loader(ImageAssetLoader);
+        server.register_loader(SpriteSheetLoader);
+        server.register_loader(FontLoader);
+        server.register_loader(LuaDataLoader);
+        server.register_loader(TiledLoader);
+        server.register_loader(LdtkLoader);
+        server
+    }
+
+    pub fn source(&self) -> Arc<dyn AssetSource> {
+        Arc::clone(&self.source)
+    }
+
+    // A loader registered later takes over any extension an earlier one claimed.
+    pub fn register_loader<L: AssetLoader>(&mut self, loader: L) {
+        let index = self.loaders.len();
+        for extension in loader.extensions() {
+            self.loaders_by_extension
+                .insert(extension.to_ascii_lowercase(), index);
+        }
+        self.loaders.push(Arc::new(loader));
+    }
+
+    pub fn cache<T: Asset>(&self) -> Option<&AssetCache<T>> {
+        self.caches
+            .get(&TypeId::of::<T>())
+            .and_then(|cache| cache.as_any().downcast_ref())
+    }
+
+    pub fn cache_mut<T: Asset>(&mut self) -> &mut AssetCache<T> {
+        self.caches
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(AssetCache::<T>::new()))
+            .as_any_mut()
+            .downcast_mut()
+            .expect("asset cache stored under the wrong type")
+    }
+
+    pub fn get<T: Asset>(&self, handle: Handle<T>) -> Option<&T> {
+        self.cache::<T>()?.get(handle)
+    }
+
+    // Adds an asset built outside a loader, e.g. one generated at runtime. It has no file to be
+    // reloaded from.
+    pub fn add<T: Asset>(&mut self, asset: T, path: Option<AssetPath>) -> Handle<T> {
+        self.cache_mut::<T>().insert(asset, path)
+    }
+
+    // Loads `id` with the loader registered for its extension, or returns the handle it
+    // already has.
+    pub fn load<T: Asset>(&mut self, id: &str) -> Result<Handle<T>> {
+        let id = normalize_asset_id(id);
+        let path = AssetPath::new(id.clone());
+        if let Some(handle) = self.cache::<T>().and_then(|c| c.get_handle_for_path(&path)) {
+            return Ok(handle);
+        }
+
+        let loader = self.typed_loader_for::<T>(&id)?;
+        let asset = self.run_loader(loader, &id)?;
+        let asset = *asset
+            .downcast::<T>()
+            .map_err(|_| anyhow!("loader for {} returned the wrong type", id))?;
+        self.loaded.insert(id, loader);
+        Ok(self.cache_mut::<T>().insert(asset, Some(path)))
+    }
+
+    // Starts loading `id` on a worker thread, poll_background moves it into its cache once
+    // it's done. Ids already loaded or on their way are left alone.
+    pub fn load_in_background<T: Asset>(&mut self, id: &str) -> Result<()> {
+        let id = normalize_asset_id(id);
+        let path = AssetPath::new(id.clone());
+        if self.pending.contains(&id) || self.cache::<T>().is_some_and(|c| c.contains_path(&path)) {
+            return Ok(());
+        }
+
+        let loader = self.typed_loader_for::<T>(&id)?;
+        let erased = Arc::clone(&self.loaders[loader]);
+        let source = Arc::clone(&self.source);
+        let job_id = id.clone();
+        self.background.request(Box::new(move || FinishedLoad {
+            result: load_with(erased.as_ref(), source.as_ref(), &job_id),
+            id: job_id,
+            loader,
+        }));
+        self.pending.insert(id);
+        Ok(())
+    }
+
+    // Puts background loads that finished into their caches. Returns their ids, with the error
+    // for ones that failed.
+    pub fn poll_background(&mut self) -> Vec<(String, Result<()>)> {
+        let mut finished = Vec::new();
+        for FinishedLoad { id, loader, result } in self.background.poll_finished() {
+            self.pending.remove(&id);
+            let result = result.map(|(asset, dependencies)| {
+                self.set_dependencies(&id, dependencies);
+                self.loaded.insert(id.clone(), loader);
+                let erased = &self.loaders[loader];
+                self.caches
+                    .entry(erased.asset_type())
+                    .or_insert_with(|| erased.new_cache())
+                    .insert_boxed(AssetPath::new(id.clone()), asset);
+            });
+            finished.push((id, result));
+        }
+        finished
+    }
+
+    // (completed, requested) background loads, since the last time none were in flight
+    pub fn background_progress(&self) -> (usize, usize) {
+        self.background.progress()
+    }
+
+    pub fn is_loaded(&self, id: &str) -> bool {
+        self.loaded.contains_key(&normalize_asset_id(id))
+    }
+
+    // Removes a loaded asset and hands it over, e.g. pixels once they're on the GPU. Loading
+    // the id again reads its file again.
+    pub fn take<T: Asset>(&mut self, id: &str) -> Option<T> {
+        let id = normalize_asset_id(id);
+        let cache = self.cache_mut::<T>();
+        let handle = cache.get_handle_for_path(&AssetPath::new(id.clone()))?;
+        let asset = cache.remove(handle)?;
+        self.loaded.remove(&id);
+        self.set_dependencies(&id, Vec::new());
+        Some(asset)
+    }
+
+    // Loads a changed file again behind its existing handle. Returns false for ids that were
+    // never loaded through a loader.
+    pub fn reload(&mut self, id: &str) -> Result<bool> {
+        let id = normalize_asset_id(id);
+        let loader = match self.loaded.get(&id) {
+            Some(loader) => *loader,
+            None => return Ok(false),
+        };
+
+        let asset = self.run_loader(loader, &id)?;
+        let asset_type = self.loaders[loader].asset_type();
+        let replaced = self
+            .caches
+            .get_mut(&asset_type)
+            .is_some_and(|cache| cache.replace_boxed(&AssetPath::new(id.clone()), asset));
+        if !replaced {
+            // unloaded since, so there's nothing to swap into
+            self.loaded.remove(&id);
+        }
+        Ok(replaced)
+    }
+
+    // Drops unreferenced assets of one type, forgetting the ids of any that came from a loader.
+    pub fn unload_unused<T: Asset>(&mut self) -> Vec<Handle<T>> {
+        let removed = self.cache_mut::<T>().unload_unused();
+        let stale: Vec<String> = self
+            .loaded
+            .iter()
+            .filter(|(id, loader)| {
+                self.loaders[**loader].asset_type() == TypeId::of::<T>()
+                    && !self.caches[&TypeId::of::<T>()].contains_path(&AssetPath::new(id.as_str()))
+            })
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in stale {
+            self.loaded.remove(&id);
+            self.set_dependencies(&id, Vec::new());
+        }
+        removed
+    }
+
+    fn add_dependency(&mut self, id: &str, dependency: &str) {
+        let id = normalize_asset_id(id);
+        let dependency = normalize_asset_id(dependency);
+        self.dependents
+            .entry(dependency.clone())
+            .or_default()
+            .insert(id.clone());
+        self.dependencies.entry(id).or_default().insert(dependency);
+    }
+
+    pub fn dependencies(&self, id: &str) -> Vec<String> {
+        self.dependencies
+            .get(&normalize_asset_id(id))
+            .map(|deps| deps.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    // Everything built from `id`, directly or through other assets, nearest first.
+    pub fn dependents(&self, id: &str) -> Vec<String> {
+        let id = normalize_asset_id(id);
+        let mut seen = HashSet::from([id.clone()]);
+        let mut queue = VecDeque::from([id]);
+        let mut dependents = Vec::new();
+        while let Some(next) = queue.pop_front() {
+            for dependent in self.dependents.get(&next).into_iter().flatten() {
+                if seen.insert(dependent.clone()) {
+                    dependents.push(dependent.clone());
+                    queue.push_back(dependent.clone());
+                }
+            }
+        }
+        dependents
+    }
+
+    fn loader_for(&self, id: &str) -> Result<usize> {
+        let extension = Path::new(id)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_ascii_lowercase())
+            .ok_or_else(|| anyhow!("{} has no file extension", id))?;
+        self.loaders_by_extension
+            .get(&extension)
+            .copied()
+            .ok_or_else(|| anyhow!("no asset loader registered for .{} ({})", extension, id))
+    }
+
+    // The loader for `id`, as long as it loads a `T`.
+    fn typed_loader_for<T: Asset>(&self, id: &str) -> Result<usize> {
+        let loader = self.loader_for(id)?;
+        if self.loaders[loader].asset_type() != TypeId::of::<T>() {
+            bail!(
+                "{} loads as {}, not {}",
+                id,
+                self.loaders[loader].asset_type_name(),
+                type_name::<T>()
+            );
+        }
+        Ok(loader)
+    }
+
+    fn run_loader(&mut self, loader: usize, id: &str) -> Result<Box<dyn Any + Send>> {
+        let (asset, dependencies) =
+            load_with(self.loaders[loader].as_ref(), self.source.as_ref(), id)?;
+        self.set_dependencies(id, dependencies);
+        Ok(asset)
+    }
+
+    fn set_dependencies(&mut self, id: &str, dependencies: Vec<String>) {
+        for old in self.dependencies.remove(id).into_iter().flatten() {
+            if let Some(dependents) = self.dependents.get_mut(&old) {
+                dependents.remove(id);
+            }
+        }
+        for dependency in dependencies {
+            self.add_dependency(id, &dependency);
+        }
+    }
+}
+
+// Runs a loader on the file behind `id`, on whichever thread calls it.
+fn load_with(
+    loader: &dyn ErasedLoader,
+    source: &dyn AssetSource,
+    id: &str,
+) -> Result<(Box<dyn Any + Send>, Vec<String>)> {
+    let bytes = source.read(id)?;
+    let mut context = LoadContext {
+        id,
+        source,
+        dependencies: Vec::new(),
+    };
+    let asset = loader
+        .load_boxed(bytes, &mut context)
+        .map_err(|err| anyhow!("failed to load {}: {}", id, err))?;
+    Ok((asset, context.dependencies))
+}
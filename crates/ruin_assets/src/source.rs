@@ -0,0 +1,84 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::archive::ArchiveSource;
+use crate::asset::normalize_asset_id;
+
+// Shipped builds bundle everything into this file, next to the working directory.
+pub const DEFAULT_ARCHIVE: &str = "game.ruin";
+
+// Where asset and script bytes come from. Ids are '/' separated paths relative to the game
+// root, e.g. "assets/skelly/skelly_idle.png" or "lua_runtime/scripts/main.lua".
+pub trait AssetSource: Send + Sync {
+    fn read(&self, id: &str) -> io::Result<Vec<u8>>;
+    fn exists(&self, id: &str) -> bool;
+
+    fn read_to_string(&self, id: &str) -> io::Result<String> {
+        String::from_utf8(self.read(id)?)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+}
+
+// Loose files on disk, what development runs against.
+pub struct DirectorySource {
+    root: PathBuf,
+}
+
+impl DirectorySource {
+    pub fn new(root: impl AsRef<Path>) -> Self {
+        Self {
+            root: root.as_ref().to_path_buf(),
+        }
+    }
+}
+
+impl AssetSource for DirectorySource {
+    fn read(&self, id: &str) -> io::Result<Vec<u8>> {
+        fs::read(self.root.join(normalize_asset_id(id)))
+    }
+
+    fn exists(&self, id: &str) -> bool {
+        self.root.join(normalize_asset_id(id)).is_file()
+    }
+}
+
+// Reads from the packed archive when one is present, otherwise from loose files.
+pub fn open_default_source() -> io::Result<Arc<dyn AssetSource>> {
+    if Path::new(DEFAULT_ARCHIVE).is_file() {
+        Ok(Arc::new(ArchiveSource::open(DEFAULT_ARCHIVE)?))
+    } else {
+        Ok(Arc::new(DirectorySource::new(".")))
+    }
+}
+
+// Files kept in memory, for testing loaders without touching the disk.
+#[cfg(test)]
+pub(crate) struct MemorySource(pub std::collections::HashMap<String, Vec<u8>>);
+
+#[cfg(test)]
+impl MemorySource {
+    pub fn new(files: &[(&str, &str)]) -> Self {
+        Self(
+            files
+                .iter()
+                .map(|(id, text)| (id.to_string(), text.as_bytes().to_vec()))
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+impl AssetSource for MemorySource {
+    fn read(&self, id: &str) -> io::Result<Vec<u8>> {
+        self.0
+            .get(&normalize_asset_id(id))
+            .cloned()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, id.to_string()))
+    }
+
+    fn exists(&self, id: &str) -> bool {
+        self.0.contains_key(&normalize_asset_id(id))
+    }
+}
@@ -0,0 +1,758 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use base64::Engine;
+use serde_json::Value;
+
+use crate::asset::normalize_asset_id;
+use crate::level::{
+    add_object, is_collision_layer, Level, LevelFile, LevelObject, LevelTileLayer, LevelTileset,
+    Properties, PropertyValue,
+};
+use crate::server::{AssetLoader, LoadContext};
+
+// the top bits of a tile id hold its flip flags
+const TILE_ID_MASK: u32 = 0x0fff_ffff;
+
+// Tiled maps, .tmx or .tmj. Tiled JSON maps need the .tmj extension since .json loads as an
+// Aseprite sheet. External tilesets (.tsx, .tsj) are read as dependencies of the map.
+pub struct TiledLoader;
+
+impl AssetLoader for TiledLoader {
+    type Asset = LevelFile;
+
+    fn extensions(&self) -> &[&str] {
+        &["tmx", "tmj"]
+    }
+
+    fn load(&self, bytes: Vec<u8>, context: &mut LoadContext) -> Result<LevelFile> {
+        let name = Path::new(context.id())
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let level = if context.id().ends_with(".tmx") {
+            let text = String::from_utf8(bytes)?;
+            let document = roxmltree::Document::parse(&text)?;
+            parse_tmx(name, document.root_element(), context)?
+        } else {
+            parse_tmj(name, &serde_json::from_slice(&bytes)?, context)?
+        };
+        Ok(LevelFile {
+            levels: vec![level],
+        })
+    }
+}
+
+// Offset and visibility handed down from group layers.
+#[derive(Clone, Copy)]
+struct Parent {
+    offset: [f32; 2],
+    visible: bool,
+}
+
+const ROOT: Parent = Parent {
+    offset: [0.0, 0.0],
+    visible: true,
+};
+
+fn new_level(name: String, map_size: [u32; 2], tile_size: [u32; 2]) -> Level {
+    Level {
+        name,
+        size: [map_size[0] * tile_size[0], map_size[1] * tile_size[1]],
+        tile_size,
+        tilesets: Vec::new(),
+        tile_layers: Vec::new(),
+        objects: Vec::new(),
+        colliders: Vec::new(),
+    }
+}
+
+// Tileset images are relative to the file the tileset is defined in.
+fn tileset_image(image: &str, base: &str) -> String {
+    match Path::new(base).parent() {
+        Some(dir) => normalize_asset_id(&format!("{}/{}", dir.to_string_lossy(), image)),
+        None => normalize_asset_id(image),
+    }
+}
+
+// Tile ids from a layer's csv or base64 data, the latter optionally compressed.
+fn decode_tiles(text: &str, encoding: Option<&str>, compression: Option<&str>) -> Result<Vec<u32>> {
+    match encoding {
+        Some("csv") => text
+            .split(',')
+            .map(str::trim)
+            .filter(|id| !id.is_empty())
+            .map(|id| Ok(id.parse::<u32>()? & TILE_ID_MASK))
+            .collect(),
+        Some("base64") => {
+            let bytes = base64::engine::general_purpose::STANDARD.decode(text.trim())?;
+            let bytes = match compression {
+                None | Some("") => bytes,
+                Some("zlib") => miniz_oxide::inflate::decompress_to_vec_zlib(&bytes)
+                    .map_err(|err| anyhow!("bad zlib tile data: {:?}", err))?,
+                Some("gzip") => miniz_oxide::inflate::decompress_to_vec(gzip_body(&bytes)?)
+                    .map_err(|err| anyhow!("bad gzip tile data: {:?}", err))?,
+                Some(other) => return Err(anyhow!("unsupported tile compression {}", other)),
+            };
+            Ok(bytes
+                .chunks_exact(4)
+                .map(|id| u32::from_le_bytes([id[0], id[1], id[2], id[3]]) & TILE_ID_MASK)
+                .collect())
+        }
+        Some(other) => Err(anyhow!("unsupported tile encoding {}", other)),
+        None => Err(anyhow!("missing tile encoding")),
+    }
+}
+
+// The deflate stream inside a gzip member. Every offset comes from the file, so each step is
+// bounds checked and a broken header is an error rather than a panic.
+fn gzip_body(bytes: &[u8]) -> Result<&[u8]> {
+    let bad = || anyhow!("bad gzip header");
+    if bytes.len() < 18 || bytes[0..3] != [0x1f, 0x8b, 8] {
+        return Err(bad());
+    }
+    let flags = bytes[3];
+    let mut start: usize = 10;
+    if flags & 0x04 != 0 {
+        let extra = bytes.get(start..start + 2).ok_or_else(bad)?;
+        let extra = u16::from_le_bytes([extra[0], extra[1]]) as usize;
+        start = start.checked_add(2 + extra).ok_or_else(bad)?;
+    }
+    for flag in [0x08, 0x10] {
+        if flags & flag != 0 {
+            let end = bytes
+                .get(start..)
+                .and_then(|rest| rest.iter().position(|b| *b == 0))
+                .ok_or_else(bad)?;
+            start = start.checked_add(end + 1).ok_or_else(bad)?;
+        }
+    }
+    if flags & 0x02 != 0 {
+        start = start.checked_add(2).ok_or_else(bad)?;
+    }
+    let end = bytes.len().checked_sub(8).ok_or_else(bad)?;
+    bytes.get(start..end).ok_or_else(bad)
+}
+
+fn tile_layer(
+    name: String,
+    size: [u32; 2],
+    tiles: Vec<u32>,
+    offset: [f32; 2],
+    visible: bool,
+    properties: Properties,
+) -> Result<LevelTileLayer> {
+    if tiles.len() != (size[0] * size[1]) as usize {
+        return Err(anyhow!(
+            "layer {} has {} tiles, expected {}x{}",
+            name,
+            tiles.len(),
+            size[0],
+            size[1]
+        ));
+    }
+    Ok(LevelTileLayer {
+        collision: is_collision_layer(&name, &properties),
+        name,
+        width: size[0],
+        height: size[1],
+        tiles,
+        offset,
+        visible,
+        properties,
+    })
+}
+
+fn parse_tmj(name: String, map: &Value, context: &mut LoadContext) -> Result<Level> {
+    if map["infinite"].as_bool().unwrap_or(false) {
+        return Err(anyhow!("infinite maps aren't supported"));
+    }
+    let mut level = new_level(
+        name,
+        [json_u32(&map["width"]), json_u32(&map["height"])],
+        [json_u32(&map["tilewidth"]), json_u32(&map["tileheight"])],
+    );
+
+    for tileset in map["tilesets"].as_array().into_iter().flatten() {
+        let first_id = json_u32(&tileset["firstgid"]);
+        let tileset = match tileset["source"].as_str() {
+            Some(source) => read_external_tileset(first_id, &context.resolve(source), context)?,
+            None => tmj_tileset(first_id, tileset, context.id())?,
+        };
+        level.tilesets.push(tileset);
+    }
+
+    tmj_layers(&mut level, &map["layers"], ROOT)?;
+    Ok(level)
+}
+
+fn tmj_tileset(first_id: u32, tileset: &Value, base: &str) -> Result<LevelTileset> {
+    let image = tileset["image"]
+        .as_str()
+        .ok_or_else(|| anyhow!("image collection tilesets aren't supported"))?;
+    Ok(LevelTileset {
+        image: tileset_image(image, base),
+        first_id,
+        tile_count: json_u32(&tileset["tilecount"]),
+        tile_size: [
+            json_u32(&tileset["tilewidth"]),
+            json_u32(&tileset["tileheight"]),
+        ],
+        margin: json_u32(&tileset["margin"]),
+        spacing: json_u32(&tileset["spacing"]),
+    })
+}
+
+fn tmj_layers(level: &mut Level, layers: &Value, parent: Parent) -> Result<()> {
+    for layer in layers.as_array().into_iter().flatten() {
+        let name = layer["name"].as_str().unwrap_or_default().to_string();
+        let properties = tmj_properties(&layer["properties"]);
+        let parent = Parent {
+            offset: [
+                parent.offset[0] + json_f32(&layer["offsetx"]),
+                parent.offset[1] + json_f32(&layer["offsety"]),
+            ],
+            visible: parent.visible && layer["visible"].as_bool().unwrap_or(true),
+        };
+
+        match layer["type"].as_str().unwrap_or_default() {
+            "tilelayer" => {
+                if layer["chunks"].is_array() {
+                    return Err(anyhow!("infinite maps aren't supported"));
+                }
+                let tiles = match &layer["data"] {
+                    Value::Array(ids) => ids.iter().map(|id| json_u32(id) & TILE_ID_MASK).collect(),
+                    Value::String(data) => decode_tiles(
+                        data,
+                        layer["encoding"].as_str(),
+                        layer["compression"].as_str(),
+                    )?,
+                    _ => Vec::new(),
+                };
+                level.tile_layers.push(tile_layer(
+                    name,
+                    [json_u32(&layer["width"]), json_u32(&layer["height"])],
+                    tiles,
+                    parent.offset,
+                    parent.visible,
+                    properties,
+                )?);
+            }
+            "objectgroup" => {
+                let collision = is_collision_layer(&name, &properties);
+                for object in layer["objects"].as_array().into_iter().flatten() {
+                    let size = [json_f32(&object["width"]), json_f32(&object["height"])];
+                    let mut position = [
+                        json_f32(&object["x"]) + parent.offset[0],
+                        json_f32(&object["y"]) + parent.offset[1],
+                    ];
+                    // tile objects are placed by their bottom-left corner
+                    if object["gid"].is_u64() {
+                        position[1] -= size[1];
+                    }
+                    let kind = object["type"]
+                        .as_str()
+                        .or(object["class"].as_str())
+                        .unwrap_or_default();
+                    add_object(
+                        level,
+                        &name,
+                        collision,
+                        LevelObject {
+                            name: object["name"].as_str().unwrap_or_default().to_string(),
+                            kind: kind.to_string(),
+                            layer: String::new(),
+                            position,
+                            size,
+                            properties: tmj_properties(&object["properties"]),
+                        },
+                    );
+                }
+            }
+            "group" => tmj_layers(level, &layer["layers"], parent)?,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn tmj_properties(properties: &Value) -> Properties {
+    properties
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|property| {
+            let name = property["name"].as_str()?.to_string();
+            let value = match (&property["value"], property["type"].as_str()) {
+                (Value::Bool(value), _) => PropertyValue::Bool(*value),
+                (Value::Number(value), Some("int")) => {
+                    PropertyValue::Int(value.as_i64().unwrap_or(0))
+                }
+                (Value::Number(value), _) => PropertyValue::Float(value.as_f64().unwrap_or(0.0)),
+                (Value::String(value), _) => PropertyValue::String(value.clone()),
+                (other, _) => PropertyValue::String(other.to_string()),
+            };
+            Some((name, value))
+        })
+        .collect()
+}
+
+fn read_external_tileset(
+    first_id: u32,
+    id: &str,
+    context: &mut LoadContext,
+) -> Result<LevelTileset> {
+    let bytes = context.read(id)?;
+    if id.ends_with(".tsx") {
+        let text = String::from_utf8(bytes)?;
+        let document = roxmltree::Document::parse(&text)?;
+        tmx_tileset(first_id, document.root_element(), id)
+    } else {
+        tmj_tileset(first_id, &serde_json::from_slice(&bytes)?, id)
+    }
+}
+
+fn parse_tmx(name: String, map: roxmltree::Node, context: &mut LoadContext) -> Result<Level> {
+    if attr(map, "infinite") == "1" {
+        return Err(anyhow!("infinite maps aren't supported"));
+    }
+    let mut level = new_level(
+        name,
+        [attr_u32(map, "width"), attr_u32(map, "height")],
+        [attr_u32(map, "tilewidth"), attr_u32(map, "tileheight")],
+    );
+
+    for tileset in map.children().filter(|node| node.has_tag_name("tileset")) {
+        let first_id = attr_u32(tileset, "firstgid");
+        let tileset = match tileset.attribute("source") {
+            Some(source) => read_external_tileset(first_id, &context.resolve(source), context)?,
+            None => tmx_tileset(first_id, tileset, context.id())?,
+        };
+        level.tilesets.push(tileset);
+    }
+
+    tmx_layers(&mut level, map, ROOT)?;
+    Ok(level)
+}
+
+fn tmx_tileset(first_id: u32, tileset: roxmltree::Node, base: &str) -> Result<LevelTileset> {
+    let image = tileset
+        .children()
+        .find(|node| node.has_tag_name("image"))
+        .and_then(|image| image.attribute("source"))
+        .ok_or_else(|| anyhow!("image collection tilesets aren't supported"))?;
+    Ok(LevelTileset {
+        image: tileset_image(image, base),
+        first_id,
+        tile_count: attr_u32(tileset, "tilecount"),
+        tile_size: [
+            attr_u32(tileset, "tilewidth"),
+            attr_u32(tileset, "tileheight"),
+        ],
+        margin: attr_u32(tileset, "margin"),
+        spacing: attr_u32(tileset, "spacing"),
+    })
+}
+
+fn tmx_layers(level: &mut Level, node: roxmltree::Node, parent: Parent) -> Result<()> {
+    for layer in node.children().filter(|node| node.is_element()) {
+        let name = attr(layer, "name").to_string();
+        let properties = tmx_properties(layer);
+        let parent = Parent {
+            offset: [
+                parent.offset[0] + attr_f32(layer, "offsetx"),
+                parent.offset[1] + attr_f32(layer, "offsety"),
+            ],
+            visible: parent.visible && attr(layer, "visible") != "0",
+        };
+
+        match layer.tag_name().name() {
+            "layer" => {
+                let data = layer
+                    .children()
+                    .find(|node| node.has_tag_name("data"))
+                    .ok_or_else(|| anyhow!("layer {} has no data", name))?;
+                if data.children().any(|node| node.has_tag_name("chunk")) {
+                    return Err(anyhow!("infinite maps aren't supported"));
+                }
+                let tiles = match data.attribute("encoding") {
+                    // the old format, one element per tile
+                    None => data
+                        .children()
+                        .filter(|node| node.has_tag_name("tile"))
+                        .map(|tile| attr_u32(tile, "gid") & TILE_ID_MASK)
+                        .collect(),
+                    encoding => decode_tiles(
+                        data.text().unwrap_or_default(),
+                        encoding,
+                        data.attribute("compression"),
+                    )?,
+                };
+                level.tile_layers.push(tile_layer(
+                    name,
+                    [attr_u32(layer, "width"), attr_u32(layer, "height")],
+                    tiles,
+                    parent.offset,
+                    parent.visible,
+                    properties,
+                )?);
+            }
+            "objectgroup" => {
+                let collision = is_collision_layer(&name, &properties);
+                for object in layer.children().filter(|node| node.has_tag_name("object")) {
+                    let size = [attr_f32(object, "width"), attr_f32(object, "height")];
+                    let mut position = [
+                        attr_f32(object, "x") + parent.offset[0],
+                        attr_f32(object, "y") + parent.offset[1],
+                    ];
+                    if object.attribute("gid").is_some() {
+                        position[1] -= size[1];
+                    }
+                    let kind = object
+                        .attribute("type")
+                        .or(object.attribute("class"))
+                        .unwrap_or_default();
+                    add_object(
+                        level,
+                        &name,
+                        collision,
+                        LevelObject {
+                            name: attr(object, "name").to_string(),
+                            kind: kind.to_string(),
+                            layer: String::new(),
+                            position,
+                            size,
+                            properties: tmx_properties(object),
+                        },
+                    );
+                }
+            }
+            "group" => tmx_layers(level, layer, parent)?,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn tmx_properties(node: roxmltree::Node) -> Properties {
+    node.children()
+        .filter(|node| node.has_tag_name("properties"))
+        .flat_map(|properties| properties.children())
+        .filter(|node| node.has_tag_name("property"))
+        .filter_map(|property| {
+            let name = property.attribute("name")?.to_string();
+            // long strings are stored as the element's text instead
+            let text = property
+                .attribute("value")
+                .or(property.text())
+                .unwrap_or_default();
+            let value = match property.attribute("type").unwrap_or("string") {
+                "bool" => PropertyValue::Bool(text == "true"),
+                "int" => PropertyValue::Int(text.parse().unwrap_or(0)),
+                "float" => PropertyValue::Float(text.parse().unwrap_or(0.0)),
+                _ => PropertyValue::String(text.to_string()),
+            };
+            Some((name, value))
+        })
+        .collect()
+}
+
+fn attr<'a>(node: roxmltree::Node<'a, '_>, name: &str) -> &'a str {
+    node.attribute(name).unwrap_or_default()
+}
+
+fn attr_u32(node: roxmltree::Node, name: &str) -> u32 {
+    attr(node, name).parse().unwrap_or(0)
+}
+
+fn attr_f32(node: roxmltree::Node, name: &str) -> f32 {
+    attr(node, name).parse().unwrap_or(0.0)
+}
+
+fn json_u32(value: &Value) -> u32 {
+    value.as_u64().unwrap_or(0) as u32
+}
+
+fn json_f32(value: &Value) -> f32 {
+    value.as_f64().unwrap_or(0.0) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::server::AssetServer;
+    use crate::source::MemorySource;
+
+    const FLIPPED_H: u32 = 0x8000_0000;
+    const FLIPPED_V: u32 = 0x4000_0000;
+    const FLIPPED_D: u32 = 0x2000_0000;
+
+    fn encode(ids: &[u32]) -> Vec<u8> {
+        ids.iter().flat_map(|id| id.to_le_bytes()).collect()
+    }
+
+    fn base64(bytes: &[u8]) -> String {
+        base64::engine::general_purpose::STANDARD.encode(bytes)
+    }
+
+    // `header` goes between the fixed 10 bytes and the deflate stream
+    fn gzip(bytes: &[u8], flags: u8, header: &[u8]) -> Vec<u8> {
+        let mut out = vec![0x1f, 0x8b, 8, flags, 0, 0, 0, 0, 0, 3];
+        out.extend_from_slice(header);
+        out.extend(miniz_oxide::deflate::compress_to_vec(bytes, 6));
+        out.extend([0; 8]); // crc and size, not checked
+        out
+    }
+
+    fn load(files: &[(&str, &str)], id: &str) -> Result<Level> {
+        let mut server = AssetServer::new(Arc::new(MemorySource::new(files)));
+        let handle = server.load::<LevelFile>(id)?;
+        Ok(server.get(handle).unwrap().levels[0].clone())
+    }
+
+    #[test]
+    fn csv_drops_flip_bits() {
+        let text = format!("1,{},\n{}, 0\n", FLIPPED_H | 2, FLIPPED_V | FLIPPED_D | 3);
+        assert_eq!(
+            decode_tiles(&text, Some("csv"), None).unwrap(),
+            [1, 2, 3, 0]
+        );
+    }
+
+    #[test]
+    fn base64_with_every_compression() {
+        let ids = [FLIPPED_H | 5, 0, 7, FLIPPED_V | 1];
+        let raw = encode(&ids);
+        let zlib = miniz_oxide::deflate::compress_to_vec_zlib(&raw, 6);
+        for (data, compression) in [
+            (raw.clone(), None),
+            (raw.clone(), Some("")),
+            (zlib, Some("zlib")),
+            (gzip(&raw, 0, &[]), Some("gzip")),
+        ] {
+            assert_eq!(
+                decode_tiles(&base64(&data), Some("base64"), compression).unwrap(),
+                [5, 0, 7, 1],
+                "{:?}",
+                compression
+            );
+        }
+    }
+
+    #[test]
+    fn gzip_skips_optional_header_fields() {
+        let raw = encode(&[1, 2]);
+        let mut header = vec![3, 0, b'x', b'y', b'z']; // extra field
+        header.extend(b"level.bin\0");
+        header.extend(b"a comment\0");
+        header.extend([0, 0]); // header crc
+        let data = gzip(&raw, 0x04 | 0x08 | 0x10 | 0x02, &header);
+        assert_eq!(
+            decode_tiles(&base64(&data), Some("base64"), Some("gzip")).unwrap(),
+            [1, 2]
+        );
+    }
+
+    #[test]
+    fn broken_gzip_headers_are_errors() {
+        let raw = encode(&[1, 2]);
+        let mut not_gzip = gzip(&raw, 0, &[]);
+        not_gzip[0] = 0;
+        let mut unterminated_name = vec![0x1f, 0x8b, 8, 0x08, 0, 0, 0, 0, 0, 3];
+        unterminated_name.extend(b"a name that never ends");
+        let cases = [
+            vec![0x1f, 0x8b, 8],
+            not_gzip,
+            gzip(&raw, 0x04, &[0xff, 0xff]), // extra field longer than the file
+            vec![
+                0x1f, 0x8b, 8, 0x04, 0, 0, 0, 0, 0, 3, 0, 0, 0, 0, 0, 0, 0, 0,
+            ],
+            unterminated_name,
+        ];
+        for bytes in cases {
+            assert!(gzip_body(&bytes).is_err(), "{:?}", bytes);
+            let result = decode_tiles(&base64(&bytes), Some("base64"), Some("gzip"));
+            assert!(result.is_err());
+        }
+    }
+
+    #[test]
+    fn unsupported_data_is_an_error() {
+        assert!(decode_tiles("AAAA", Some("base64"), Some("zstd")).is_err());
+        assert!(decode_tiles("1,2", Some("xml"), None).is_err());
+        assert!(decode_tiles("1,2", None, None).is_err());
+        assert!(decode_tiles("not base64!", Some("base64"), None).is_err());
+        assert!(decode_tiles(&base64(b"junk"), Some("base64"), Some("zlib")).is_err());
+    }
+
+    const TMX: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.10" orientation="orthogonal" width="2" height="2" tilewidth="16" tileheight="16" infinite="0">
+ <tileset firstgid="1" name="inline" tilewidth="16" tileheight="16" tilecount="4" columns="2" margin="1" spacing="2">
+  <image source="../tiles/inline.png" width="34" height="34"/>
+ </tileset>
+ <tileset firstgid="5" source="../tilesets/external.tsx"/>
+ <layer id="1" name="ground" width="2" height="2">
+  <properties>
+   <property name="depth" type="int" value="3"/>
+  </properties>
+  <data encoding="csv">1,2147483650,
+0,5</data>
+ </layer>
+ <group id="2" name="front" offsetx="8" offsety="4" visible="0">
+  <layer id="3" name="collision" width="2" height="2" offsetx="2">
+   <data encoding="base64" compression="zlib">ZLIB</data>
+  </layer>
+ </group>
+ <objectgroup id="4" name="spawns">
+  <object id="1" name="player" type="spawn" x="16" y="32" width="16" height="16" gid="6">
+   <properties>
+    <property name="facing" value="left"/>
+    <property name="flying" type="bool" value="true"/>
+    <property name="speed" type="float" value="1.5"/>
+   </properties>
+  </object>
+ </objectgroup>
+ <objectgroup id="5" name="walls">
+  <properties>
+   <property name="collision" type="bool" value="true"/>
+  </properties>
+  <object id="2" x="0" y="0" width="32" height="8"/>
+  <object id="3" x="4" y="4"/>
+ </objectgroup>
+</map>"#;
+
+    const TSX: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<tileset name="external" tilewidth="16" tileheight="16" tilecount="8" columns="4">
+ <image source="external.png" width="64" height="32"/>
+</tileset>"#;
+
+    #[test]
+    fn loads_tmx() {
+        let zlib =
+            miniz_oxide::deflate::compress_to_vec_zlib(&encode(&[0, 6, FLIPPED_D | 7, 0]), 6);
+        let tmx = TMX.replace("ZLIB", &base64(&zlib));
+        let level = load(
+            &[("maps/level.tmx", &tmx), ("tilesets/external.tsx", TSX)],
+            "maps/level.tmx",
+        )
+        .unwrap();
+
+        assert_eq!(level.name, "level");
+        assert_eq!(level.size, [32, 32]);
+        assert_eq!(level.tilesets.len(), 2);
+        assert_eq!(level.tilesets[0].image, "tiles/inline.png");
+        assert_eq!(level.tilesets[0].margin, 1);
+        assert_eq!(level.tilesets[0].spacing, 2);
+        assert_eq!(level.tilesets[1].image, "tilesets/external.png");
+        assert_eq!(level.tilesets[1].first_id, 5);
+        assert_eq!(level.tilesets[1].tile_count, 8);
+
+        let ground = &level.tile_layers[0];
+        assert_eq!(ground.tiles, [1, 2, 0, 5]);
+        assert_eq!(
+            ground.properties,
+            [("depth".to_string(), PropertyValue::Int(3))]
+        );
+        assert!(ground.visible && !ground.collision);
+
+        let front = &level.tile_layers[1];
+        assert_eq!(front.tiles, [0, 6, 7, 0]);
+        assert_eq!(front.offset, [10.0, 4.0]);
+        assert!(!front.visible && front.collision);
+        assert_eq!(level.tileset_for(7), Some((1, 3)));
+
+        // tile objects sit on their bottom-left corner
+        let player = &level.objects[0];
+        assert_eq!(
+            (player.name.as_str(), player.kind.as_str()),
+            ("player", "spawn")
+        );
+        assert_eq!(player.layer, "spawns");
+        assert_eq!(player.position, [16.0, 16.0]);
+        assert_eq!(
+            player.properties,
+            [
+                (
+                    "facing".to_string(),
+                    PropertyValue::String("left".to_string())
+                ),
+                ("flying".to_string(), PropertyValue::Bool(true)),
+                ("speed".to_string(), PropertyValue::Float(1.5)),
+            ]
+        );
+        // collision objects only keep their rectangle, empty ones are dropped
+        assert_eq!(level.objects.len(), 1);
+        assert_eq!(level.colliders, [[0.0, 0.0, 32.0, 8.0]]);
+    }
+
+    #[test]
+    fn tmx_with_a_missing_tileset_fails() {
+        assert!(load(&[("maps/level.tmx", TMX)], "maps/level.tmx").is_err());
+    }
+
+    #[test]
+    fn tmx_rejects_infinite_maps() {
+        let tmx = TMX.replace(r#"infinite="0""#, r#"infinite="1""#);
+        let files = [
+            ("maps/level.tmx", tmx.as_str()),
+            ("tilesets/external.tsx", TSX),
+        ];
+        assert!(load(&files, "maps/level.tmx").is_err());
+    }
+
+    #[test]
+    fn loads_tmj() {
+        let gzip = gzip(&encode(&[FLIPPED_H | FLIPPED_V | 3, 0, 0, 1]), 0, &[]);
+        let tmj = format!(
+            r#"{{
+            "width": 2, "height": 2, "tilewidth": 8, "tileheight": 8, "infinite": false,
+            "tilesets": [{{ "firstgid": 1, "source": "external.tsj" }}],
+            "layers": [
+                {{ "type": "tilelayer", "name": "ground", "width": 2, "height": 2,
+                   "data": [1, {}, 0, 4] }},
+                {{ "type": "group", "name": "details", "offsetx": 4, "layers": [
+                    {{ "type": "tilelayer", "name": "decor", "width": 2, "height": 2,
+                       "offsety": 2, "encoding": "base64", "compression": "gzip",
+                       "data": "{}" }}
+                ] }},
+                {{ "type": "objectgroup", "name": "things", "objects": [
+                    {{ "name": "chest", "class": "loot", "x": 4, "y": 8, "width": 8, "height": 8,
+                       "properties": [{{ "name": "gold", "type": "int", "value": 10 }}] }}
+                ] }}
+            ]
+        }}"#,
+            FLIPPED_D | 2,
+            base64(&gzip)
+        );
+        let tsj = r#"{ "image": "tiles.png", "tilecount": 4, "tilewidth": 8, "tileheight": 8 }"#;
+        let level = load(
+            &[("maps/level.tmj", &tmj), ("maps/external.tsj", tsj)],
+            "maps/level.tmj",
+        )
+        .unwrap();
+
+        assert_eq!(level.tilesets[0].image, "maps/tiles.png");
+        assert_eq!(level.tile_layers[0].tiles, [1, 2, 0, 4]);
+        assert_eq!(level.tile_layers[1].tiles, [3, 0, 0, 1]);
+        assert_eq!(level.tile_layers[1].offset, [4.0, 2.0]);
+        let chest = &level.objects[0];
+        assert_eq!(chest.kind, "loot");
+        assert_eq!(chest.position, [4.0, 8.0]);
+        assert_eq!(
+            chest.properties,
+            [("gold".to_string(), PropertyValue::Int(10))]
+        );
+    }
+
+    #[test]
+    fn tmj_layer_with_the_wrong_tile_count_fails() {
+        let tmj = r#"{
+            "width": 2, "height": 2, "tilewidth": 8, "tileheight": 8,
+            "layers": [{ "type": "tilelayer", "name": "ground", "width": 2, "height": 2,
+                         "data": [1, 2, 3] }]
+        }"#;
+        assert!(load(&[("level.tmj", tmj)], "level.tmj").is_err());
+    }
+}
@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::asset::normalize_asset_id;
+
+// Exporters often write a file in several steps, so wait for it to go quiet before reporting.
+const SETTLE_TIME: Duration = Duration::from_millis(150);
+
+pub struct AssetWatcher {
+    root: PathBuf,
+    _watcher: RecommendedWatcher, // dropping this stops the watch
+    events: Receiver<notify::Result<Event>>,
+    pending: HashMap<String, Instant>,
+}
+
+impl AssetWatcher {
+    pub fn new(root: impl AsRef<Path>) -> Result<Self> {
+        let root = root.as_ref().canonicalize()?;
+        let (sender, events) = channel();
+        let mut watcher = notify::recommended_watcher(sender)?;
+        watcher.watch(&root, RecursiveMode::Recursive)?;
+
+        Ok(Self {
+            root,
+            _watcher: watcher,
+            events,
+            pending: HashMap::new(),
+        })
+    }
+
+    // Asset ids of files that changed and have settled since the last call.
+    pub fn poll_changed(&mut self) -> Vec<String> {
+        let now = Instant::now();
+        for event in self.events.try_iter().flatten() {
+            if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                continue;
+            }
+            for path in &event.paths {
+                if let Some(id) = self.asset_id(path) {
+                    self.pending.insert(id, now);
+                }
+            }
+        }
+
+        let settled: Vec<String> = self
+            .pending
+            .iter()
+            .filter(|(_, changed_at)| now.duration_since(**changed_at) >= SETTLE_TIME)
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in &settled {
+            self.pending.remove(id);
+        }
+
+        settled
+    }
+
+    fn asset_id(&self, path: &Path) -> Option<String> {
+        let relative = match path.strip_prefix(&self.root) {
+            Ok(relative) => relative.to_path_buf(),
+            Err(_) => path
+                .canonicalize()
+                .ok()?
+                .strip_prefix(&self.root)
+                .ok()?
+                .to_path_buf(),
+        };
+        // directories and extensionless editor temp files
+        relative.extension()?;
+        Some(normalize_asset_id(&relative.to_string_lossy()))
+    }
+}
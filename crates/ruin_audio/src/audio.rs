@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::mixer::{Bus, Mixer, SoundSettings, VoiceId};
+use crate::output::{open_default_output, AudioOutput, NullOutput};
+use crate::sound::Sound;
+use crate::spatial::{Emitter, Listener};
+use crate::stream::MusicStream;
+
+pub struct Audio {
+    mixer: Arc<Mutex<Mixer>>,
+    output: Box<dyn AudioOutput>,
+    next_voice: u32,
+    listener: Listener,
+    emitters: HashMap<VoiceId, Emitter>,
+}
+
+impl Audio {
+    pub fn new() -> Self {
+        let mixer = Arc::new(Mutex::new(Mixer::new()));
+        let output = open_default_output(&mixer);
+        Self::with_output(mixer, output)
+    }
+
+    // No device at all, for headless runs.
+    pub fn null() -> Self {
+        Self::with_output(
+            Arc::new(Mutex::new(Mixer::new())),
+            Box::new(NullOutput::default()),
+        )
+    }
+
+    pub fn with_output(mixer: Arc<Mutex<Mixer>>, output: Box<dyn AudioOutput>) -> Self {
+        Self {
+            mixer,
+            output,
+            next_voice: 1,
+            listener: Listener::default(),
+            emitters: HashMap::new(),
+        }
+    }
+
+    pub fn mixer(&self) -> Arc<Mutex<Mixer>> {
+        Arc::clone(&self.mixer)
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.output.sample_rate()
+    }
+
+    pub fn play_sound(&mut self, sound: &Sound, settings: SoundSettings) -> VoiceId {
+        let id = self.next_voice_id();
+        self.mixer.lock().unwrap().play(id, sound, settings);
+        id
+    }
+
+    // Plays a sound placed in the world, panned and attenuated relative to the listener.
+    // Sounds that start out of range are never played.
+    pub fn play_sound_at(
+        &mut self,
+        sound: &Sound,
+        settings: SoundSettings,
+        emitter: Emitter,
+    ) -> Option<VoiceId> {
+        let spatial = emitter.spatialize(&self.listener)?;
+        let id = self.next_voice_id();
+        let mut mixer = self.mixer.lock().unwrap();
+        mixer.play(id, sound, settings);
+        mixer.set_spatial(id, Some(spatial));
+        self.emitters.insert(id, emitter);
+        Some(id)
+    }
+
+    pub fn move_emitter(&mut self, id: VoiceId, position: [f32; 2]) {
+        if let Some(emitter) = self.emitters.get_mut(&id) {
+            emitter.position = position;
+        }
+    }
+
+    pub fn emitters(&self) -> impl Iterator<Item = VoiceId> + '_ {
+        self.emitters.keys().copied()
+    }
+
+    pub fn set_listener(&mut self, listener: Listener) {
+        self.listener = listener;
+    }
+
+    pub fn stop_sound(&mut self, id: VoiceId) {
+        self.emitters.remove(&id);
+        self.mixer.lock().unwrap().stop(id);
+    }
+
+    fn next_voice_id(&mut self) -> VoiceId {
+        let id = VoiceId(self.next_voice);
+        self.next_voice = self.next_voice.wrapping_add(1).max(1);
+        id
+    }
+
+    pub fn is_playing(&self, id: VoiceId) -> bool {
+        self.mixer.lock().unwrap().is_playing(id)
+    }
+
+    pub fn play_music(&mut self, stream: MusicStream, fade_seconds: f32) {
+        let feed = stream.start();
+        self.mixer.lock().unwrap().play_feed(feed, fade_seconds);
+    }
+
+    pub fn stop_music(&mut self, fade_seconds: f32) {
+        self.mixer.lock().unwrap().stop_music(fade_seconds);
+    }
+
+    // Id of the track playing or fading in, None when music is off or fading out.
+    pub fn music_id(&self) -> Option<String> {
+        self.mixer.lock().unwrap().music_id().map(str::to_string)
+    }
+
+    pub fn set_bus_volume(&mut self, bus: Bus, volume: f32) {
+        self.mixer.lock().unwrap().set_bus_volume(bus, volume);
+    }
+
+    pub fn bus_volume(&self, bus: Bus) -> f32 {
+        self.mixer.lock().unwrap().bus_volume(bus)
+    }
+
+    pub fn set_master_volume(&mut self, volume: f32) {
+        self.mixer.lock().unwrap().set_master_volume(volume);
+    }
+
+    // Once per frame, after emitters and the listener have moved.
+    pub fn update(&mut self, dt: f32) {
+        {
+            let mut mixer = self.mixer.lock().unwrap();
+            self.emitters.retain(|id, _| mixer.is_playing(*id));
+            for (id, emitter) in &self.emitters {
+                mixer.set_spatial(*id, emitter.spatialize(&self.listener));
+            }
+        }
+        self.output.update(&self.mixer, dt);
+    }
+}
+
+impl Default for Audio {
+    fn default() -> Self {
+        Self::new()
+    }
+}
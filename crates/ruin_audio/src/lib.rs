@@ -0,0 +1,15 @@
+mod audio;
+mod mixer;
+mod output;
+mod sound;
+mod spatial;
+mod stream;
+
+pub use crate::audio::Audio;
+pub use crate::mixer::{Bus, Mixer, SoundSettings, VoiceId};
+#[cfg(feature = "cpal")]
+pub use crate::output::DeviceOutput;
+pub use crate::output::{open_default_output, AudioOutput, NullOutput};
+pub use crate::sound::{Sound, SoundLoader};
+pub use crate::spatial::{Emitter, Listener};
+pub use crate::stream::MusicStream;
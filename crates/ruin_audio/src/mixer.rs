@@ -0,0 +1,482 @@
+use std::sync::mpsc::TryRecvError;
+use std::sync::Arc;
+
+use crate::sound::Sound;
+use crate::stream::{MusicFeed, MusicStream};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bus {
+    Music,
+    Sfx,
+    Ui,
+}
+
+impl Bus {
+    const COUNT: usize = 3;
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "music" => Some(Bus::Music),
+            "sfx" => Some(Bus::Sfx),
+            "ui" => Some(Bus::Ui),
+            _ => None,
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VoiceId(pub u32);
+
+#[derive(Debug, Clone, Copy)]
+pub struct SoundSettings {
+    pub volume: f32,
+    // playback speed, 2.0 is an octave up
+    pub pitch: f32,
+    pub bus: Bus,
+}
+
+impl Default for SoundSettings {
+    fn default() -> Self {
+        Self {
+            volume: 1.0,
+            pitch: 1.0,
+            bus: Bus::Sfx,
+        }
+    }
+}
+
+struct Voice {
+    id: VoiceId,
+    samples: Arc<[f32]>,
+    channels: usize,
+    sample_rate: u32,
+    // in source frames, fractional when resampling
+    position: f64,
+    settings: SoundSettings,
+    // set by positional sounds, -1 is all left and 1 all right
+    spatial_gain: f32,
+    pan: f32,
+    // out of range of the listener, keeps its place without being mixed
+    culled: bool,
+}
+
+impl Voice {
+    fn frames(&self) -> usize {
+        self.samples.len() / self.channels
+    }
+
+    fn sample(&self, frame: usize, channel: usize) -> f32 {
+        self.samples[frame * self.channels + channel.min(self.channels - 1)]
+    }
+}
+
+struct MusicTrack {
+    feed: MusicFeed,
+    channels: usize,
+    // decoded frames not played yet, position is relative to the start of it
+    buffer: Vec<f32>,
+    position: f64,
+    gain: f32,
+    target_gain: f32,
+    fade_seconds: f32,
+    finished: bool,
+}
+
+impl MusicTrack {
+    // Takes decoded chunks until the frames around `position` are in, dropping ones already
+    // played. False when the decoder hasn't caught up yet.
+    fn fill(&mut self) -> bool {
+        let needed = self.position as usize + 2;
+        while self.buffer.len() / self.channels < needed && !self.finished {
+            let played = (self.position as usize).min(self.buffer.len() / self.channels);
+            self.buffer.drain(..played * self.channels);
+            self.position -= played as f64;
+            match self.feed.chunks.try_recv() {
+                Ok(chunk) => self.buffer.extend_from_slice(&chunk),
+                Err(TryRecvError::Empty) => return false,
+                Err(TryRecvError::Disconnected) => self.finished = true,
+            }
+        }
+        true
+    }
+
+    fn sample(&self, frame: usize, channel: usize) -> f32 {
+        self.buffer
+            .get(frame * self.channels + channel.min(self.channels - 1))
+            .copied()
+            .unwrap_or(0.0)
+    }
+
+    fn is_done(&self) -> bool {
+        (self.finished && self.position as usize >= self.buffer.len() / self.channels)
+            || (self.gain <= 0.0 && self.target_gain <= 0.0)
+    }
+}
+
+// Everything that's playing. Shared with the output, which calls render from its own thread.
+pub struct Mixer {
+    voices: Vec<Voice>,
+    music: Vec<MusicTrack>,
+    bus_volumes: [f32; Bus::COUNT],
+    master_volume: f32,
+}
+
+impl Default for Mixer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Mixer {
+    pub fn new() -> Self {
+        Self {
+            voices: Vec::new(),
+            music: Vec::new(),
+            bus_volumes: [1.0; Bus::COUNT],
+            master_volume: 1.0,
+        }
+    }
+
+    pub fn play(&mut self, id: VoiceId, sound: &Sound, settings: SoundSettings) {
+        // less than a frame, e.g. one sample of a stereo clip, has nothing to play
+        if sound.channels == 0 || sound.samples.len() < sound.channels as usize {
+            return;
+        }
+        self.voices.push(Voice {
+            id,
+            samples: Arc::clone(&sound.samples),
+            channels: sound.channels as usize,
+            sample_rate: sound.sample_rate,
+            position: 0.0,
+            settings,
+            spatial_gain: 1.0,
+            pan: 0.0,
+            culled: false,
+        });
+    }
+
+    // (gain, pan) from Emitter::spatialize, None to cull the voice.
+    pub fn set_spatial(&mut self, id: VoiceId, spatial: Option<(f32, f32)>) {
+        if let Some(voice) = self.voices.iter_mut().find(|voice| voice.id == id) {
+            match spatial {
+                Some((gain, pan)) => {
+                    voice.spatial_gain = gain;
+                    voice.pan = pan;
+                    voice.culled = false;
+                }
+                None => voice.culled = true,
+            }
+        }
+    }
+
+    pub fn stop(&mut self, id: VoiceId) {
+        self.voices.retain(|voice| voice.id != id);
+    }
+
+    pub fn is_playing(&self, id: VoiceId) -> bool {
+        self.voices.iter().any(|voice| voice.id == id)
+    }
+
+    // Fades whatever is playing out while the new track fades in over the same time.
+    pub fn play_music(&mut self, stream: MusicStream, fade_seconds: f32) {
+        self.play_feed(stream.start(), fade_seconds);
+    }
+
+    // Like play_music, for a stream whose decoder was started outside the lock.
+    pub(crate) fn play_feed(&mut self, feed: MusicFeed, fade_seconds: f32) {
+        self.stop_music(fade_seconds);
+        let channels = feed.channels.max(1) as usize;
+        self.music.push(MusicTrack {
+            feed,
+            channels,
+            buffer: Vec::new(),
+            position: 0.0,
+            gain: if fade_seconds > 0.0 { 0.0 } else { 1.0 },
+            target_gain: 1.0,
+            fade_seconds,
+            finished: false,
+        });
+    }
+
+    pub fn stop_music(&mut self, fade_seconds: f32) {
+        if fade_seconds <= 0.0 {
+            self.music.clear();
+            return;
+        }
+        for track in self.music.iter_mut() {
+            track.target_gain = 0.0;
+            track.fade_seconds = fade_seconds;
+        }
+    }
+
+    pub fn music_id(&self) -> Option<&str> {
+        self.music
+            .iter()
+            .rfind(|track| track.target_gain > 0.0)
+            .map(|track| track.feed.id.as_str())
+    }
+
+    pub fn set_bus_volume(&mut self, bus: Bus, volume: f32) {
+        self.bus_volumes[bus.index()] = volume.max(0.0);
+    }
+
+    pub fn bus_volume(&self, bus: Bus) -> f32 {
+        self.bus_volumes[bus.index()]
+    }
+
+    pub fn set_master_volume(&mut self, volume: f32) {
+        self.master_volume = volume.max(0.0);
+    }
+
+    // Mixes the next `out.len() / channels` frames into `out`.
+    pub fn render(&mut self, out: &mut [f32], channels: u16, sample_rate: u32) {
+        out.fill(0.0);
+        let channels = channels.max(1) as usize;
+        let sample_rate = sample_rate as f64;
+
+        let frames = out.len() / channels;
+        for voice in self.voices.iter_mut() {
+            let step =
+                voice.settings.pitch.max(0.01) as f64 * voice.sample_rate as f64 / sample_rate;
+            if voice.culled {
+                voice.position += step * frames as f64;
+                continue;
+            }
+
+            let gain = voice.settings.volume
+                * voice.spatial_gain
+                * self.bus_volumes[voice.settings.bus.index()]
+                * self.master_volume;
+            // only stereo outputs pan, the louder side stays at full volume
+            let channel_gains = if channels == 2 {
+                [(1.0 - voice.pan).min(1.0), (1.0 + voice.pan).min(1.0)]
+            } else {
+                [1.0, 1.0]
+            };
+            let last = voice.frames() - 1;
+
+            for frame in out.chunks_mut(channels) {
+                let index = voice.position as usize;
+                if index > last {
+                    break;
+                }
+                let t = (voice.position - index as f64) as f32;
+                for (channel, out) in frame.iter_mut().enumerate() {
+                    let a = voice.sample(index, channel);
+                    let b = voice.sample((index + 1).min(last), channel);
+                    let pan = channel_gains.get(channel).copied().unwrap_or(1.0);
+                    *out += (a + (b - a) * t) * gain * pan;
+                }
+                voice.position += step;
+            }
+        }
+        self.voices
+            .retain(|voice| (voice.position as usize) < voice.frames());
+
+        let music_gain = self.bus_volumes[Bus::Music.index()] * self.master_volume;
+        for track in self.music.iter_mut() {
+            let step = track.feed.sample_rate as f64 / sample_rate;
+            let fade_step = if track.fade_seconds > 0.0 {
+                (1.0 / (track.fade_seconds as f64 * sample_rate)) as f32
+            } else {
+                1.0
+            };
+
+            for frame in out.chunks_mut(channels) {
+                // an underrun stays silent rather than waiting on the decoder
+                if !track.fill() {
+                    break;
+                }
+                if track.is_done() {
+                    break;
+                }
+                let index = track.position as usize;
+                let t = (track.position - index as f64) as f32;
+                let gain = track.gain * music_gain;
+                for (channel, out) in frame.iter_mut().enumerate() {
+                    let a = track.sample(index, channel);
+                    let b = track.sample(index + 1, channel);
+                    *out += (a + (b - a) * t) * gain;
+                }
+                track.position += step;
+
+                if track.gain < track.target_gain {
+                    track.gain = (track.gain + fade_step).min(track.target_gain);
+                } else if track.gain > track.target_gain {
+                    track.gain = (track.gain - fade_step).max(track.target_gain);
+                }
+            }
+        }
+        self.music.retain(|track| !track.is_done());
+
+        for sample in out.iter_mut() {
+            *sample = sample.clamp(-1.0, 1.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::channel;
+
+    use super::*;
+
+    const RATE: u32 = 100;
+
+    fn constant(value: f32, channels: u16, frames: usize) -> Sound {
+        Sound {
+            samples: vec![value; frames * channels as usize].into(),
+            channels,
+            sample_rate: RATE,
+        }
+    }
+
+    fn render(mixer: &mut Mixer, frames: usize) -> Vec<f32> {
+        let mut out = vec![0.0; frames * 2];
+        mixer.render(&mut out, 2, RATE);
+        out
+    }
+
+    #[test]
+    fn mono_plays_in_both_channels_and_finishes() {
+        let mut mixer = Mixer::new();
+        mixer.play(VoiceId(1), &constant(0.5, 1, 3), SoundSettings::default());
+        assert_eq!(
+            render(&mut mixer, 4),
+            [0.5, 0.5, 0.5, 0.5, 0.5, 0.5, 0.0, 0.0]
+        );
+        assert!(!mixer.is_playing(VoiceId(1)));
+    }
+
+    #[test]
+    fn volumes_multiply() {
+        let mut mixer = Mixer::new();
+        let settings = SoundSettings {
+            volume: 0.5,
+            bus: Bus::Ui,
+            ..SoundSettings::default()
+        };
+        mixer.play(VoiceId(1), &constant(1.0, 1, 10), settings);
+        mixer.set_bus_volume(Bus::Ui, 0.5);
+        mixer.set_master_volume(0.5);
+        assert_eq!(render(&mut mixer, 1), [0.125, 0.125]);
+        mixer.set_bus_volume(Bus::Ui, -1.0);
+        assert_eq!(mixer.bus_volume(Bus::Ui), 0.0);
+        assert_eq!(render(&mut mixer, 1), [0.0, 0.0]);
+    }
+
+    #[test]
+    fn output_is_clamped() {
+        let mut mixer = Mixer::new();
+        mixer.play(VoiceId(1), &constant(0.8, 2, 4), SoundSettings::default());
+        mixer.play(VoiceId(2), &constant(0.8, 2, 4), SoundSettings::default());
+        assert_eq!(render(&mut mixer, 1), [1.0, 1.0]);
+    }
+
+    #[test]
+    fn pitch_resamples() {
+        let sound = Sound {
+            samples: vec![0.0, 0.2, 0.4, 0.6].into(),
+            channels: 1,
+            sample_rate: RATE,
+        };
+        let mut mixer = Mixer::new();
+        let settings = SoundSettings {
+            pitch: 0.5,
+            ..SoundSettings::default()
+        };
+        mixer.play(VoiceId(1), &sound, settings);
+        let out = render(&mut mixer, 3);
+        let left: Vec<f32> = out.iter().step_by(2).copied().collect();
+        assert!((left[1] - 0.1).abs() < 1e-6, "{:?}", left);
+        assert!((left[2] - 0.2).abs() < 1e-6, "{:?}", left);
+    }
+
+    #[test]
+    fn pan_keeps_the_louder_side_at_full_volume() {
+        let mut mixer = Mixer::new();
+        mixer.play(VoiceId(1), &constant(1.0, 1, 10), SoundSettings::default());
+        mixer.set_spatial(VoiceId(1), Some((0.5, -0.5)));
+        assert_eq!(render(&mut mixer, 1), [0.5, 0.25]);
+        mixer.set_spatial(VoiceId(1), Some((1.0, 1.0)));
+        assert_eq!(render(&mut mixer, 1), [0.0, 1.0]);
+    }
+
+    #[test]
+    fn culled_voices_keep_their_place() {
+        let mut mixer = Mixer::new();
+        mixer.play(VoiceId(1), &constant(1.0, 1, 4), SoundSettings::default());
+        mixer.set_spatial(VoiceId(1), None);
+        assert_eq!(render(&mut mixer, 2), [0.0; 4]);
+        assert!(mixer.is_playing(VoiceId(1)));
+
+        mixer.set_spatial(VoiceId(1), Some((1.0, 0.0)));
+        assert_eq!(render(&mut mixer, 3), [1.0, 1.0, 1.0, 1.0, 0.0, 0.0]);
+        assert!(!mixer.is_playing(VoiceId(1)));
+    }
+
+    #[test]
+    fn stop_and_empty_sounds() {
+        let mut mixer = Mixer::new();
+        mixer.play(VoiceId(1), &constant(1.0, 1, 4), SoundSettings::default());
+        mixer.stop(VoiceId(1));
+        assert!(!mixer.is_playing(VoiceId(1)));
+
+        // one sample of a stereo clip isn't a whole frame
+        let half_frame = Sound {
+            samples: vec![1.0].into(),
+            channels: 2,
+            sample_rate: RATE,
+        };
+        mixer.play(VoiceId(2), &half_frame, SoundSettings::default());
+        mixer.play(VoiceId(3), &constant(1.0, 0, 0), SoundSettings::default());
+        assert!(!mixer.is_playing(VoiceId(2)) && !mixer.is_playing(VoiceId(3)));
+        assert_eq!(render(&mut mixer, 1), [0.0, 0.0]);
+    }
+
+    #[test]
+    fn music_fades_in_and_ends_with_its_feed() {
+        let (sender, chunks) = channel();
+        sender.send(vec![1.0; 8]).unwrap();
+        drop(sender);
+        let mut mixer = Mixer::new();
+        let feed = MusicFeed {
+            id: "theme".into(),
+            channels: 1,
+            sample_rate: RATE,
+            chunks,
+        };
+        mixer.play_feed(feed, 0.04);
+        assert_eq!(mixer.music_id(), Some("theme"));
+
+        let out = render(&mut mixer, 10);
+        let left: Vec<f32> = out.iter().step_by(2).copied().collect();
+        assert_eq!(left[..4], [0.0, 0.25, 0.5, 0.75]);
+        assert_eq!(left[4..], [1.0, 1.0, 1.0, 1.0, 0.0, 0.0]);
+        assert_eq!(mixer.music_id(), None);
+    }
+
+    #[test]
+    fn stopping_music_fades_it_out() {
+        let (sender, chunks) = channel();
+        sender.send(vec![1.0; 100]).unwrap();
+        let mut mixer = Mixer::new();
+        let feed = MusicFeed {
+            id: "theme".into(),
+            channels: 1,
+            sample_rate: RATE,
+            chunks,
+        };
+        mixer.play_feed(feed, 0.0);
+        assert_eq!(render(&mut mixer, 1), [1.0, 1.0]);
+        mixer.stop_music(0.02);
+        assert_eq!(mixer.music_id(), None);
+        let out = render(&mut mixer, 4);
+        assert_eq!(out, [1.0, 1.0, 0.5, 0.5, 0.0, 0.0, 0.0, 0.0]);
+        drop(sender);
+    }
+}
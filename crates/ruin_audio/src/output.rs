@@ -0,0 +1,160 @@
+use std::sync::{Arc, Mutex};
+
+use crate::mixer::Mixer;
+
+// Where mixed audio ends up. Device outputs pull from the mixer on their own thread, the null
+// output is driven by update so playback still advances with nothing to hear it.
+pub trait AudioOutput {
+    fn sample_rate(&self) -> u32;
+    fn channels(&self) -> u16;
+    fn update(&mut self, _mixer: &Mutex<Mixer>, _dt: f32) {}
+}
+
+pub struct NullOutput {
+    sample_rate: u32,
+    channels: u16,
+    scratch: Vec<f32>,
+    // fraction of a frame carried over between updates
+    remainder: f64,
+}
+
+impl NullOutput {
+    pub fn new(sample_rate: u32, channels: u16) -> Self {
+        Self {
+            sample_rate,
+            channels,
+            scratch: Vec::new(),
+            remainder: 0.0,
+        }
+    }
+}
+
+impl Default for NullOutput {
+    fn default() -> Self {
+        Self::new(44_100, 2)
+    }
+}
+
+impl AudioOutput for NullOutput {
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn update(&mut self, mixer: &Mutex<Mixer>, dt: f32) {
+        let frames = dt.max(0.0) as f64 * self.sample_rate as f64 + self.remainder;
+        self.remainder = frames.fract();
+        self.scratch
+            .resize(frames as usize * self.channels as usize, 0.0);
+        mixer
+            .lock()
+            .unwrap()
+            .render(&mut self.scratch, self.channels, self.sample_rate);
+    }
+}
+
+#[cfg(feature = "cpal")]
+pub use device::DeviceOutput;
+
+#[cfg(feature = "cpal")]
+mod device {
+    use std::sync::{Arc, Mutex};
+
+    use anyhow::{anyhow, Result};
+    use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+
+    use super::AudioOutput;
+    use crate::mixer::Mixer;
+
+    // The system's default output device, rendering straight from the stream callback.
+    pub struct DeviceOutput {
+        _stream: cpal::Stream, // dropping this stops playback
+        sample_rate: u32,
+        channels: u16,
+    }
+
+    impl DeviceOutput {
+        pub fn open(mixer: Arc<Mutex<Mixer>>) -> Result<Self> {
+            let device = cpal::default_host()
+                .default_output_device()
+                .ok_or_else(|| anyhow!("no audio output device"))?;
+            let config = device.default_output_config()?;
+            let format = config.sample_format();
+            let config: cpal::StreamConfig = config.into();
+            let sample_rate = config.sample_rate.0;
+            let channels = config.channels;
+
+            let stream = match format {
+                cpal::SampleFormat::F32 => build_stream::<f32>(&device, &config, mixer)?,
+                cpal::SampleFormat::I16 => build_stream::<i16>(&device, &config, mixer)?,
+                cpal::SampleFormat::U16 => build_stream::<u16>(&device, &config, mixer)?,
+                other => return Err(anyhow!("unsupported output sample format {:?}", other)),
+            };
+            stream.play()?;
+
+            Ok(Self {
+                _stream: stream,
+                sample_rate,
+                channels,
+            })
+        }
+    }
+
+    // The mixer works in f32, devices wanting integer samples get it converted per callback.
+    fn build_stream<T>(
+        device: &cpal::Device,
+        config: &cpal::StreamConfig,
+        mixer: Arc<Mutex<Mixer>>,
+    ) -> Result<cpal::Stream>
+    where
+        T: cpal::SizedSample + cpal::FromSample<f32>,
+    {
+        let sample_rate = config.sample_rate.0;
+        let channels = config.channels;
+        let mut mixed: Vec<f32> = Vec::new();
+
+        let stream = device.build_output_stream(
+            config,
+            move |out: &mut [T], _| {
+                mixed.resize(out.len(), 0.0);
+                mixer
+                    .lock()
+                    .unwrap()
+                    .render(&mut mixed, channels, sample_rate);
+                for (out, sample) in out.iter_mut().zip(&mixed) {
+                    *out = T::from_sample(*sample);
+                }
+            },
+            |err| println!("Audio output error: {}", err),
+            None,
+        )?;
+        Ok(stream)
+    }
+
+    impl AudioOutput for DeviceOutput {
+        fn sample_rate(&self) -> u32 {
+            self.sample_rate
+        }
+
+        fn channels(&self) -> u16 {
+            self.channels
+        }
+    }
+}
+
+// The default device when built with the cpal feature and one is available, otherwise the
+// null output.
+pub fn open_default_output(mixer: &Arc<Mutex<Mixer>>) -> Box<dyn AudioOutput> {
+    #[cfg(feature = "cpal")]
+    match DeviceOutput::open(Arc::clone(mixer)) {
+        Ok(output) => return Box::new(output),
+        Err(err) => println!("Audio disabled: {}", err),
+    }
+    #[cfg(not(feature = "cpal"))]
+    let _ = mixer;
+
+    Box::new(NullOutput::default())
+}
@@ -0,0 +1,128 @@
+use std::io::Cursor;
+use std::sync::Arc;
+
+use anyhow::{bail, Result};
+use lewton::inside_ogg::OggStreamReader;
+use ruin_assets::{Asset, AssetLoader, LoadContext};
+
+// A fully decoded sound effect, interleaved f32 samples. Cloning shares the samples, so voices
+// keep playing after the asset is unloaded.
+#[derive(Clone)]
+pub struct Sound {
+    pub samples: Arc<[f32]>,
+    pub channels: u16,
+    pub sample_rate: u32,
+}
+
+impl Asset for Sound {}
+
+impl Sound {
+    pub fn frames(&self) -> usize {
+        self.samples.len() / self.channels.max(1) as usize
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.frames() as f32 / self.sample_rate as f32
+    }
+}
+
+pub struct SoundLoader;
+
+impl AssetLoader for SoundLoader {
+    type Asset = Sound;
+
+    fn extensions(&self) -> &[&str] {
+        &["wav", "ogg"]
+    }
+
+    fn load(&self, bytes: Vec<u8>, context: &mut LoadContext) -> Result<Sound> {
+        let mut decoder = SoundDecoder::new(bytes.into(), context.id())?;
+        let mut samples = Vec::new();
+        while let Some(chunk) = decoder.read_chunk()? {
+            samples.extend_from_slice(&chunk);
+        }
+        Ok(Sound {
+            samples: samples.into(),
+            channels: decoder.channels(),
+            sample_rate: decoder.sample_rate(),
+        })
+    }
+}
+
+// Samples read per wav chunk, ogg hands out whole packets instead.
+const WAV_CHUNK: usize = 4096;
+
+// Decodes wav or ogg a chunk at a time, so music can stream instead of sitting in memory
+// fully decoded.
+pub(crate) enum SoundDecoder {
+    Wav {
+        reader: hound::WavReader<Cursor<Arc<[u8]>>>,
+        spec: hound::WavSpec,
+    },
+    Ogg(Box<OggStreamReader<Cursor<Arc<[u8]>>>>),
+}
+
+impl SoundDecoder {
+    pub(crate) fn new(bytes: Arc<[u8]>, id: &str) -> Result<Self> {
+        if bytes.starts_with(b"RIFF") {
+            let reader = hound::WavReader::new(Cursor::new(bytes))?;
+            let spec = reader.spec();
+            Ok(SoundDecoder::Wav { reader, spec })
+        } else if bytes.starts_with(b"OggS") {
+            Ok(SoundDecoder::Ogg(Box::new(OggStreamReader::new(
+                Cursor::new(bytes),
+            )?)))
+        } else {
+            bail!("{} is neither wav nor ogg", id)
+        }
+    }
+
+    pub(crate) fn channels(&self) -> u16 {
+        match self {
+            SoundDecoder::Wav { spec, .. } => spec.channels,
+            SoundDecoder::Ogg(reader) => reader.ident_hdr.audio_channels as u16,
+        }
+    }
+
+    pub(crate) fn sample_rate(&self) -> u32 {
+        match self {
+            SoundDecoder::Wav { spec, .. } => spec.sample_rate,
+            SoundDecoder::Ogg(reader) => reader.ident_hdr.audio_sample_rate,
+        }
+    }
+
+    // Next interleaved chunk, None once the end is reached.
+    pub(crate) fn read_chunk(&mut self) -> Result<Option<Vec<f32>>> {
+        match self {
+            SoundDecoder::Wav { reader, spec } => {
+                let chunk: Vec<f32> = match spec.sample_format {
+                    hound::SampleFormat::Float => reader
+                        .samples::<f32>()
+                        .take(WAV_CHUNK)
+                        .collect::<Result<_, _>>()?,
+                    hound::SampleFormat::Int => {
+                        let scale = 1.0 / (1u64 << (spec.bits_per_sample - 1)) as f32;
+                        reader
+                            .samples::<i32>()
+                            .take(WAV_CHUNK)
+                            .map(|sample| sample.map(|s| s as f32 * scale))
+                            .collect::<Result<_, _>>()?
+                    }
+                };
+                Ok((!chunk.is_empty()).then_some(chunk))
+            }
+            SoundDecoder::Ogg(reader) => loop {
+                match reader.read_dec_packet_itl()? {
+                    // packets can decode to nothing, e.g. the first one
+                    Some(packet) if packet.is_empty() => continue,
+                    Some(packet) => {
+                        return Ok(Some(
+                            packet.into_iter().map(|s| s as f32 / 32768.0).collect(),
+                        ))
+                    }
+                    None => return Ok(None),
+                }
+            },
+        }
+    }
+}
@@ -0,0 +1,100 @@
+// Where sounds are heard from, normally the camera. Sounds `pan_width` to either side are
+// fully in one speaker, so with the half width of the view that's the screen edges.
+#[derive(Debug, Clone, Copy)]
+pub struct Listener {
+    pub position: [f32; 2],
+    pub pan_width: f32,
+}
+
+impl Default for Listener {
+    fn default() -> Self {
+        Self {
+            position: [0.0, 0.0],
+            pan_width: 1.0,
+        }
+    }
+}
+
+// A playing sound placed in the world. Full volume within `min_distance` of the listener,
+// fading out linearly to silence at `max_distance`, past which it isn't mixed at all.
+#[derive(Debug, Clone, Copy)]
+pub struct Emitter {
+    pub position: [f32; 2],
+    pub min_distance: f32,
+    pub max_distance: f32,
+}
+
+impl Emitter {
+    // (gain, pan) as heard by `listener`, None when out of range.
+    pub fn spatialize(&self, listener: &Listener) -> Option<(f32, f32)> {
+        let dx = self.position[0] - listener.position[0];
+        let dy = self.position[1] - listener.position[1];
+        let distance = (dx * dx + dy * dy).sqrt();
+        if distance >= self.max_distance {
+            return None;
+        }
+
+        let falloff = (self.max_distance - self.min_distance).max(f32::EPSILON);
+        let gain = 1.0 - ((distance - self.min_distance) / falloff).clamp(0.0, 1.0);
+        let pan = (dx / listener.pan_width.max(f32::EPSILON)).clamp(-1.0, 1.0);
+        Some((gain, pan))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn emitter(x: f32, y: f32) -> Emitter {
+        Emitter {
+            position: [x, y],
+            min_distance: 2.0,
+            max_distance: 10.0,
+        }
+    }
+
+    #[test]
+    fn full_volume_inside_min_distance() {
+        let listener = Listener::default();
+        assert_eq!(emitter(0.0, 0.0).spatialize(&listener), Some((1.0, 0.0)));
+        assert_eq!(emitter(0.0, 2.0).spatialize(&listener).unwrap().0, 1.0);
+    }
+
+    #[test]
+    fn fades_linearly_then_culls() {
+        let listener = Listener {
+            position: [10.0, 10.0],
+            pan_width: 100.0,
+        };
+        let (gain, _) = emitter(10.0, 16.0).spatialize(&listener).unwrap();
+        assert!((gain - 0.5).abs() < 1e-6);
+        assert!(emitter(10.0, 20.0).spatialize(&listener).is_none());
+        assert!(emitter(30.0, 10.0).spatialize(&listener).is_none());
+    }
+
+    #[test]
+    fn pans_by_horizontal_offset() {
+        let listener = Listener {
+            position: [0.0, 0.0],
+            pan_width: 4.0,
+        };
+        assert_eq!(emitter(-2.0, 0.0).spatialize(&listener).unwrap().1, -0.5);
+        assert_eq!(emitter(6.0, 0.0).spatialize(&listener).unwrap().1, 1.0);
+        // height doesn't pan
+        assert_eq!(emitter(0.0, 1.0).spatialize(&listener).unwrap().1, 0.0);
+    }
+
+    #[test]
+    fn degenerate_ranges_dont_divide_by_zero() {
+        let listener = Listener {
+            position: [0.0, 0.0],
+            pan_width: 0.0,
+        };
+        let point = Emitter {
+            position: [1.0, 0.0],
+            min_distance: 5.0,
+            max_distance: 5.0,
+        };
+        assert_eq!(point.spatialize(&listener), Some((1.0, 1.0)));
+    }
+}
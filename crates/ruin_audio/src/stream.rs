@@ -0,0 +1,92 @@
+use std::sync::mpsc::{sync_channel, Receiver};
+use std::sync::Arc;
+use std::thread;
+
+use anyhow::Result;
+
+use crate::sound::SoundDecoder;
+
+// Music is kept encoded and decoded a chunk at a time, on a thread of its own once it plays.
+const QUEUED_CHUNKS: usize = 16;
+
+pub struct MusicStream {
+    id: String,
+    bytes: Arc<[u8]>,
+    decoder: SoundDecoder,
+    looped: bool,
+}
+
+impl MusicStream {
+    pub fn new(id: &str, bytes: Vec<u8>, looped: bool) -> Result<Self> {
+        let bytes: Arc<[u8]> = bytes.into();
+        Ok(Self {
+            id: id.to_string(),
+            decoder: SoundDecoder::new(Arc::clone(&bytes), id)?,
+            bytes,
+            looped,
+        })
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn channels(&self) -> u16 {
+        self.decoder.channels()
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.decoder.sample_rate()
+    }
+
+    // Starts decoding on a separate thread, a few chunks ahead of what's been played, so the
+    // audio callback only has to copy samples out.
+    pub(crate) fn start(mut self) -> MusicFeed {
+        let (sender, chunks) = sync_channel(QUEUED_CHUNKS);
+        let feed = MusicFeed {
+            id: self.id.clone(),
+            channels: self.channels(),
+            sample_rate: self.sample_rate(),
+            chunks,
+        };
+        let decoder = thread::Builder::new()
+            .name(format!("music {}", self.id))
+            .spawn(move || {
+                // stops when the track ends or the feed is dropped
+                while let Some(chunk) = self.read_chunk() {
+                    if sender.send(chunk).is_err() {
+                        break;
+                    }
+                }
+            });
+        if let Err(err) = decoder {
+            println!("Music {} couldn't start: {}", feed.id, err);
+        }
+        feed
+    }
+
+    // Next interleaved chunk, starting over at the end when looping. None once a one-shot
+    // track is done or the file turns out to be broken part way through.
+    pub(crate) fn read_chunk(&mut self) -> Option<Vec<f32>> {
+        match self.decoder.read_chunk() {
+            Ok(Some(chunk)) => return Some(chunk),
+            Ok(None) if self.looped => {}
+            Ok(None) => return None,
+            Err(err) => {
+                println!("Music {} stopped: {}", self.id, err);
+                return None;
+            }
+        }
+
+        self.decoder = SoundDecoder::new(Arc::clone(&self.bytes), &self.id).ok()?;
+        self.decoder.read_chunk().ok().flatten()
+    }
+}
+
+// The playing end of a started MusicStream. The decoder thread hangs up once the track is over.
+pub(crate) struct MusicFeed {
+    pub id: String,
+    pub channels: u16,
+    pub sample_rate: u32,
+    pub chunks: Receiver<Vec<f32>>,
+}
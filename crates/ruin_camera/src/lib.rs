@@ -98,14 +98,35 @@ impl Camera2D {
         self.aspect_ratio = width as f32 / height as f32;
     }
 
+    // Half the visible world area, in world units.
+    pub fn half_extents(&self) -> [f32; 2] {
+        [self.aspect_ratio * self.zoom, self.zoom]
+    }
+
+    // World units covered by one pixel of the screen the camera draws to.
+    pub fn pixel_size(&self) -> f32 {
+        self.zoom * 2.0 / self.screen_height
+    }
+
+    // Where the camera is drawn from, rounded to whole screen pixels so sprites don't shimmer
+    // as it moves in fractions of a pixel.
+    pub fn snapped_position(&self) -> Vector2<f32> {
+        let pixel = self.pixel_size();
+        Vector2::new(
+            (self.position.x / pixel).round() * pixel,
+            (self.position.y / pixel).round() * pixel,
+        )
+    }
+
     pub fn build_matrix(&self) -> Matrix4<f32> {
         let half_width = self.aspect_ratio * self.zoom;
         let half_height = self.zoom;
+        let position = self.snapped_position();
 
-        let left = self.position.x - half_width;
-        let right = self.position.x + half_width;
-        let bottom = self.position.y - half_height;
-        let top = self.position.y + half_height;
+        let left = position.x - half_width;
+        let right = position.x + half_width;
+        let bottom = position.y - half_height;
+        let top = position.y + half_height;
 
         // z: -1 to 1, because we're not using 3D
         ortho(left, right, bottom, top, -1.0, 1.0)
@@ -128,6 +149,73 @@ impl Camera2D {
     }
 }
 
+// How the virtual resolution is scaled up to fill the window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScaleMode {
+    // largest whole multiple that fits, bars around the rest
+    Integer,
+    // largest scale that keeps the aspect ratio, bars on two sides
+    Fit,
+    // fills the whole window, pixels may end up uneven or squashed
+    Stretch,
+}
+
+impl FromStr for ScaleMode {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<ScaleMode, ()> {
+        match s.to_lowercase().as_str() {
+            "integer" | "pixel_perfect" => Ok(ScaleMode::Integer),
+            "fit" | "letterbox" => Ok(ScaleMode::Fit),
+            "stretch" => Ok(ScaleMode::Stretch),
+            _ => Err(()),
+        }
+    }
+}
+
+impl ScaleMode {
+    // Where a screen of `virtual_size` is drawn inside a window of `window_size`.
+    pub fn viewport(&self, virtual_size: [u32; 2], window_size: [u32; 2]) -> Viewport {
+        let [virtual_w, virtual_h] = [virtual_size[0].max(1) as f32, virtual_size[1].max(1) as f32];
+        let [window_w, window_h] = [window_size[0].max(1) as f32, window_size[1].max(1) as f32];
+        let fit = (window_w / virtual_w).min(window_h / virtual_h);
+
+        let (width, height) = match self {
+            // windows smaller than the virtual resolution fall back to fitting
+            ScaleMode::Integer if fit >= 1.0 => (virtual_w * fit.floor(), virtual_h * fit.floor()),
+            ScaleMode::Integer | ScaleMode::Fit => (virtual_w * fit, virtual_h * fit),
+            ScaleMode::Stretch => (window_w, window_h),
+        };
+        Viewport {
+            // whole pixels, so integer scaling lines up with the window's pixels
+            x: ((window_w - width) / 2.0).floor(),
+            y: ((window_h - height) / 2.0).floor(),
+            width,
+            height,
+        }
+    }
+}
+
+// A rectangle of the window in pixels, y down.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Viewport {
+    // Maps a window position into a screen of `size` drawn in this viewport. Positions over
+    // the bars land outside the screen.
+    pub fn to_screen(&self, position: [f32; 2], size: [u32; 2]) -> [f32; 2] {
+        [
+            (position[0] - self.x) / self.width * size[0] as f32,
+            (position[1] - self.y) / self.height * size[1] as f32,
+        ]
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub enum Dimensions {
     Two,
@@ -150,3 +238,86 @@ impl FromStr for CameraOption {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn viewport(x: f32, y: f32, width: f32, height: f32) -> Viewport {
+        Viewport {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn integer_scales_by_whole_multiples() {
+        assert_eq!(
+            ScaleMode::Integer.viewport([320, 180], [1280, 720]),
+            viewport(0.0, 0.0, 1280.0, 720.0)
+        );
+        // 3.4x fits, 3x is used and the rest is split into bars
+        assert_eq!(
+            ScaleMode::Integer.viewport([320, 180], [1100, 700]),
+            viewport(70.0, 80.0, 960.0, 540.0)
+        );
+    }
+
+    #[test]
+    fn integer_fits_windows_smaller_than_the_screen() {
+        assert_eq!(
+            ScaleMode::Integer.viewport([320, 180], [160, 160]),
+            viewport(0.0, 35.0, 160.0, 90.0)
+        );
+    }
+
+    #[test]
+    fn fit_keeps_the_aspect_ratio() {
+        assert_eq!(
+            ScaleMode::Fit.viewport([320, 180], [1000, 1000]),
+            viewport(0.0, 218.0, 1000.0, 562.5)
+        );
+        assert_eq!(
+            ScaleMode::Fit.viewport([100, 100], [300, 200]),
+            viewport(50.0, 0.0, 200.0, 200.0)
+        );
+    }
+
+    #[test]
+    fn stretch_fills_the_window() {
+        assert_eq!(
+            ScaleMode::Stretch.viewport([320, 180], [1000, 1000]),
+            viewport(0.0, 0.0, 1000.0, 1000.0)
+        );
+    }
+
+    #[test]
+    fn zero_sizes_dont_divide_by_zero() {
+        let minimized = ScaleMode::Integer.viewport([320, 180], [0, 0]);
+        assert!(minimized.width.is_finite() && minimized.height.is_finite());
+        let empty = ScaleMode::Fit.viewport([0, 0], [100, 50]);
+        assert_eq!(empty, viewport(25.0, 0.0, 50.0, 50.0));
+    }
+
+    #[test]
+    fn maps_window_positions_onto_the_screen() {
+        let viewport = ScaleMode::Integer.viewport([320, 180], [1100, 700]);
+        assert_eq!(viewport.to_screen([70.0, 80.0], [320, 180]), [0.0, 0.0]);
+        assert_eq!(
+            viewport.to_screen([550.0, 350.0], [320, 180]),
+            [160.0, 90.0]
+        );
+        // over the bars
+        assert!(viewport.to_screen([10.0, 10.0], [320, 180])[0] < 0.0);
+    }
+
+    #[test]
+    fn scale_mode_names() {
+        assert_eq!("pixel_perfect".parse(), Ok(ScaleMode::Integer));
+        assert_eq!("Letterbox".parse(), Ok(ScaleMode::Fit));
+        assert_eq!("stretch".parse(), Ok(ScaleMode::Stretch));
+        assert_eq!("zoom".parse::<ScaleMode>(), Err(()));
+    }
+}
@@ -1,11 +1,12 @@
 use std::collections::{HashMap, HashSet};
 
 use cgmath::{Array, InnerSpace, Vector2};
-use ruin_assets::{Handle, ImageTexture};
+use ruin_assets::TextureRegion;
 use ruin_ecs::{
     physics_2d::{HalfExtents, Shape2D},
     world::{RenderElement2D, RenderQueue2D},
-    ActionState, ActionStateComponent, Animation, AnimationComponent, Entity,
+    ActionState, ActionStateComponent, Animation, AnimationComponent, ColorMatrix, Entity,
+    RenderLayer,
 };
 use ruin_lua_runtime::LuaExtendedExecutor;
 
@@ -21,6 +22,18 @@ pub struct CanvasNode {
     active_elements: Vec<Index>,
 }
 
+impl CanvasNode {
+    fn retexture(&mut self, sprite: &str, region: &TextureRegion) {
+        if self.animation.retexture(sprite, region) {
+            self.animation
+                .refresh_current_frame(&ActionState::Custom(0));
+        }
+        for element in self.elements.iter_mut() {
+            element.retexture(sprite, region);
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct CanvasView {
     pub elements: Vec<CanvasNode>,
@@ -57,6 +70,14 @@ impl Canvas {
         self.action_states.clear();
     }
 
+    pub fn retexture(&mut self, sprite: &str, region: &TextureRegion) {
+        for view in self.views.values_mut() {
+            for element in view.elements.iter_mut() {
+                element.retexture(sprite, region);
+            }
+        }
+    }
+
     pub fn new_entity(&mut self) -> Entity {
         let e = self.next_id;
         self.next_id += 1;
@@ -99,6 +120,8 @@ impl Canvas {
                     position: element.position.into(),
                     size: element.scale.into(),
                     z_order: 0.0,
+                    layer: RenderLayer::Foreground,
+                    order: 0,
                     image_texture: element
                         .animation
                         .animations
@@ -106,6 +129,11 @@ impl Canvas {
                         .unwrap()
                         .sprite_sheet_id,
                     uv_coords: element.animation.current_frame.uv_coords,
+                    rotation: 0.0,
+                    pivot: [0.0, 0.0],
+                    tint: [1.0; 4],
+                    flash: [0.0; 4],
+                    color_matrix: ColorMatrix::IDENTITY,
                 });
             }
         }
@@ -117,7 +145,7 @@ impl Canvas {
 // Lua
 pub fn parse_canvas_view_from_lua(
     table: mlua::Table,
-    texture_loader: &mut impl FnMut(String) -> Handle<ImageTexture>,
+    texture_loader: &mut impl FnMut(String) -> TextureRegion,
 ) -> (CanvasView, bool) {
     let elements_table: mlua::Table = table.get("elements").unwrap();
 
@@ -145,7 +173,7 @@ pub fn parse_canvas_view_from_lua(
 
 fn parse_element_from_lua(
     table: mlua::Table,
-    texture_loader: &mut impl FnMut(String) -> Handle<ImageTexture>,
+    texture_loader: &mut impl FnMut(String) -> TextureRegion,
 ) -> (CanvasNode, bool) {
     println!("{:?}", LuaExtendedExecutor::pretty_print_table(&table, 0));
     let first: mlua::Table = table.get("animations").unwrap();
@@ -1,5 +1,5 @@
 use cgmath::Vector2;
-use ruin_assets::{Handle, ImageTexture};
+use ruin_assets::{normalize_asset_id, Handle, ImageTexture, TextureRegion};
 use ruin_bitmaps::vecbool_to_u8;
 use std::collections::HashMap;
 
@@ -16,13 +16,20 @@ pub struct SpriteFrame {
     pub duration: f32,
     pub hitboxes: Vec<Area2D>,
     pub hurtboxes: Vec<Area2D>,
-    pub frame_pixel_dims: [f32; 2],
+    pub frame_pixel_dims: [f32; 2], // untrimmed source frame size
+    pub source_rect: [f32; 4],      // x, y, w, h in sprite sheet pixels (origin top-left)
+    // Trimmed frames only cover part of the source frame. These place the drawn quad
+    // relative to the pivot, as fractions of the source frame so they follow Transform2D scale.
+    pub trim_offset: [f32; 2],
+    pub trim_scale: [f32; 2],
 }
 
 #[derive(Debug, Clone)]
 pub struct Animation {
     // this should change to TextureId
     pub sprite_sheet_id: Handle<ImageTexture>,
+    pub sprite: String,         // asset id of the sprite sheet
+    pub source: Option<String>, // key the Lua side rebuilds this animation under
     pub is_transparent: bool,
     pub frames: Vec<SpriteFrame>,
     pub looped: bool,
@@ -36,10 +43,63 @@ pub struct AnimationComponent {
     pub frame_timer: f32,
 }
 
+impl SpriteFrame {
+    pub fn update_uv_coords(&mut self, region: &TextureRegion) {
+        let [x, y, w, h] = self.source_rect;
+        let [sheet_w, sheet_h] = region.size().map(|v| v as f32);
+
+        let u0 = x / sheet_w;
+        let u1 = (x + w) / sheet_w;
+        let v1 = 1.0 - (y / sheet_h);
+        let v0 = 1.0 - ((y + h) / sheet_h);
+
+        // WGPU uses origin at top-left by default. Flip V if needed.
+        // Sheets may be packed into an atlas page, so map into the backing texture last.
+        self.uv_coords = [
+            region.remap_uv([u0, v1]), // bottom-left
+            region.remap_uv([u1, v1]), // bottom-right
+            region.remap_uv([u1, v0]), // top-right
+            region.remap_uv([u0, v0]), // top-left
+        ];
+    }
+}
+
+impl AnimationComponent {
+    // Points every animation drawn from `sprite` at its reloaded region.
+    pub fn retexture(&mut self, sprite: &str, region: &TextureRegion) -> bool {
+        let mut changed = false;
+        for animation in self.animations.values_mut() {
+            if animation.sprite == sprite {
+                animation.retexture(region);
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    // Re-syncs the cached current frame after the animations changed underneath it.
+    pub fn refresh_current_frame(&mut self, state: &ActionState) {
+        if let Some(animation) = self.animations.get(state) {
+            if animation.frames.is_empty() {
+                return;
+            }
+            self.current_frame_index = self.current_frame_index.min(animation.frames.len() - 1);
+            self.current_frame = animation.frames[self.current_frame_index].clone();
+        }
+    }
+}
+
 impl Animation {
+    pub fn retexture(&mut self, region: &TextureRegion) {
+        self.sprite_sheet_id = region.texture;
+        for frame in self.frames.iter_mut() {
+            frame.update_uv_coords(region);
+        }
+    }
+
     pub fn raw_from_lua_table(
         table: mlua::Table,
-        texture_loader: &mut impl FnMut(String) -> Handle<ImageTexture>,
+        texture_loader: &mut impl FnMut(String) -> TextureRegion,
     ) -> Self {
         let looped: bool = table.get("looped").unwrap_or(true);
         let is_transparent: bool = table.get("is_transparent").unwrap_or(false);
@@ -53,50 +113,20 @@ impl Animation {
             .get("sprite")
             .expect("Sprite Sheet is required for animation.");
         println!("SPRITE {:?}", sprite_path);
-
-        //let tex_w: f32 = table.get("tex_width").unwrap();
-        //let tex_h: f32 = table.get("tex_height").unwrap();
-        let tex_w = 384.0;
-        let tex_h = 32.0;
+        let sprite = normalize_asset_id(&sprite_path);
+        let region = texture_loader(sprite.clone());
 
         let mut frames = Vec::new();
-        for (i, pair) in frames_table.sequence_values::<mlua::Table>().enumerate() {
-            if let Ok(frame_data) = pair {
-                let x: f32 = frame_data.get("x").unwrap();
-                let y: f32 = frame_data.get("y").unwrap();
-                let w: f32 = frame_data.get("w").unwrap_or(1.0);
-                let h: f32 = frame_data.get("h").unwrap_or(1.0);
-
-                let duration: f32 = frame_data.get("duration").unwrap_or(1.0);
-
-                let u0 = x / tex_w;
-                let u1 = (x + w) / tex_w;
-                let v1 = 1.0 - (y / tex_h);
-                let v0 = 1.0 - ((y + h) / tex_h);
-
-                // WGPU uses origin at top-left by default. Flip Y if needed.
-                let uv_coords = [[u0, v1], [u1, v1], [u1, v0], [u0, v0]];
-
-                // Lets base shape off of half extents for now
-                let shape = Shape2D::Rectangle {
-                    half_extents: HalfExtents { x: w, y: h },
-                };
-
-                let frame_pixel_dims = [16.0, 16.0]; // I really don't know why I need this right
-                                                     // now
-                frames.push(SpriteFrame {
-                    shape,
-                    uv_coords,
-                    duration,
-                    hitboxes: Vec::new(),
-                    hurtboxes: Vec::new(),
-                    frame_pixel_dims,
-                });
-            }
+        for frame_data in frames_table.sequence_values::<mlua::Table>().flatten() {
+            let mut frame = parse_frame_from_table(&frame_data, None);
+            frame.update_uv_coords(&region);
+            frames.push(frame);
         }
 
         Animation {
-            sprite_sheet_id: texture_loader(sprite_path),
+            sprite_sheet_id: region.texture,
+            sprite,
+            source: table.get("source").ok(),
             frames,
             looped,
             is_transparent,
@@ -105,7 +135,7 @@ impl Animation {
 
     pub fn from_lua_table(
         table: mlua::Table,
-        texture_loader: &mut impl FnMut(String) -> Handle<ImageTexture>,
+        texture_loader: &mut impl FnMut(String) -> TextureRegion,
     ) -> Self {
         let looped: bool = table.get("looped").unwrap_or(true);
         let is_transparent: bool = table.get("is_transparent").unwrap_or(false);
@@ -116,57 +146,34 @@ impl Animation {
         let hitboxes: mlua::Table = table.get("hitboxes").unwrap();
         let hurtboxes: mlua::Table = table.get("hurtboxes").unwrap();
 
-        let tex_w: f32 = table.get("sprite_sheet_width").unwrap();
-        let tex_h: f32 = table.get("sprite_sheet_height").unwrap();
         let tile_w: f32 = table.get("tile_width").unwrap();
         let tile_h: f32 = table.get("tile_height").unwrap();
 
         let sprite_path: String = table
             .get("sprite")
             .expect("Sprite Sheet is required for animation.");
+        let sprite = normalize_asset_id(&sprite_path);
+        let region = texture_loader(sprite.clone());
 
         let mut frames = Vec::new();
         for (i, pair) in frames_table.sequence_values::<mlua::Table>().enumerate() {
             if let Ok(frame_data) = pair {
-                let x: f32 = frame_data.get("x").unwrap();
-                let y: f32 = frame_data.get("y").unwrap();
-                // h,w are currently not really being set from lua
-                let w: f32 = frame_data.get("width").unwrap_or(1.0);
-                let h: f32 = frame_data.get("height").unwrap_or(1.0);
-                let duration: f32 = frame_data.get("duration").unwrap_or(1.0);
-
-                // Convert to UVs (and optionally flip Y if needed)
-                let u0 = x / tex_w;
-                let u1 = (x + w) / tex_w;
-                let v1 = 1.0 - (y / tex_h);
-                let v0 = 1.0 - ((y + h) / tex_h);
-
-                // WGPU uses origin at top-left by default. Flip V if needed.
-                let uv_coords = [
-                    [u0, v1], // bottom-left
-                    [u1, v1], // bottom-right
-                    [u1, v0], // top-right
-                    [u0, v0], // top-left
-                ];
-                // Lets base shape off of half extents for now
-                let shape = Shape2D::Rectangle {
-                    half_extents: HalfExtents { x: w, y: h },
-                };
-
-                let frame_pixel_dims = [tile_w, tile_h];
-                frames.push(SpriteFrame {
-                    shape,
-                    uv_coords,
-                    duration,
-                    hitboxes: parse_hitboxes_from_table(&hitboxes, i, frame_pixel_dims),
-                    hurtboxes: parse_hitboxes_from_table(&hurtboxes, i, frame_pixel_dims),
-                    frame_pixel_dims,
-                });
+                let mut frame = parse_frame_from_table(&frame_data, Some([tile_w, tile_h]));
+                frame.update_uv_coords(&region);
+
+                let pivot = parse_pivot_from_table(&frame_data, frame.frame_pixel_dims);
+                frame.hitboxes =
+                    parse_hitboxes_from_table(&hitboxes, i, frame.frame_pixel_dims, pivot);
+                frame.hurtboxes =
+                    parse_hitboxes_from_table(&hurtboxes, i, frame.frame_pixel_dims, pivot);
+                frames.push(frame);
             }
         }
 
         Animation {
-            sprite_sheet_id: texture_loader(sprite_path),
+            sprite_sheet_id: region.texture,
+            sprite,
+            source: table.get("source").ok(),
             frames,
             looped,
             is_transparent,
@@ -174,10 +181,61 @@ impl Animation {
     }
 }
 
+// Frames are pixel rects into the sprite sheet. Trimmed frames (packed atlases) also carry
+// their untrimmed source size and where the trimmed rect sits inside it.
+fn parse_frame_from_table(
+    frame_data: &mlua::Table,
+    default_source: Option<[f32; 2]>,
+) -> SpriteFrame {
+    let x: f32 = frame_data.get("x").unwrap();
+    let y: f32 = frame_data.get("y").unwrap();
+    let w: f32 = frame_data.get("w").unwrap_or(1.0);
+    let h: f32 = frame_data.get("h").unwrap_or(1.0);
+    let duration: f32 = frame_data.get("duration").unwrap_or(1.0);
+
+    let default_source = default_source.unwrap_or([w, h]);
+    let source_w: f32 = frame_data.get("source_w").unwrap_or(default_source[0]);
+    let source_h: f32 = frame_data.get("source_h").unwrap_or(default_source[1]);
+    let trim_x: f32 = frame_data.get("trim_x").unwrap_or(0.0);
+    let trim_y: f32 = frame_data.get("trim_y").unwrap_or(0.0);
+    let frame_pixel_dims = [source_w, source_h];
+    let pivot = parse_pivot_from_table(frame_data, frame_pixel_dims);
+
+    // Center of the trimmed rect relative to the pivot, flipped to y-up.
+    let offset_x = (trim_x + w * 0.5) - pivot[0];
+    let offset_y = pivot[1] - (trim_y + h * 0.5);
+
+    // Lets base shape off of half extents for now
+    let shape = Shape2D::Rectangle {
+        half_extents: HalfExtents { x: w, y: h },
+    };
+
+    SpriteFrame {
+        shape,
+        uv_coords: [[0.0; 2]; 4],
+        duration,
+        hitboxes: Vec::new(),
+        hurtboxes: Vec::new(),
+        frame_pixel_dims,
+        source_rect: [x, y, w, h],
+        trim_offset: [offset_x / source_w, offset_y / source_h],
+        trim_scale: [w / source_w, h / source_h],
+    }
+}
+
+// Pivot in source frame pixels (origin top-left). Defaults to the frame center.
+fn parse_pivot_from_table(frame_data: &mlua::Table, frame_size: [f32; 2]) -> [f32; 2] {
+    [
+        frame_data.get("pivot_x").unwrap_or(frame_size[0] * 0.5),
+        frame_data.get("pivot_y").unwrap_or(frame_size[1] * 0.5),
+    ]
+}
+
 fn parse_hitboxes_from_table(
     table: &mlua::Table,
     index: usize,
     frame_size: [f32; 2],
+    pivot: [f32; 2],
 ) -> Vec<Area2D> {
     let mut boxes = Vec::new();
     let frame_boxes: mlua::Table = match table.get((index + 1) as i64) {
@@ -194,11 +252,12 @@ fn parse_hitboxes_from_table(
             let layers: [bool; 8] = b.get("layers").unwrap_or_default();
             let masks: [bool; 8] = b.get("masks").unwrap_or_default();
 
-            let frame_center_x = frame_size[0] * 0.5;
-            let frame_center_y = frame_size[1] * 0.5;
+            // hitbox centers are already y-up, pivot is y-down
+            let pivot_x = pivot[0];
+            let pivot_y = frame_size[1] - pivot[1];
 
-            let offset_x = x - frame_center_x;
-            let offset_y = y - frame_center_y;
+            let offset_x = x - pivot_x;
+            let offset_y = y - pivot_y;
 
             boxes.push(Area2D {
                 shape: Shape2D::Rectangle {
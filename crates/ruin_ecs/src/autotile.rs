@@ -0,0 +1,231 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+// Neighbour bits, clockwise from north. Wang16 only uses the four edges.
+pub const NORTH: u8 = 1;
+pub const NORTH_EAST: u8 = 2;
+pub const EAST: u8 = 4;
+pub const SOUTH_EAST: u8 = 8;
+pub const SOUTH: u8 = 16;
+pub const SOUTH_WEST: u8 = 32;
+pub const WEST: u8 = 64;
+pub const NORTH_WEST: u8 = 128;
+
+// (bit, dx, dy) with y pointing down the map
+const NEIGHBOURS: [(u8, i32, i32); 8] = [
+    (NORTH, 0, -1),
+    (NORTH_EAST, 1, -1),
+    (EAST, 1, 0),
+    (SOUTH_EAST, 1, 1),
+    (SOUTH, 0, 1),
+    (SOUTH_WEST, -1, 1),
+    (WEST, -1, 0),
+    (NORTH_WEST, -1, -1),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AutotileMode {
+    // edges only, 16 tiles
+    Wang16,
+    // edges and the corners between two connected edges, 47 tiles
+    Blob47,
+}
+
+impl FromStr for AutotileMode {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<AutotileMode, ()> {
+        match s.to_lowercase().as_str() {
+            "wang16" | "16" => Ok(AutotileMode::Wang16),
+            "blob47" | "blob" | "47" => Ok(AutotileMode::Blob47),
+            _ => Err(()),
+        }
+    }
+}
+
+impl AutotileMode {
+    // Drops the bits this mode ignores. Blob corners only count when both edges next to
+    // them are connected, which is what brings 256 masks down to 47.
+    pub fn reduce(&self, mask: u8) -> u8 {
+        let edges = mask & (NORTH | EAST | SOUTH | WEST);
+        match self {
+            AutotileMode::Wang16 => edges,
+            AutotileMode::Blob47 => {
+                let mut reduced = edges;
+                for (corner, a, b) in [
+                    (NORTH_EAST, NORTH, EAST),
+                    (SOUTH_EAST, SOUTH, EAST),
+                    (SOUTH_WEST, SOUTH, WEST),
+                    (NORTH_WEST, NORTH, WEST),
+                ] {
+                    if mask & corner != 0 && edges & a != 0 && edges & b != 0 {
+                        reduced |= corner;
+                    }
+                }
+                reduced
+            }
+        }
+    }
+
+    // Every mask the mode can produce, ascending. Tilesets laid out in this order can be
+    // configured with just their first tile.
+    pub fn masks(&self) -> Vec<u8> {
+        (0..=255u8)
+            .filter(|mask| self.reduce(*mask) == *mask)
+            .collect()
+    }
+}
+
+// A terrain painted onto a tilemap. Each cell of the terrain shows the tile matching which of
+// its neighbours are the same terrain.
+#[derive(Debug, Clone)]
+pub struct Autotile {
+    pub name: String,
+    pub mode: AutotileMode,
+    pub tiles: HashMap<u8, u32>, // reduced neighbour mask to tile
+}
+
+impl Autotile {
+    pub fn contains(&self, tile: u32) -> bool {
+        self.tiles.values().any(|own| *own == tile)
+    }
+
+    // Falls back to the edges-only tile, then the isolated tile, for masks the tileset lacks.
+    pub fn tile_for(&self, mask: u8) -> Option<u32> {
+        let mask = self.mode.reduce(mask);
+        self.tiles
+            .get(&mask)
+            .or_else(|| self.tiles.get(&AutotileMode::Wang16.reduce(mask)))
+            .or_else(|| self.tiles.get(&0))
+            .copied()
+    }
+
+    // {
+    //   name,
+    //   mode = "blob47" | "wang16",
+    //   first_tile,                -- tiles laid out in ascending mask order
+    //   tiles = { [mask] = tile }, -- or any other layout, mask bits clockwise from north = 1
+    // }
+    pub fn from_lua_table(table: mlua::Table) -> mlua::Result<Self> {
+        let name: String = table.get("name")?;
+        let mode: String = table.get("mode")?;
+        let mode = AutotileMode::from_str(&mode).map_err(|_| {
+            mlua::Error::RuntimeError(format!("unknown autotile mode {} for {}", mode, name))
+        })?;
+
+        let mut tiles = HashMap::new();
+        if let Ok(first_tile) = table.get::<u32>("first_tile") {
+            for (offset, mask) in mode.masks().into_iter().enumerate() {
+                tiles.insert(mask, first_tile + offset as u32);
+            }
+        }
+        if let Ok(tiles_table) = table.get::<mlua::Table>("tiles") {
+            for pair in tiles_table.pairs::<u8, u32>() {
+                let (mask, tile) = pair?;
+                tiles.insert(mode.reduce(mask), tile);
+            }
+        }
+
+        Ok(Self { name, mode, tiles })
+    }
+}
+
+// Neighbour mask of the cell at `x`, `y`, given which cells belong to the terrain.
+pub fn neighbour_mask(x: u32, y: u32, same: impl Fn(i64, i64) -> bool) -> u8 {
+    NEIGHBOURS
+        .iter()
+        .filter(|(_, dx, dy)| same(x as i64 + *dx as i64, y as i64 + *dy as i64))
+        .fold(0, |mask, (bit, _, _)| mask | bit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn terrain(rows: &'static [&'static str]) -> impl Fn(i64, i64) -> bool {
+        move |x, y| {
+            x >= 0
+                && y >= 0
+                && rows
+                    .get(y as usize)
+                    .and_then(|row| row.as_bytes().get(x as usize))
+                    == Some(&b'#')
+        }
+    }
+
+    #[test]
+    fn mode_counts() {
+        assert_eq!(AutotileMode::Wang16.masks().len(), 16);
+        assert_eq!(AutotileMode::Blob47.masks().len(), 47);
+        assert_eq!(AutotileMode::Blob47.masks()[..3], [0, 1, 4]);
+    }
+
+    #[test]
+    fn blob_corners_need_both_edges() {
+        let blob = AutotileMode::Blob47;
+        assert_eq!(blob.reduce(NORTH_EAST), 0);
+        assert_eq!(blob.reduce(NORTH | NORTH_EAST), NORTH);
+        assert_eq!(
+            blob.reduce(NORTH | NORTH_EAST | EAST),
+            NORTH | NORTH_EAST | EAST
+        );
+        assert_eq!(blob.reduce(255), 255);
+        assert_eq!(
+            AutotileMode::Wang16.reduce(255),
+            NORTH | EAST | SOUTH | WEST
+        );
+    }
+
+    #[test]
+    fn neighbour_masks() {
+        let same = terrain(&["##.", "###", ".#."]);
+        assert_eq!(
+            neighbour_mask(1, 1, &same),
+            NORTH | EAST | SOUTH | WEST | NORTH_WEST
+        );
+        // cells past the edge of the map are never the same terrain
+        assert_eq!(neighbour_mask(0, 0, &same), EAST | SOUTH | SOUTH_EAST);
+        assert_eq!(neighbour_mask(2, 2, &same), NORTH | WEST | NORTH_WEST);
+        assert_eq!(neighbour_mask(5, 5, &same), 0);
+    }
+
+    #[test]
+    fn tiles_fall_back_to_edges_then_isolated() {
+        let autotile = Autotile {
+            name: "grass".into(),
+            mode: AutotileMode::Blob47,
+            tiles: HashMap::from([(0, 10), (NORTH | EAST, 11)]),
+        };
+        assert_eq!(autotile.tile_for(NORTH | EAST), Some(11));
+        assert_eq!(autotile.tile_for(NORTH | NORTH_EAST | EAST), Some(11));
+        assert_eq!(autotile.tile_for(SOUTH), Some(10));
+        assert!(autotile.contains(11) && !autotile.contains(12));
+        let empty = Autotile {
+            tiles: HashMap::new(),
+            ..autotile
+        };
+        assert_eq!(empty.tile_for(0), None);
+    }
+
+    #[test]
+    fn from_lua_table() {
+        let lua = mlua::Lua::new();
+        let table: mlua::Table = lua
+            .load(r#"{ name = "cave", mode = "WANG16", first_tile = 100, tiles = { [255] = 7 } }"#)
+            .eval()
+            .unwrap();
+        let autotile = Autotile::from_lua_table(table).unwrap();
+        assert_eq!(autotile.mode, AutotileMode::Wang16);
+        assert_eq!(autotile.tile_for(0), Some(100));
+        assert_eq!(autotile.tile_for(NORTH), Some(101));
+        assert_eq!(autotile.tile_for(EAST), Some(102));
+        // overrides are reduced like the masks they're looked up with
+        assert_eq!(autotile.tile_for(NORTH | EAST | SOUTH | WEST), Some(7));
+
+        let table: mlua::Table = lua
+            .load(r#"{ name = "cave", mode = "hex" }"#)
+            .eval()
+            .unwrap();
+        assert!(Autotile::from_lua_table(table).is_err());
+    }
+}
@@ -1,5 +1,18 @@
+use cgmath::Vector2;
+
+// Sprites are mirrored by swapping their UVs, offsets inside the sprite mirror with them.
 #[derive(Debug, Clone, Copy, Default)]
 pub struct FlipComponent {
     pub x: bool, // flip horizontally
     pub y: bool, // flip vertically
 }
+
+impl FlipComponent {
+    // -1 along each flipped axis, 1 otherwise. Multiply offsets by this to mirror them.
+    pub fn signs(&self) -> Vector2<f32> {
+        Vector2::new(
+            if self.x { -1.0 } else { 1.0 },
+            if self.y { -1.0 } else { 1.0 },
+        )
+    }
+}
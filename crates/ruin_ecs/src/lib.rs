@@ -1,10 +1,16 @@
 mod action_state;
 mod animation;
+mod autotile;
 mod entity;
 mod flip;
 mod health;
+mod light;
+mod particle;
+mod render_layer;
 mod scene;
+mod sprite_effect;
 mod sprite_sheet;
+mod tilemap;
 mod transform;
 
 pub mod physics_2d;
@@ -12,9 +18,18 @@ pub mod world;
 
 pub use action_state::{set_entity_state, ActionState, ActionStateComponent};
 pub use animation::{animation_system_update_frames, Animation, AnimationComponent, SpriteFrame};
+pub use autotile::{Autotile, AutotileMode};
 pub use entity::Entity;
 pub use flip::FlipComponent;
 pub use health::{damage, HealthComponent};
+pub use light::{light_system_update, LightComponent, LightKind, Lighting};
+pub use particle::{
+    particle_system_update, Curve, Particle, ParticleBlend, ParticleEmitterComponent,
+    ParticleEmitterConfig, ParticleSprite, RandomRange,
+};
+pub use render_layer::{RenderLayer, RenderLayerComponent, SortMode};
 pub use scene::{Element, Scene};
+pub use sprite_effect::{sprite_effect_system_update, ColorMatrix, SpriteEffectComponent};
 pub use sprite_sheet::SpriteSheetComponent;
+pub use tilemap::{TileLayer, TilemapComponent, Tileset, EMPTY_TILE};
 pub use transform::Transform2D;
@@ -0,0 +1,172 @@
+use std::f32::consts::TAU;
+
+use cgmath::Vector2;
+
+use crate::{world::World, Entity};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LightKind {
+    Point,
+    // a cone around `direction` (radians, 0 pointing right), `angle` wide. `softness` is the
+    // fraction of it that fades out towards its edges.
+    Spot {
+        direction: f32,
+        angle: f32,
+        softness: f32,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LightComponent {
+    pub kind: LightKind,
+    pub color: [f32; 3],
+    pub intensity: f32,
+    pub radius: f32,  // world units, nothing past it is lit
+    pub falloff: f32, // exponent on (1 - distance / radius), 1 fades linearly
+    pub height: f32,  // above the ground in world units, only normal maps notice it
+    pub cast_shadows: bool,
+    pub position: Vector2<f32>,
+    pub follow: Option<Entity>,
+    pub offset: Vector2<f32>, // from the followed entity, mirrored with its flip
+    mirror: Vector2<f32>,
+}
+
+impl Default for LightComponent {
+    fn default() -> Self {
+        Self {
+            kind: LightKind::Point,
+            color: [1.0; 3],
+            intensity: 1.0,
+            radius: 5.0,
+            falloff: 2.0,
+            height: 1.0,
+            cast_shadows: true,
+            position: Vector2::new(0.0, 0.0),
+            follow: None,
+            offset: Vector2::new(0.0, 0.0),
+            mirror: Vector2::new(1.0, 1.0),
+        }
+    }
+}
+
+impl LightComponent {
+    // {kind, x, y, color, intensity, radius, falloff, height, shadows, offset, direction,
+    // angle, softness}, keys left out keep their current value. Angles are in degrees.
+    pub fn apply_lua_table(&mut self, table: &mlua::Table) -> mlua::Result<()> {
+        if let Some(kind) = table.get::<Option<String>>("kind")? {
+            self.kind = match kind.as_str() {
+                "point" => LightKind::Point,
+                "spot" => match self.kind {
+                    LightKind::Spot { .. } => self.kind,
+                    LightKind::Point => LightKind::Spot {
+                        direction: 0.0,
+                        angle: TAU / 4.0,
+                        softness: 0.25,
+                    },
+                },
+                _ => {
+                    return Err(mlua::Error::RuntimeError(format!(
+                        "unknown light kind {}",
+                        kind
+                    )))
+                }
+            };
+        }
+        if let LightKind::Spot {
+            direction,
+            angle,
+            softness,
+        } = &mut self.kind
+        {
+            if let Some(degrees) = table.get::<Option<f32>>("direction")? {
+                *direction = degrees.to_radians();
+            }
+            if let Some(degrees) = table.get::<Option<f32>>("angle")? {
+                *angle = degrees.to_radians().clamp(0.0, TAU);
+            }
+            *softness = table
+                .get::<Option<f32>>("softness")?
+                .unwrap_or(*softness)
+                .clamp(0.0, 1.0);
+        }
+
+        if let Some(color) = table.get::<Option<mlua::Table>>("color")? {
+            self.color = [color.get(1)?, color.get(2)?, color.get(3)?];
+        }
+        if let Some(offset) = table.get::<Option<mlua::Table>>("offset")? {
+            self.offset = Vector2::new(offset.get(1)?, offset.get(2)?);
+        }
+        self.position.x = table.get::<Option<f32>>("x")?.unwrap_or(self.position.x);
+        self.position.y = table.get::<Option<f32>>("y")?.unwrap_or(self.position.y);
+        self.intensity = table
+            .get::<Option<f32>>("intensity")?
+            .unwrap_or(self.intensity);
+        self.radius = table
+            .get::<Option<f32>>("radius")?
+            .unwrap_or(self.radius)
+            .max(0.0);
+        self.falloff = table
+            .get::<Option<f32>>("falloff")?
+            .unwrap_or(self.falloff)
+            .max(0.0);
+        self.height = table.get::<Option<f32>>("height")?.unwrap_or(self.height);
+        self.cast_shadows = table
+            .get::<Option<bool>>("shadows")?
+            .unwrap_or(self.cast_shadows);
+        Ok(())
+    }
+
+    // Where the cone points once the followed entity's flip is applied.
+    pub fn direction(&self) -> Option<Vector2<f32>> {
+        match self.kind {
+            LightKind::Point => None,
+            LightKind::Spot { direction, .. } => Some(Vector2::new(
+                direction.cos() * self.mirror.x,
+                direction.sin() * self.mirror.y,
+            )),
+        }
+    }
+}
+
+// Scene-wide lighting. While it's off the world is drawn fully lit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Lighting {
+    pub enabled: bool,
+    pub ambient: [f32; 3], // how lit everything is without any light on it
+}
+
+impl Default for Lighting {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ambient: [1.0; 3],
+        }
+    }
+}
+
+// Moves lights along with the entities they follow, removing those whose entity is gone.
+pub fn light_system_update(world: &mut World) {
+    world.lights.retain(|_, light| {
+        let target = match light.follow {
+            Some(target) => target,
+            None => return true,
+        };
+        match world.transforms_2d.get(&target) {
+            Some(transform) => {
+                light.mirror = world
+                    .flips
+                    .get(&target)
+                    .copied()
+                    .unwrap_or_default()
+                    .signs();
+                light.position = transform.position
+                    + Vector2::new(
+                        light.offset.x * light.mirror.x,
+                        light.offset.y * light.mirror.y,
+                    );
+                true
+            }
+            None => false,
+        }
+    });
+}
@@ -0,0 +1,504 @@
+use std::f32::consts::TAU;
+use std::str::FromStr;
+
+use cgmath::Vector2;
+use ruin_assets::{normalize_asset_id, Handle, ImageTexture, TextureRegion};
+
+use crate::{world::World, Entity, RenderLayer};
+
+// Picked at random between min and max for every particle.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RandomRange {
+    pub min: f32,
+    pub max: f32,
+}
+
+impl RandomRange {
+    pub fn constant(value: f32) -> Self {
+        Self {
+            min: value,
+            max: value,
+        }
+    }
+
+    fn sample(&self, rng: &mut ParticleRng) -> f32 {
+        self.min + (self.max - self.min) * rng.next_f32()
+    }
+
+    // A number, or {min, max}.
+    fn from_lua(value: mlua::Value, default: RandomRange) -> mlua::Result<Self> {
+        match value {
+            mlua::Value::Nil => Ok(default),
+            mlua::Value::Table(range) => Ok(Self {
+                min: range.get(1)?,
+                max: range.get(2)?,
+            }),
+            value => Ok(Self::constant(number_from_lua(value)?)),
+        }
+    }
+}
+
+// Keys spread evenly over a particle's life, blended linearly in between.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Curve<const N: usize> {
+    pub keys: Vec<[f32; N]>,
+}
+
+impl<const N: usize> Curve<N> {
+    pub fn constant(value: [f32; N]) -> Self {
+        Self { keys: vec![value] }
+    }
+
+    // `t` runs from 0 at birth to 1 at death.
+    pub fn sample(&self, t: f32) -> [f32; N] {
+        if self.keys.len() < 2 {
+            return self.keys.first().copied().unwrap_or([0.0; N]);
+        }
+        let position = t.clamp(0.0, 1.0) * (self.keys.len() - 1) as f32;
+        let index = (position as usize).min(self.keys.len() - 2);
+        let amount = position - index as f32;
+        let (from, to) = (self.keys[index], self.keys[index + 1]);
+        std::array::from_fn(|i| from[i] + (to[i] - from[i]) * amount)
+    }
+}
+
+impl Curve<1> {
+    // A number, or a list of them.
+    fn from_lua(value: mlua::Value, default: f32) -> mlua::Result<Self> {
+        match value {
+            mlua::Value::Nil => Ok(Self::constant([default])),
+            mlua::Value::Table(keys) => {
+                let keys = keys
+                    .sequence_values::<f32>()
+                    .map(|key| key.map(|key| [key]))
+                    .collect::<mlua::Result<Vec<_>>>()?;
+                if keys.is_empty() {
+                    return Err(mlua::Error::RuntimeError("size needs a value".to_string()));
+                }
+                Ok(Self { keys })
+            }
+            value => Ok(Self::constant([number_from_lua(value)?])),
+        }
+    }
+}
+
+impl Curve<4> {
+    // {r, g, b, a?}, or a list of them.
+    fn from_lua(value: mlua::Value) -> mlua::Result<Self> {
+        let table = match value {
+            mlua::Value::Nil => return Ok(Self::constant([1.0; 4])),
+            mlua::Value::Table(table) => table,
+            _ => {
+                return Err(mlua::Error::RuntimeError(
+                    "color is {r, g, b, a} or a list of them".to_string(),
+                ))
+            }
+        };
+        if let Ok(mlua::Value::Table(_)) = table.get::<mlua::Value>(1) {
+            let keys = table
+                .sequence_values::<mlua::Table>()
+                .map(|key| key.and_then(|key| color_from_lua(&key)))
+                .collect::<mlua::Result<Vec<_>>>()?;
+            return Ok(Self { keys });
+        }
+        Ok(Self::constant(color_from_lua(&table)?))
+    }
+}
+
+fn color_from_lua(table: &mlua::Table) -> mlua::Result<[f32; 4]> {
+    Ok([
+        table.get(1)?,
+        table.get(2)?,
+        table.get(3)?,
+        table.get::<Option<f32>>(4)?.unwrap_or(1.0),
+    ])
+}
+
+fn number_from_lua(value: mlua::Value) -> mlua::Result<f32> {
+    match value {
+        mlua::Value::Integer(value) => Ok(value as f32),
+        mlua::Value::Number(value) => Ok(value as f32),
+        value => Err(mlua::Error::RuntimeError(format!(
+            "expected a number, got {}",
+            value.type_name()
+        ))),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParticleBlend {
+    Alpha,
+    Additive,
+}
+
+impl FromStr for ParticleBlend {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<ParticleBlend, ()> {
+        match s.to_lowercase().as_str() {
+            "alpha" => Ok(ParticleBlend::Alpha),
+            "add" | "additive" => Ok(ParticleBlend::Additive),
+            _ => Err(()),
+        }
+    }
+}
+
+// Frames cut from a sprite sheet. Without one particles are plain coloured squares.
+#[derive(Debug, Clone)]
+pub struct ParticleSprite {
+    pub sprite: String, // asset id of the sprite sheet
+    pub texture: Handle<ImageTexture>,
+    pub source_rects: Vec<[f32; 4]>, // x, y, w, h in sprite sheet pixels (origin top-left)
+    pub uv_coords: Vec<[[f32; 2]; 2]>, // top-left and bottom-right of each frame
+    // frames play over each particle's life, otherwise every particle keeps a random one
+    pub animated: bool,
+}
+
+impl ParticleSprite {
+    pub fn retexture(&mut self, region: &TextureRegion) {
+        let [sheet_w, sheet_h] = region.size().map(|v| v as f32);
+        self.texture = region.texture;
+        self.uv_coords = self
+            .source_rects
+            .iter()
+            .map(|[x, y, w, h]| {
+                // same orientation as SpriteFrame::update_uv_coords
+                let u0 = x / sheet_w;
+                let u1 = (x + w) / sheet_w;
+                let v1 = 1.0 - (y / sheet_h);
+                let v0 = 1.0 - ((y + h) / sheet_h);
+                [region.remap_uv([u0, v1]), region.remap_uv([u1, v0])]
+            })
+            .collect();
+    }
+
+    // Height over width of a frame, so sizes stay in world units across the particle's width.
+    pub fn aspect(&self, frame: usize) -> f32 {
+        match self.source_rects.get(frame) {
+            Some([_, _, w, h]) if *w > 0.0 => h / w,
+            _ => 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ParticleEmitterConfig {
+    pub rate: f32,             // particles a second while emitting
+    pub burst: u32,            // particles emitted at once when the emitter starts
+    pub duration: Option<f32>, // seconds the rate keeps emitting for, forever when None
+    pub max_particles: usize,
+    pub lifetime: RandomRange, // seconds
+    pub speed: RandomRange,    // world units a second
+    pub direction: f32,        // radians, counter-clockwise from +x
+    pub spread: f32,           // radians, particles leave anywhere within it around `direction`
+    pub gravity: Vector2<f32>,
+    pub drag: f32,            // how quickly particles slow down, 0 keeps their speed
+    pub spin: RandomRange,    // radians a second
+    pub size: Curve<1>,       // world units across
+    pub color: Curve<4>,      // multiplies the sprite, alpha fades it
+    pub offset: Vector2<f32>, // from the followed entity, mirrored with its flip
+    pub spawn_radius: f32,
+    pub sprite: Option<ParticleSprite>,
+    pub layer: RenderLayer,
+    pub blend: ParticleBlend,
+    pub remove_when_done: bool,
+}
+
+impl ParticleEmitterConfig {
+    // Keys left out keep their defaults, see the fields above.
+    pub fn from_lua_table(
+        table: &mlua::Table,
+        texture_loader: &mut impl FnMut(String) -> TextureRegion,
+    ) -> mlua::Result<Self> {
+        let layer = match table.get::<Option<String>>("layer")? {
+            Some(layer) => RenderLayer::from_str(&layer).map_err(|_| {
+                mlua::Error::RuntimeError(format!("unknown render layer {}", layer))
+            })?,
+            None => RenderLayer::Effects,
+        };
+        let blend = match table.get::<Option<String>>("blend")? {
+            Some(blend) => ParticleBlend::from_str(&blend).map_err(|_| {
+                mlua::Error::RuntimeError(format!("unknown particle blend {}", blend))
+            })?,
+            None => ParticleBlend::Alpha,
+        };
+        let gravity = table
+            .get::<Option<[f32; 2]>>("gravity")?
+            .unwrap_or([0.0, 0.0]);
+        let offset = table
+            .get::<Option<[f32; 2]>>("offset")?
+            .unwrap_or([0.0, 0.0]);
+
+        let sprite = match table.get::<Option<String>>("sprite")? {
+            Some(sprite_path) => {
+                let frames: mlua::Table = table.get("frames")?;
+                let mut source_rects = Vec::new();
+                for frame in frames.sequence_values::<mlua::Table>() {
+                    let frame = frame?;
+                    source_rects.push([
+                        frame.get("x")?,
+                        frame.get("y")?,
+                        frame.get("w")?,
+                        frame.get("h")?,
+                    ]);
+                }
+                if source_rects.is_empty() {
+                    return Err(mlua::Error::RuntimeError(
+                        "a particle sprite needs at least one frame".to_string(),
+                    ));
+                }
+                let sprite = normalize_asset_id(&sprite_path);
+                let region = texture_loader(sprite.clone());
+                let mut sprite = ParticleSprite {
+                    sprite,
+                    texture: region.texture,
+                    source_rects,
+                    uv_coords: Vec::new(),
+                    animated: table.get::<Option<bool>>("animated")?.unwrap_or(true),
+                };
+                sprite.retexture(&region);
+                Some(sprite)
+            }
+            None => None,
+        };
+
+        Ok(Self {
+            rate: table.get::<Option<f32>>("rate")?.unwrap_or(0.0),
+            burst: table.get::<Option<u32>>("burst")?.unwrap_or(0),
+            duration: table.get("duration")?,
+            max_particles: table.get::<Option<usize>>("max_particles")?.unwrap_or(256),
+            lifetime: RandomRange::from_lua(table.get("lifetime")?, RandomRange::constant(1.0))?,
+            speed: RandomRange::from_lua(table.get("speed")?, RandomRange::constant(1.0))?,
+            direction: table.get::<Option<f32>>("direction")?.unwrap_or(0.0),
+            spread: table.get::<Option<f32>>("spread")?.unwrap_or(TAU),
+            gravity: Vector2::new(gravity[0], gravity[1]),
+            drag: table.get::<Option<f32>>("drag")?.unwrap_or(0.0),
+            spin: RandomRange::from_lua(table.get("spin")?, RandomRange::constant(0.0))?,
+            size: Curve::<1>::from_lua(table.get("size")?, 0.25)?,
+            color: Curve::<4>::from_lua(table.get("color")?)?,
+            offset: Vector2::new(offset[0], offset[1]),
+            spawn_radius: table.get::<Option<f32>>("spawn_radius")?.unwrap_or(0.0),
+            sprite,
+            layer,
+            blend,
+            remove_when_done: table
+                .get::<Option<bool>>("remove_when_done")?
+                .unwrap_or(false),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Particle {
+    pub position: Vector2<f32>,
+    pub velocity: Vector2<f32>,
+    pub age: f32,
+    pub lifetime: f32,
+    pub rotation: f32,
+    pub spin: f32,
+    pub frame: usize,
+}
+
+impl Particle {
+    // 0 at birth, 1 at death.
+    pub fn life(&self) -> f32 {
+        if self.lifetime > 0.0 {
+            (self.age / self.lifetime).min(1.0)
+        } else {
+            1.0
+        }
+    }
+}
+
+// xorshift, particles only need cheap noise
+#[derive(Debug, Clone, Copy)]
+struct ParticleRng(u32);
+
+impl ParticleRng {
+    fn new(seed: u32) -> Self {
+        Self(seed.wrapping_mul(0x9E37_79B9) | 1)
+    }
+
+    // in [0, 1)
+    fn next_f32(&mut self) -> f32 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.0 = x;
+        (x >> 8) as f32 / (1u32 << 24) as f32
+    }
+}
+
+// Particles live in world space, so they stay behind when the emitter moves on.
+#[derive(Debug, Clone)]
+pub struct ParticleEmitterComponent {
+    pub config: ParticleEmitterConfig,
+    pub particles: Vec<Particle>,
+    pub position: Vector2<f32>,
+    pub follow: Option<Entity>,
+    pub emitting: bool,
+    pub elapsed: f32,
+    mirror: Vector2<f32>, // the followed entity's flip
+    spawn_accumulator: f32,
+    pending_burst: u32,
+    rng: ParticleRng,
+}
+
+impl ParticleEmitterComponent {
+    // Starts out stopped, `start` sets it going.
+    pub fn new(config: ParticleEmitterConfig, position: Vector2<f32>, seed: u32) -> Self {
+        Self {
+            config,
+            particles: Vec::new(),
+            position,
+            follow: None,
+            emitting: false,
+            elapsed: 0.0,
+            mirror: Vector2::new(1.0, 1.0),
+            spawn_accumulator: 0.0,
+            pending_burst: 0,
+            rng: ParticleRng::new(seed),
+        }
+    }
+
+    // Emitted on the next update, whether or not the emitter is running.
+    pub fn burst(&mut self, count: u32) {
+        self.pending_burst += count;
+    }
+
+    // Emits the burst and runs the rate for the duration, from the top if it was going already.
+    pub fn start(&mut self) {
+        self.emitting = true;
+        self.elapsed = 0.0;
+        self.pending_burst += self.config.burst;
+    }
+
+    fn emits_over_time(&self) -> bool {
+        self.emitting
+            && self.config.rate > 0.0
+            && self
+                .config
+                .duration
+                .is_none_or(|duration| self.elapsed < duration)
+    }
+
+    // Nothing left to draw and nothing more coming.
+    pub fn is_done(&self) -> bool {
+        !self.emits_over_time() && self.pending_burst == 0 && self.particles.is_empty()
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        let drag = (-self.config.drag * dt).exp();
+        for particle in self.particles.iter_mut() {
+            particle.age += dt;
+            particle.velocity = (particle.velocity + self.config.gravity * dt) * drag;
+            particle.position += particle.velocity * dt;
+            particle.rotation += particle.spin * dt;
+        }
+        self.particles
+            .retain(|particle| particle.age < particle.lifetime);
+
+        let mut count = std::mem::take(&mut self.pending_burst);
+        if self.emits_over_time() {
+            self.spawn_accumulator += self.config.rate * dt;
+            let whole = self.spawn_accumulator.floor();
+            self.spawn_accumulator -= whole;
+            count += whole as u32;
+        } else {
+            self.spawn_accumulator = 0.0;
+        }
+        self.elapsed += dt;
+
+        for _ in 0..count {
+            if self.particles.len() >= self.config.max_particles {
+                break;
+            }
+            self.spawn();
+        }
+    }
+
+    fn spawn(&mut self) {
+        let config = &self.config;
+        let rng = &mut self.rng;
+
+        let angle = config.direction + (rng.next_f32() - 0.5) * config.spread;
+        let speed = config.speed.sample(rng);
+        let velocity = Vector2::new(
+            angle.cos() * speed * self.mirror.x,
+            angle.sin() * speed * self.mirror.y,
+        );
+        // uniform over the disc
+        let distance = config.spawn_radius * rng.next_f32().sqrt();
+        let around = rng.next_f32() * TAU;
+        let frame = match &config.sprite {
+            Some(sprite) if !sprite.animated => {
+                ((rng.next_f32() * sprite.source_rects.len() as f32) as usize)
+                    .min(sprite.source_rects.len() - 1)
+            }
+            _ => 0,
+        };
+
+        self.particles.push(Particle {
+            position: self.position + Vector2::new(around.cos(), around.sin()) * distance,
+            velocity,
+            age: 0.0,
+            lifetime: config.lifetime.sample(rng),
+            rotation: 0.0,
+            spin: config.spin.sample(rng),
+            frame,
+        });
+    }
+
+    // Which sprite frame a particle shows right now.
+    pub fn frame_of(&self, particle: &Particle) -> usize {
+        match &self.config.sprite {
+            Some(sprite) if sprite.animated => {
+                ((particle.life() * sprite.source_rects.len() as f32) as usize)
+                    .min(sprite.source_rects.len() - 1)
+            }
+            _ => particle.frame,
+        }
+    }
+
+    pub fn retexture(&mut self, sprite: &str, region: &TextureRegion) {
+        if let Some(particle_sprite) = self.config.sprite.as_mut() {
+            if particle_sprite.sprite == sprite {
+                particle_sprite.retexture(region);
+            }
+        }
+    }
+}
+
+// Moves emitters along with the entities they follow, then steps their particles. Emitters
+// whose entity is gone stop emitting and let their particles run out.
+pub fn particle_system_update(world: &mut World, dt: f32) {
+    for emitter in world.particle_emitters.values_mut() {
+        if let Some(target) = emitter.follow {
+            match world.transforms_2d.get(&target) {
+                Some(transform) => {
+                    let mirror = world
+                        .flips
+                        .get(&target)
+                        .copied()
+                        .unwrap_or_default()
+                        .signs();
+                    emitter.mirror = mirror;
+                    emitter.position = transform.position
+                        + Vector2::new(
+                            emitter.config.offset.x * mirror.x,
+                            emitter.config.offset.y * mirror.y,
+                        );
+                }
+                None => {
+                    emitter.follow = None;
+                    emitter.emitting = false;
+                }
+            }
+        }
+        emitter.update(dt);
+    }
+    world
+        .particle_emitters
+        .retain(|_, emitter| !(emitter.config.remove_when_done && emitter.is_done()));
+}
@@ -203,6 +203,14 @@ impl Body2D {
         }
     }
 
+    fn is_dynamic(&self) -> bool {
+        matches!(self.body_type, BodyType2D::Rigid | BodyType2D::Kinematic)
+    }
+
+    pub fn is_static(&self) -> bool {
+        self.body_type == BodyType2D::Static
+    }
+
     fn push_collider(&mut self, collider: Area2D) {
         self.masks_superset |= collider.masks;
         self.layers_superset |= collider.layers;
@@ -256,6 +264,8 @@ pub struct PhysicsWorld {
     grid: SpatialGrid,
     player_pos: Point2D,
     slop: f32,
+    // static bodies stay put, so their grid cells are only rebuilt when one is added or removed
+    statics_changed: bool,
 }
 
 impl PhysicsWorld {
@@ -271,12 +281,15 @@ impl PhysicsWorld {
             },
             player_pos: Point2D { x: 0.0, y: 0.0 },
             slop: 0.0,
+            statics_changed: false,
         }
     }
 
     pub fn unload(&mut self) {
         self.bodies.clear();
         self.entity_map.clear();
+        self.grid.static_tiles.clear();
+        self.statics_changed = false;
     }
 
     pub fn get_velocity(&self, entity: &Entity) -> Vector2D {
@@ -395,6 +408,7 @@ impl PhysicsWorld {
         if let Some(index) = self.entity_map.get(entity) {
             let body = &mut self.bodies[*index];
             body.push_collider(collider);
+            self.statics_changed |= !body.is_dynamic();
         } else {
             eprintln!(
                 "Warning: Tried to add a collider to nonexistent body {:?}",
@@ -406,19 +420,24 @@ impl PhysicsWorld {
     fn broad_phase(&mut self) -> Vec<CollisionPair> {
         let i = Instant::now();
         self.grid.dynamic_tiles.clear();
-        self.grid.static_tiles.clear();
+        let rebuild_statics = self.statics_changed;
+        if rebuild_statics {
+            self.grid.static_tiles.clear();
+            self.statics_changed = false;
+        }
 
         for (i, body) in self
             .bodies
             .iter()
-            .filter(|b| !b.colliders.is_empty())
             .enumerate()
+            .filter(|(_, b)| !b.colliders.is_empty())
         {
-            let target_map = if matches!(body.body_type, BodyType2D::Rigid | BodyType2D::Kinematic)
-            {
+            let target_map = if body.is_dynamic() {
                 &mut self.grid.dynamic_tiles
-            } else {
+            } else if rebuild_statics {
                 &mut self.grid.static_tiles
+            } else {
+                continue;
             };
 
             Self::insert_body_into_grid(target_map, body, i, self.grid.tile_size);
@@ -485,10 +504,29 @@ impl PhysicsWorld {
 
     pub fn add_body(&mut self, entity: Entity, body: Body2D) {
         let index = self.bodies.len();
+        self.statics_changed |= !body.is_dynamic();
         self.bodies.push(body);
         self.entity_map.insert(entity, index);
     }
 
+    // The last body takes the removed one's place, so never remove the player (body 0)
+    // while other bodies are around.
+    pub fn remove_body(&mut self, entity: &Entity) {
+        let index = match self.entity_map.remove(entity) {
+            Some(index) => index,
+            None => return,
+        };
+        let last = self.bodies.len() - 1;
+        self.bodies.swap_remove(index);
+        if index != last {
+            if let Some(moved) = self.entity_map.values_mut().find(|moved| **moved == last) {
+                *moved = index;
+            }
+        }
+        // indices changed, so the static grid is stale
+        self.statics_changed = true;
+    }
+
     pub fn positions(&self) -> HashMap<Entity, Point2D> {
         self.entity_map
             .iter()
@@ -496,6 +534,32 @@ impl PhysicsWorld {
             .collect()
     }
 
+    // Outlines of every active static collider, counter-clockwise, for lights to cast hard
+    // shadows from. Circles come out as octagons.
+    pub fn occluder_outlines(&self) -> Vec<Vec<Point2D>> {
+        let mut outlines = Vec::new();
+        for body in self.bodies.iter().filter(|b| b.is_active && b.is_static()) {
+            for collider in body.colliders.iter().filter(|c| c.active) {
+                let center = body.position + collider.offset;
+                outlines.push(match collider.shape {
+                    Shape2D::Rectangle { half_extents: h } => vec![
+                        center + Vector2::new(-h.x, -h.y),
+                        center + Vector2::new(h.x, -h.y),
+                        center + Vector2::new(h.x, h.y),
+                        center + Vector2::new(-h.x, h.y),
+                    ],
+                    Shape2D::Circle { radius } => (0..8)
+                        .map(|i| {
+                            let angle = i as f32 * std::f32::consts::FRAC_PI_4;
+                            center + Vector2::new(angle.cos(), angle.sin()) * radius
+                        })
+                        .collect(),
+                });
+            }
+        }
+        outlines
+    }
+
     fn insert_body_into_grid(
         grid: &mut HashMap<GridCoord, Vec<Index>>,
         body: &Body2D,
@@ -1,6 +1,8 @@
 mod body_2d;
+mod tile_collision;
 
 pub use body_2d::{
     Area2D, Body2D, BodyType2D, CollisionPair, HalfExtents, PhysicsWorld, Point2D, Shape2D,
     Vector2D,
 };
+pub use tile_collision::{merge_cells, TileCollision};
@@ -0,0 +1,276 @@
+use cgmath::Vector2;
+
+use super::body_2d::{Area2D, Body2D, BodyType2D, MaskLayerBitmap, PhysicsWorld, Shape2D};
+use crate::Entity;
+
+// Cells per side of a collision chunk. A changed cell only re-merges its own chunk.
+const CHUNK_SIZE: u32 = 16;
+
+// Solid cells of a tile layer, merged into as few rectangles as possible and registered
+// with the physics world as static bodies. Cells changed at runtime mark their chunk, which
+// is rebuilt on the next sync.
+#[derive(Debug, Clone)]
+pub struct TileCollision {
+    pub layer: String,
+    // tilemaps whose `layer` tiles are solid, empty when the collision has no tiles to draw
+    pub tilemaps: Vec<Entity>,
+    pub width: u32,
+    pub height: u32,
+    pub origin: [f32; 2], // world position of the top-left corner, rows run downwards
+    pub cell_size: [f32; 2],
+    pub masks: MaskLayerBitmap,
+    pub layers: MaskLayerBitmap,
+    solid: Vec<bool>,
+    chunk_bodies: Vec<Vec<Entity>>,
+    dirty_chunks: Vec<bool>,
+}
+
+impl TileCollision {
+    pub fn new(
+        layer: String,
+        size: [u32; 2],
+        solid: Vec<bool>,
+        origin: [f32; 2],
+        cell_size: [f32; 2],
+        masks: MaskLayerBitmap,
+        layers: MaskLayerBitmap,
+    ) -> Self {
+        let [width, height] = size;
+        let chunks = (width.div_ceil(CHUNK_SIZE) * height.div_ceil(CHUNK_SIZE)) as usize;
+        let mut solid = solid;
+        solid.resize((width * height) as usize, false);
+        Self {
+            layer,
+            tilemaps: Vec::new(),
+            width,
+            height,
+            origin,
+            cell_size,
+            masks,
+            layers,
+            solid,
+            chunk_bodies: vec![Vec::new(); chunks],
+            dirty_chunks: vec![true; chunks],
+        }
+    }
+
+    pub fn is_solid(&self, x: u32, y: u32) -> bool {
+        x < self.width && y < self.height && self.solid[(y * self.width + x) as usize]
+    }
+
+    // Returns false when the cell is outside the layer.
+    pub fn set_solid(&mut self, x: u32, y: u32, solid: bool) -> bool {
+        if x >= self.width || y >= self.height {
+            return false;
+        }
+        let index = (y * self.width + x) as usize;
+        if self.solid[index] != solid {
+            self.solid[index] = solid;
+            let chunk = self.chunk_index(x / CHUNK_SIZE, y / CHUNK_SIZE);
+            self.dirty_chunks[chunk] = true;
+        }
+        true
+    }
+
+    // Replaces the bodies of every changed chunk.
+    pub fn sync(&mut self, physics: &mut PhysicsWorld, new_entity: &mut impl FnMut() -> Entity) {
+        for chunk_y in 0..self.height.div_ceil(CHUNK_SIZE) {
+            for chunk_x in 0..self.width.div_ceil(CHUNK_SIZE) {
+                let chunk = self.chunk_index(chunk_x, chunk_y);
+                if !self.dirty_chunks[chunk] {
+                    continue;
+                }
+                self.dirty_chunks[chunk] = false;
+
+                for body in self.chunk_bodies[chunk].drain(..) {
+                    physics.remove_body(&body);
+                }
+
+                let left = chunk_x * CHUNK_SIZE;
+                let top = chunk_y * CHUNK_SIZE;
+                let rects = merge_cells(
+                    CHUNK_SIZE.min(self.width - left),
+                    CHUNK_SIZE.min(self.height - top),
+                    |x, y| self.is_solid(left + x, top + y),
+                );
+                for [x, y, w, h] in rects {
+                    let [cell_w, cell_h] = self.cell_size;
+                    let half_extents = Vector2::new(w as f32 * cell_w, h as f32 * cell_h) / 2.0;
+                    let center = Vector2::new(
+                        self.origin[0] + (left + x) as f32 * cell_w + half_extents.x,
+                        self.origin[1] - (top + y) as f32 * cell_h - half_extents.y,
+                    );
+
+                    let entity = new_entity();
+                    physics.add_body(
+                        entity,
+                        Body2D::new(center, Vector2::new(0.0, 0.0), BodyType2D::Static, true),
+                    );
+                    physics.add_collider(
+                        &entity,
+                        Area2D {
+                            shape: Shape2D::Rectangle { half_extents },
+                            offset: Vector2::new(0.0, 0.0),
+                            masks: self.masks,
+                            layers: self.layers,
+                            active: true,
+                        },
+                    );
+                    self.chunk_bodies[chunk].push(entity);
+                }
+            }
+        }
+    }
+
+    fn chunk_index(&self, chunk_x: u32, chunk_y: u32) -> usize {
+        (chunk_y * self.width.div_ceil(CHUNK_SIZE) + chunk_x) as usize
+    }
+}
+
+// Greedy meshing: grows each rectangle right along its row, then down while the whole span
+// stays solid. Returns x, y, width and height in cells.
+pub fn merge_cells(width: u32, height: u32, solid: impl Fn(u32, u32) -> bool) -> Vec<[u32; 4]> {
+    let mut used = vec![false; (width * height) as usize];
+    let free = |used: &[bool], x: u32, y: u32| !used[(y * width + x) as usize] && solid(x, y);
+
+    let mut rects = Vec::new();
+    for y in 0..height {
+        for x in 0..width {
+            if !free(&used, x, y) {
+                continue;
+            }
+            let mut w = 1;
+            while x + w < width && free(&used, x + w, y) {
+                w += 1;
+            }
+            let mut h = 1;
+            while y + h < height && (x..x + w).all(|column| free(&used, column, y + h)) {
+                h += 1;
+            }
+            for row in y..y + h {
+                for column in x..x + w {
+                    used[(row * width + column) as usize] = true;
+                }
+            }
+            rects.push([x, y, w, h]);
+        }
+    }
+    rects
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid<'a>(rows: &'a [&str]) -> (u32, u32, impl Fn(u32, u32) -> bool + 'a) {
+        let width = rows.first().map_or(0, |row| row.len() as u32);
+        (width, rows.len() as u32, move |x: u32, y: u32| {
+            rows[y as usize].as_bytes()[x as usize] == b'#'
+        })
+    }
+
+    fn merge(rows: &[&str]) -> Vec<[u32; 4]> {
+        let (width, height, solid) = grid(rows);
+        merge_cells(width, height, solid)
+    }
+
+    #[test]
+    fn empty_grids_have_no_rects() {
+        assert!(merge(&[]).is_empty());
+        assert!(merge(&["....", "...."]).is_empty());
+    }
+
+    #[test]
+    fn solid_block_is_one_rect() {
+        assert_eq!(merge(&["###", "###"]), [[0, 0, 3, 2]]);
+    }
+
+    #[test]
+    fn grows_right_then_down() {
+        // the top row only goes down as far as its whole span is solid
+        assert_eq!(
+            merge(&["###.", "##..", "##.#"]),
+            [[0, 0, 3, 1], [0, 1, 2, 2], [3, 2, 1, 1]]
+        );
+        assert_eq!(merge(&["#.", "##"]), [[0, 0, 1, 2], [1, 1, 1, 1]]);
+    }
+
+    #[test]
+    fn rects_cover_every_solid_cell_once() {
+        let rows = ["#.#.#", ".###.", "#####", "#.#.#"];
+        let rects = merge(&rows);
+        let (width, height, solid) = grid(&rows);
+        let mut covered = vec![0; (width * height) as usize];
+        for [x, y, w, h] in rects {
+            for row in y..y + h {
+                for column in x..x + w {
+                    assert!(solid(column, row));
+                    covered[(row * width + column) as usize] += 1;
+                }
+            }
+        }
+        for y in 0..height {
+            for x in 0..width {
+                assert_eq!(covered[(y * width + x) as usize], solid(x, y) as i32);
+            }
+        }
+    }
+
+    fn sync(collision: &mut TileCollision, physics: &mut PhysicsWorld, next: &mut Entity) {
+        collision.sync(physics, &mut || {
+            *next += 1;
+            *next
+        });
+    }
+
+    #[test]
+    fn sync_only_rebuilds_changed_chunks() {
+        // two chunks side by side, both solid along the bottom row
+        let width = CHUNK_SIZE * 2;
+        let solid = (0..width * 2).map(|i| i >= width).collect();
+        let mut collision = TileCollision::new(
+            "walls".into(),
+            [width, 2],
+            solid,
+            [0.0, 0.0],
+            [1.0, 1.0],
+            1,
+            1,
+        );
+        let mut physics = PhysicsWorld::new();
+        let mut next = 0;
+
+        sync(&mut collision, &mut physics, &mut next);
+        assert_eq!(physics.bodies.len(), 2);
+        let first = physics.positions()[&1];
+        assert_eq!((first.x, first.y), (CHUNK_SIZE as f32 / 2.0, -1.5));
+
+        // nothing changed, nothing rebuilt
+        sync(&mut collision, &mut physics, &mut next);
+        assert_eq!(next, 2);
+
+        // a gap in the second chunk splits its rect, the first chunk keeps its body
+        assert!(collision.set_solid(CHUNK_SIZE + 4, 1, false));
+        sync(&mut collision, &mut physics, &mut next);
+        assert_eq!(physics.bodies.len(), 3);
+        assert!(physics.entity_map.contains_key(&1));
+        assert!(!physics.entity_map.contains_key(&2));
+    }
+
+    #[test]
+    fn cells_outside_the_layer_are_never_solid() {
+        let mut collision = TileCollision::new(
+            "walls".into(),
+            [3, 2],
+            vec![true],
+            [0.0, 0.0],
+            [1.0, 1.0],
+            1,
+            1,
+        );
+        assert!(collision.is_solid(0, 0));
+        assert!(!collision.is_solid(1, 0));
+        assert!(!collision.is_solid(3, 0));
+        assert!(!collision.set_solid(0, 2, true));
+    }
+}
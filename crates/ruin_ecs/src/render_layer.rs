@@ -0,0 +1,125 @@
+use std::str::FromStr;
+
+use crate::Entity;
+
+// Drawn in this order, each layer over the ones before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RenderLayer {
+    Background,
+    Ground,
+    Entities,
+    Effects,
+    Foreground,
+}
+
+impl RenderLayer {
+    pub const ALL: [RenderLayer; 5] = [
+        RenderLayer::Background,
+        RenderLayer::Ground,
+        RenderLayer::Entities,
+        RenderLayer::Effects,
+        RenderLayer::Foreground,
+    ];
+
+    pub fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+impl FromStr for RenderLayer {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<RenderLayer, ()> {
+        match s.to_lowercase().as_str() {
+            "background" => Ok(RenderLayer::Background),
+            "ground" => Ok(RenderLayer::Ground),
+            "entities" => Ok(RenderLayer::Entities),
+            "effects" => Ok(RenderLayer::Effects),
+            "foreground" => Ok(RenderLayer::Foreground),
+            _ => Err(()),
+        }
+    }
+}
+
+// How sprites within one layer are ordered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortMode {
+    // lower on screen is drawn later, so it's in front
+    YSort,
+    // higher z is drawn later
+    ZValue,
+    // entities created later are drawn later
+    Insertion,
+}
+
+impl FromStr for SortMode {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<SortMode, ()> {
+        match s.to_lowercase().as_str() {
+            "y" | "y_sort" | "ysort" => Ok(SortMode::YSort),
+            "z" | "z_value" => Ok(SortMode::ZValue),
+            "insertion" | "insertion_order" => Ok(SortMode::Insertion),
+            _ => Err(()),
+        }
+    }
+}
+
+impl SortMode {
+    pub fn default_for(layer: RenderLayer) -> SortMode {
+        match layer {
+            RenderLayer::Entities => SortMode::YSort,
+            RenderLayer::Effects => SortMode::Insertion,
+            _ => SortMode::ZValue,
+        }
+    }
+}
+
+// Which layer an entity is drawn on and where it sorts inside it. Sprites without one are
+// drawn on Entities, tilemaps on Ground.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderLayerComponent {
+    pub layer: RenderLayer,
+    pub z: f32,
+    // moves the point y-sorting looks at, in world units
+    pub sort_offset: f32,
+    // same, as a fraction of the sprite's height from its centre, -0.5 being its base
+    pub sort_pivot: f32,
+}
+
+impl RenderLayerComponent {
+    pub fn new(layer: RenderLayer) -> Self {
+        Self {
+            layer,
+            z: 0.0,
+            sort_offset: 0.0,
+            sort_pivot: 0.0,
+        }
+    }
+
+    // Higher keys are drawn later. `height` is how tall the sprite is in world units.
+    pub fn sort_key(&self, mode: SortMode, entity: Entity, y: f32, height: f32) -> f32 {
+        match mode {
+            SortMode::YSort => -(y + self.sort_offset + self.sort_pivot * height),
+            SortMode::ZValue => self.z,
+            SortMode::Insertion => entity as f32,
+        }
+    }
+
+    // {layer, z, sort_offset, sort_pivot}, keys left out keep their current value.
+    pub fn apply_lua_table(&mut self, table: &mlua::Table) -> mlua::Result<()> {
+        if let Ok(layer) = table.get::<String>("layer") {
+            self.layer = RenderLayer::from_str(&layer).map_err(|_| {
+                mlua::Error::RuntimeError(format!("unknown render layer {}", layer))
+            })?;
+        }
+        self.z = table.get::<Option<f32>>("z")?.unwrap_or(self.z);
+        self.sort_offset = table
+            .get::<Option<f32>>("sort_offset")?
+            .unwrap_or(self.sort_offset);
+        self.sort_pivot = table
+            .get::<Option<f32>>("sort_pivot")?
+            .unwrap_or(self.sort_pivot);
+        Ok(())
+    }
+}
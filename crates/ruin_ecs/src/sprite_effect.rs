@@ -0,0 +1,136 @@
+use crate::world::World;
+
+// Applied to the sampled colour before tinting: each output channel is the matching row
+// dotted with (r, g, b, a), plus its offset.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColorMatrix {
+    pub rows: [[f32; 4]; 4],
+    pub offset: [f32; 4],
+}
+
+impl ColorMatrix {
+    pub const IDENTITY: ColorMatrix = ColorMatrix {
+        rows: [
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ],
+        offset: [0.0; 4],
+    };
+
+    pub fn preset(name: &str) -> Option<ColorMatrix> {
+        let luma = [0.299, 0.587, 0.114, 0.0];
+        match name {
+            "grayscale" => Some(ColorMatrix {
+                rows: [luma, luma, luma, [0.0, 0.0, 0.0, 1.0]],
+                offset: [0.0; 4],
+            }),
+            "sepia" => Some(ColorMatrix {
+                rows: [
+                    [0.393, 0.769, 0.189, 0.0],
+                    [0.349, 0.686, 0.168, 0.0],
+                    [0.272, 0.534, 0.131, 0.0],
+                    [0.0, 0.0, 0.0, 1.0],
+                ],
+                offset: [0.0; 4],
+            }),
+            "invert" => Some(ColorMatrix {
+                rows: [
+                    [-1.0, 0.0, 0.0, 0.0],
+                    [0.0, -1.0, 0.0, 0.0],
+                    [0.0, 0.0, -1.0, 0.0],
+                    [0.0, 0.0, 0.0, 1.0],
+                ],
+                offset: [1.0, 1.0, 1.0, 0.0],
+            }),
+            _ => None,
+        }
+    }
+
+    // A preset name, or 4 rows of 5 numbers (the 5th being the offset) given flat, row by row.
+    // 16 numbers leave the offsets at 0.
+    pub fn from_lua_value(value: mlua::Value) -> mlua::Result<Self> {
+        if let mlua::Value::String(name) = &value {
+            let name = name.to_str()?.to_string();
+            return ColorMatrix::preset(&name).ok_or_else(|| {
+                mlua::Error::RuntimeError(format!("unknown color matrix {}", name))
+            });
+        }
+
+        let values: Vec<f32> = match value {
+            mlua::Value::Table(table) => {
+                table.sequence_values::<f32>().collect::<Result<_, _>>()?
+            }
+            _ => Vec::new(),
+        };
+        let columns = match values.len() {
+            20 => 5,
+            16 => 4,
+            count => {
+                return Err(mlua::Error::RuntimeError(format!(
+                    "a color matrix takes 16 or 20 numbers, got {}",
+                    count
+                )))
+            }
+        };
+
+        let mut matrix = ColorMatrix::IDENTITY;
+        for (row, chunk) in values.chunks(columns).enumerate() {
+            matrix.rows[row].copy_from_slice(&chunk[..4]);
+            matrix.offset[row] = chunk.get(4).copied().unwrap_or(0.0);
+        }
+        Ok(matrix)
+    }
+}
+
+// How an entity's sprite is coloured on top of its texture.
+#[derive(Debug, Clone, Copy)]
+pub struct SpriteEffectComponent {
+    pub tint: [f32; 4], // multiplies the texture, alpha is the sprite's opacity
+    pub flash_color: [f32; 3],
+    pub flash_duration: f32,
+    pub flash_time: f32, // seconds left, the flash fades out as it runs down
+    pub color_matrix: ColorMatrix,
+}
+
+impl Default for SpriteEffectComponent {
+    fn default() -> Self {
+        Self {
+            tint: [1.0; 4],
+            flash_color: [1.0; 3],
+            flash_duration: 0.0,
+            flash_time: 0.0,
+            color_matrix: ColorMatrix::IDENTITY,
+        }
+    }
+}
+
+impl SpriteEffectComponent {
+    pub fn flash(&mut self, color: [f32; 3], duration: f32) {
+        self.flash_color = color;
+        self.flash_duration = duration.max(0.0);
+        self.flash_time = self.flash_duration;
+    }
+
+    // Flash colour and how strongly it's added, 1 right after `flash` down to 0 at its end.
+    pub fn flash_amount(&self) -> [f32; 4] {
+        let strength = if self.flash_duration > 0.0 {
+            (self.flash_time / self.flash_duration).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        let [r, g, b] = self.flash_color;
+        [r, g, b, strength]
+    }
+
+    pub fn is_transparent(&self) -> bool {
+        self.tint[3] < 1.0 || self.color_matrix != ColorMatrix::IDENTITY
+    }
+}
+
+pub fn sprite_effect_system_update(world: &mut World, dt: f32) {
+    for effect in world.sprite_effects.values_mut() {
+        effect.flash_time = (effect.flash_time - dt).max(0.0);
+    }
+}
@@ -0,0 +1,284 @@
+use ruin_assets::{normalize_asset_id, Handle, ImageTexture, TextureRegion};
+
+use crate::autotile::{neighbour_mask, Autotile};
+
+// Tile indices are 1-based into the tileset, 0 leaves the cell empty.
+pub const EMPTY_TILE: u32 = 0;
+
+#[derive(Debug, Clone)]
+pub struct Tileset {
+    pub texture: Handle<ImageTexture>,
+    pub sprite: String, // asset id of the tileset image
+    pub tile_size: [u32; 2],
+    pub margin: u32,
+    pub spacing: u32,
+    pub columns: u32,
+    // per tile, in the same order as SpriteFrame::uv_coords
+    pub tile_uvs: Vec<[[f32; 2]; 4]>,
+    pub autotiles: Vec<Autotile>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TileLayer {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    pub tiles: Vec<u32>, // row major, top row first
+}
+
+// A grid of tiles drawn from one tileset. The entity's Transform2D position is the top-left
+// corner of the map, rows run downwards.
+#[derive(Debug, Clone)]
+pub struct TilemapComponent {
+    pub tileset: Tileset,
+    pub tile_world_size: [f32; 2],
+    pub layers: Vec<TileLayer>,
+    // bumped on every change so the renderer knows to rebuild its buffers
+    pub revision: u64,
+}
+
+impl Tileset {
+    pub fn new(
+        sprite: String,
+        region: &TextureRegion,
+        tile_size: [u32; 2],
+        margin: u32,
+        spacing: u32,
+    ) -> Self {
+        let mut tileset = Self {
+            texture: region.texture,
+            sprite,
+            tile_size: [tile_size[0].max(1), tile_size[1].max(1)],
+            margin,
+            spacing,
+            columns: 0,
+            tile_uvs: Vec::new(),
+            autotiles: Vec::new(),
+        };
+        tileset.retexture(region);
+        tileset
+    }
+
+    pub fn retexture(&mut self, region: &TextureRegion) {
+        let [sheet_w, sheet_h] = region.size();
+        let [tile_w, tile_h] = self.tile_size;
+        let cells = |size: u32, tile: u32| {
+            (size.saturating_sub(self.margin * 2) + self.spacing) / (tile + self.spacing)
+        };
+        let columns = cells(sheet_w, tile_w);
+        let rows = cells(sheet_h, tile_h);
+
+        self.texture = region.texture;
+        self.columns = columns;
+        self.tile_uvs.clear();
+        for index in 0..columns * rows {
+            let x = (self.margin + (index % columns) * (tile_w + self.spacing)) as f32;
+            let y = (self.margin + (index / columns) * (tile_h + self.spacing)) as f32;
+
+            let u0 = x / sheet_w as f32;
+            let u1 = (x + tile_w as f32) / sheet_w as f32;
+            let v1 = 1.0 - (y / sheet_h as f32);
+            let v0 = 1.0 - ((y + tile_h as f32) / sheet_h as f32);
+
+            self.tile_uvs.push([
+                region.remap_uv([u0, v1]),
+                region.remap_uv([u1, v1]),
+                region.remap_uv([u1, v0]),
+                region.remap_uv([u0, v0]),
+            ]);
+        }
+    }
+
+    pub fn tile_uv(&self, tile: u32) -> Option<&[[f32; 2]; 4]> {
+        if tile == EMPTY_TILE {
+            return None;
+        }
+        self.tile_uvs.get(tile as usize - 1)
+    }
+
+    // Index of the autotile terrain a tile belongs to.
+    pub fn autotile_of(&self, tile: u32) -> Option<usize> {
+        if tile == EMPTY_TILE {
+            return None;
+        }
+        self.autotiles
+            .iter()
+            .position(|autotile| autotile.contains(tile))
+    }
+}
+
+impl TileLayer {
+    pub fn new(name: String, width: u32, height: u32) -> Self {
+        Self {
+            name,
+            width,
+            height,
+            tiles: vec![EMPTY_TILE; (width * height) as usize],
+        }
+    }
+
+    pub fn tile(&self, x: u32, y: u32) -> u32 {
+        if x >= self.width || y >= self.height {
+            return EMPTY_TILE;
+        }
+        self.tiles[(y * self.width + x) as usize]
+    }
+}
+
+impl TilemapComponent {
+    pub fn layer_index(&self, name: &str) -> Option<usize> {
+        self.layers.iter().position(|layer| layer.name == name)
+    }
+
+    // Returns false when the cell is outside the layer.
+    pub fn set_tile(&mut self, layer: usize, x: u32, y: u32, tile: u32) -> bool {
+        let layer = match self.layers.get_mut(layer) {
+            Some(layer) if x < layer.width && y < layer.height => layer,
+            _ => return false,
+        };
+        let index = (y * layer.width + x) as usize;
+        if layer.tiles[index] != tile {
+            layer.tiles[index] = tile;
+            self.revision += 1;
+        }
+        true
+    }
+
+    // Paints the cell with the named autotile terrain, or clears it with None, then picks the
+    // tiles of it and its eight neighbours. Returns the cells whose tile changed, or None when
+    // the cell is outside the layer or the terrain is unknown.
+    pub fn paint(
+        &mut self,
+        layer: usize,
+        x: u32,
+        y: u32,
+        terrain: Option<&str>,
+    ) -> Option<Vec<[u32; 2]>> {
+        let painted = match terrain {
+            Some(name) => {
+                let index = self
+                    .tileset
+                    .autotiles
+                    .iter()
+                    .position(|autotile| autotile.name == name)?;
+                // any tile of the terrain will do until the neighbours are looked at
+                Some(self.tileset.autotiles[index].tile_for(0)?)
+            }
+            None => None,
+        };
+        let tile_layer = self.layers.get(layer)?;
+        if x >= tile_layer.width || y >= tile_layer.height {
+            return None;
+        }
+
+        let (width, height) = (tile_layer.width as i64, tile_layer.height as i64);
+        let block: Vec<[u32; 2]> = (-1..=1)
+            .flat_map(|dy| (-1..=1).map(move |dx| (x as i64 + dx, y as i64 + dy)))
+            .filter(|(cx, cy)| (0..width).contains(cx) && (0..height).contains(cy))
+            .map(|(cx, cy)| [cx as u32, cy as u32])
+            .collect();
+        let before: Vec<u32> = block
+            .iter()
+            .map(|[cx, cy]| tile_layer.tile(*cx, *cy))
+            .collect();
+
+        self.set_tile(layer, x, y, painted.unwrap_or(EMPTY_TILE));
+        for [cx, cy] in block.iter().copied() {
+            let tile_layer = &self.layers[layer];
+            let autotile = match self.tileset.autotile_of(tile_layer.tile(cx, cy)) {
+                Some(index) => &self.tileset.autotiles[index],
+                None => continue,
+            };
+            let mask = neighbour_mask(cx, cy, |nx, ny| {
+                (0..width).contains(&nx)
+                    && (0..height).contains(&ny)
+                    && autotile.contains(tile_layer.tile(nx as u32, ny as u32))
+            });
+            if let Some(tile) = autotile.tile_for(mask) {
+                self.set_tile(layer, cx, cy, tile);
+            }
+        }
+
+        Some(
+            block
+                .into_iter()
+                .zip(before)
+                .filter(|([cx, cy], tile)| self.layers[layer].tile(*cx, *cy) != *tile)
+                .map(|(cell, _)| cell)
+                .collect(),
+        )
+    }
+
+    // Centre of a cell relative to the map's top-left corner.
+    pub fn tile_center(&self, x: u32, y: u32) -> [f32; 2] {
+        let [w, h] = self.tile_world_size;
+        [(x as f32 + 0.5) * w, -(y as f32 + 0.5) * h]
+    }
+
+    pub fn retexture(&mut self, sprite: &str, region: &TextureRegion) -> bool {
+        if self.tileset.sprite != sprite {
+            return false;
+        }
+        self.tileset.retexture(region);
+        self.revision += 1;
+        true
+    }
+
+    // {
+    //   tile_width, tile_height,    -- world units per tile
+    //   tileset = { sprite, tile_width, tile_height, margin, spacing,    -- pixels
+    //               autotiles = { { ... }, ... } },    -- see Autotile::from_lua_table
+    //   layers = { { name, width, height, tiles = { ... }, collision } },
+    //   collision = { masks, layers },    -- for layers with collision = true, read by the engine
+    // }
+    pub fn from_lua_table(
+        table: mlua::Table,
+        texture_loader: &mut impl FnMut(String) -> TextureRegion,
+    ) -> mlua::Result<Self> {
+        let tileset_table: mlua::Table = table.get("tileset")?;
+        let sprite: String = tileset_table.get("sprite")?;
+        let sprite = normalize_asset_id(&sprite);
+        let region = texture_loader(sprite.clone());
+        let mut tileset = Tileset::new(
+            sprite,
+            &region,
+            [
+                tileset_table.get("tile_width")?,
+                tileset_table.get("tile_height")?,
+            ],
+            tileset_table.get("margin").unwrap_or(0),
+            tileset_table.get("spacing").unwrap_or(0),
+        );
+        if let Ok(autotiles) = tileset_table.get::<mlua::Table>("autotiles") {
+            for autotile in autotiles.sequence_values::<mlua::Table>() {
+                tileset.autotiles.push(Autotile::from_lua_table(autotile?)?);
+            }
+        }
+
+        let mut layers = Vec::new();
+        let layers_table: mlua::Table = table.get("layers")?;
+        for layer_table in layers_table.sequence_values::<mlua::Table>() {
+            let layer_table = layer_table?;
+            let mut layer = TileLayer::new(
+                layer_table.get("name").unwrap_or_default(),
+                layer_table.get("width")?,
+                layer_table.get("height")?,
+            );
+            let tiles: Vec<u32> = layer_table.get("tiles")?;
+            for (cell, tile) in layer.tiles.iter_mut().zip(tiles) {
+                *cell = tile;
+            }
+            layers.push(layer);
+        }
+
+        Ok(Self {
+            tileset,
+            tile_world_size: [
+                table.get("tile_width").unwrap_or(1.0),
+                table.get("tile_height").unwrap_or(1.0),
+            ],
+            layers,
+            revision: 0,
+        })
+    }
+}
@@ -7,7 +7,10 @@ pub struct Transform2D {
     pub position: Vector2<f32>,
     pub shape: Shape2D,
     pub scale: Vector2<f32>,
-    pub rotation_radians: f32,
+    pub rotation_radians: f32, // counter-clockwise
+    // what the sprite rotates around, as a fraction of its size from its centre, e.g. (-0.5, 0)
+    // for the middle of its left edge
+    pub pivot: Vector2<f32>,
 }
 
 impl Transform2D {
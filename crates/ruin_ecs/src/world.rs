@@ -4,8 +4,10 @@ use cgmath::Vector2;
 use ruin_assets::{Handle, ImageTexture};
 
 use crate::{
-    physics_2d::{Area2D, Point2D, Shape2D},
-    ActionStateComponent, AnimationComponent, Entity, FlipComponent, HealthComponent, Transform2D,
+    physics_2d::{Area2D, PhysicsWorld, Point2D, Shape2D, TileCollision},
+    ActionStateComponent, AnimationComponent, ColorMatrix, Entity, FlipComponent, HealthComponent,
+    LightComponent, LightKind, Lighting, ParticleBlend, ParticleEmitterComponent, RenderLayer,
+    RenderLayerComponent, SortMode, SpriteEffectComponent, TilemapComponent, Transform2D,
 };
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -43,7 +45,16 @@ pub struct World {
     pub flips: HashMap<Entity, FlipComponent>,
     pub health_bars: HashMap<Entity, HealthComponent>,
     pub animations: HashMap<Entity, AnimationComponent>,
+    pub sprite_effects: HashMap<Entity, SpriteEffectComponent>,
+    pub render_layers: HashMap<Entity, RenderLayerComponent>,
+    pub particle_emitters: HashMap<Entity, ParticleEmitterComponent>,
+    pub lights: HashMap<Entity, LightComponent>,
+    pub lighting: Lighting,
+    // indexed by RenderLayer::index
+    pub layer_sort_modes: [SortMode; 5],
     pub transforms_2d: HashMap<Entity, Transform2D>,
+    pub tilemaps: HashMap<Entity, TilemapComponent>,
+    pub tile_collisions: HashMap<Entity, TileCollision>,
     pub action_states: HashMap<Entity, ActionStateComponent>,
     pub physical_colliders_2d: HashMap<Entity, HashMap<Entity, Area2D>>,
     pub hitboxes_2d: HashMap<Entity, HashMap<Entity, Area2D>>,
@@ -62,8 +73,16 @@ impl World {
             next_id: 0,
             health_bars: HashMap::new(),
             transforms_2d: HashMap::new(),
+            tilemaps: HashMap::new(),
+            tile_collisions: HashMap::new(),
             action_states: HashMap::new(),
             animations: HashMap::new(),
+            sprite_effects: HashMap::new(),
+            render_layers: HashMap::new(),
+            particle_emitters: HashMap::new(),
+            lights: HashMap::new(),
+            lighting: Lighting::default(),
+            layer_sort_modes: RenderLayer::ALL.map(SortMode::default_for),
             physical_colliders_2d: HashMap::new(),
             hitboxes_2d: HashMap::new(),
             hurtboxes_2d: HashMap::new(),
@@ -86,7 +105,13 @@ impl World {
         self.flips.clear();
         self.health_bars.clear();
         self.animations.clear();
+        self.sprite_effects.clear();
+        self.render_layers.clear();
+        self.particle_emitters.clear();
+        self.lights.clear();
         self.transforms_2d.clear();
+        self.tilemaps.clear();
+        self.tile_collisions.clear();
         self.action_states.clear();
         self.physical_colliders_2d.clear();
         self.hitboxes_2d.clear();
@@ -101,6 +126,26 @@ impl World {
         return entity;
     }
 
+    // Brings the static bodies of every tile collision up to date with its cells.
+    pub fn sync_tile_collisions(&mut self, physics: &mut PhysicsWorld) {
+        let next_id = &mut self.next_id;
+        let mut new_entity = || {
+            let entity = *next_id;
+            *next_id += 1;
+            entity
+        };
+        for collision in self.tile_collisions.values_mut() {
+            collision.sync(physics, &mut new_entity);
+        }
+    }
+
+    pub fn render_layer_of(&self, entity: &Entity, default: RenderLayer) -> RenderLayerComponent {
+        self.render_layers
+            .get(entity)
+            .copied()
+            .unwrap_or_else(|| RenderLayerComponent::new(default))
+    }
+
     fn get_all_areas_by_info(&self, info: AreaInfo) -> HashMap<Entity, Area2D> {
         match info.role {
             AreaRole::Physics => self
@@ -257,23 +302,69 @@ impl World {
 
         for (entity, animation) in self.animations.iter() {
             if let Some(transform) = self.transforms_2d.get(entity) {
-                let uv_coords = animation.current_frame.uv_coords;
+                let frame = &animation.current_frame;
+                let uv_coords = frame.uv_coords;
                 let action_animation = &animation.animations[&self
                     .action_states
                     .get(&entity)
                     .expect("Animation not found")
                     .state];
 
+                // flips swap the UVs, everything placed inside the sprite mirrors with them
+                let flip = self.flips.get(entity).copied().unwrap_or_default();
+                let mirror = flip.signs();
+                let mut uv_coords = uv_coords;
+                if flip.x {
+                    uv_coords.swap(0, 1);
+                    uv_coords.swap(2, 3);
+                }
+                if flip.y {
+                    uv_coords.swap(0, 3);
+                    uv_coords.swap(1, 2);
+                }
+
+                let scale = transform.get_scale_abs();
+                let trim_offset = Vector2::new(
+                    frame.trim_offset[0] * scale.x * mirror.x,
+                    frame.trim_offset[1] * scale.y * mirror.y,
+                );
+                let size =
+                    Vector2::new(scale.x * frame.trim_scale[0], scale.y * frame.trim_scale[1]);
+                // the pivot is given relative to the whole frame, the quad is only its trimmed part
+                let pivot_offset = Vector2::new(
+                    transform.pivot.x * mirror.x * scale.x * 2.0 * transform.shape.half_extents().x,
+                    transform.pivot.y * mirror.y * scale.y * 2.0 * transform.shape.half_extents().y,
+                );
+                let rotation = transform.rotation_radians * mirror.x * mirror.y;
+                let position = transform.position + trim_offset;
+
+                let effect = self.sprite_effects.get(entity).copied().unwrap_or_default();
+                let render_layer = self.render_layer_of(entity, RenderLayer::Entities);
+                let height = transform.scale.y.abs() * transform.shape.half_extents().y * 2.0;
+                let z_order = render_layer.sort_key(
+                    self.layer_sort_modes[render_layer.layer.index()],
+                    *entity,
+                    transform.position.y,
+                    height,
+                );
                 let tmp = RenderElement2D {
                     shape: transform.shape,
-                    position: transform.position.into(),
-                    size: transform.scale.into(),
-                    z_order: -transform.position[1], // Sort top to bottom: lower y = drawn later
+                    position: position.into(),
+                    size: size.into(),
+                    z_order,
+                    layer: render_layer.layer,
+                    order: *entity,
                     image_texture: action_animation.sprite_sheet_id,
                     uv_coords,
+                    rotation,
+                    pivot: (pivot_offset - trim_offset).into(),
+                    tint: effect.tint,
+                    flash: effect.flash_amount(),
+                    color_matrix: effect.color_matrix,
                 };
 
-                if action_animation.is_transparent {
+                // faded sprites have to blend with what's behind them
+                if action_animation.is_transparent || effect.is_transparent() {
                     transparent.push(tmp);
                 } else {
                     opaque.push(tmp);
@@ -286,6 +377,85 @@ impl World {
             opaque,
         }
     }
+
+    // One batch per emitter with live particles, in layer order.
+    pub fn extract_particles_2d(&self) -> Vec<ParticleBatch2D> {
+        let mut batches = Vec::new();
+        for (entity, emitter) in self.particle_emitters.iter() {
+            if emitter.particles.is_empty() {
+                continue;
+            }
+            let sprite = emitter.config.sprite.as_ref();
+            let particles = emitter
+                .particles
+                .iter()
+                .map(|particle| {
+                    let life = particle.life();
+                    let frame = emitter.frame_of(particle);
+                    let [width] = emitter.config.size.sample(life);
+                    let aspect = sprite.map_or(1.0, |sprite| sprite.aspect(frame));
+                    ParticleElement2D {
+                        position: particle.position.into(),
+                        size: [width, width * aspect],
+                        rotation: particle.rotation,
+                        color: emitter.config.color.sample(life),
+                        uv_coords: sprite
+                            .map_or([[0.0, 0.0], [1.0, 1.0]], |sprite| sprite.uv_coords[frame]),
+                    }
+                })
+                .collect();
+            batches.push(ParticleBatch2D {
+                layer: emitter.config.layer,
+                order: *entity,
+                texture: sprite.map(|sprite| sprite.texture),
+                blend: emitter.config.blend,
+                particles,
+            });
+        }
+        batches.sort_by_key(|batch| (batch.layer, batch.order));
+        batches
+    }
+
+    // Lights that reach anything, ordered by entity so they don't swap stencil slots.
+    pub fn extract_lights_2d(&self) -> Vec<LightElement2D> {
+        let mut lights: Vec<_> = self
+            .lights
+            .iter()
+            .filter(|(_, light)| light.radius > 0.0 && light.intensity > 0.0)
+            .map(|(entity, light)| {
+                let (direction, cone) = match (light.kind, light.direction()) {
+                    (
+                        LightKind::Spot {
+                            angle, softness, ..
+                        },
+                        Some(direction),
+                    ) => {
+                        let outer = (angle * 0.5).cos();
+                        // kept apart, a hard edge still needs some room to fade
+                        let inner = (angle * 0.5 * (1.0 - softness)).cos().max(outer + 0.001);
+                        (direction.into(), [outer, inner])
+                    }
+                    // every direction is inside a cone this wide
+                    _ => ([1.0, 0.0], [-2.0, -1.0]),
+                };
+                (
+                    *entity,
+                    LightElement2D {
+                        position: light.position.into(),
+                        color: light.color.map(|channel| channel * light.intensity),
+                        radius: light.radius,
+                        falloff: light.falloff,
+                        height: light.height,
+                        direction,
+                        cone,
+                        cast_shadows: light.cast_shadows,
+                    },
+                )
+            })
+            .collect();
+        lights.sort_by_key(|(entity, _)| *entity);
+        lights.into_iter().map(|(_, light)| light).collect()
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -293,9 +463,16 @@ pub struct RenderElement2D {
     pub shape: Shape2D,
     pub position: [f32; 2],
     pub size: [f32; 2],
-    pub z_order: f32, // for Y-based sorting (e.g., lower y = drawn on top)
+    pub z_order: f32, // higher is drawn later within the layer, see SortMode
+    pub layer: RenderLayer,
+    pub order: u32, // breaks z_order ties, so equal sprites don't flicker between frames
     pub image_texture: Handle<ImageTexture>,
     pub uv_coords: [[f32; 2]; 4],
+    pub rotation: f32,   // radians, counter-clockwise around `pivot`
+    pub pivot: [f32; 2], // world units from `position`
+    pub tint: [f32; 4],
+    pub flash: [f32; 4], // added colour, alpha is how much of it
+    pub color_matrix: ColorMatrix,
 }
 
 #[derive(Debug, Clone)]
@@ -303,3 +480,33 @@ pub struct RenderQueue2D {
     pub transparent: Vec<RenderElement2D>,
     pub opaque: Vec<RenderElement2D>,
 }
+
+#[derive(Debug, Clone, Copy)]
+pub struct ParticleElement2D {
+    pub position: [f32; 2],
+    pub size: [f32; 2],
+    pub rotation: f32, // radians, counter-clockwise around `position`
+    pub color: [f32; 4],
+    pub uv_coords: [[f32; 2]; 2], // top-left, bottom-right
+}
+
+#[derive(Debug, Clone)]
+pub struct ParticleBatch2D {
+    pub layer: RenderLayer,
+    pub order: u32,
+    pub texture: Option<Handle<ImageTexture>>, // None draws plain coloured squares
+    pub blend: ParticleBlend,
+    pub particles: Vec<ParticleElement2D>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct LightElement2D {
+    pub position: [f32; 2],
+    pub color: [f32; 3], // already scaled by intensity
+    pub radius: f32,
+    pub falloff: f32,
+    pub height: f32,
+    pub direction: [f32; 2], // unit vector a spot light points along
+    pub cone: [f32; 2],      // cosines of the outer and inner cone half-angles
+    pub cast_shadows: bool,
+}
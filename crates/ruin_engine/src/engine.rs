@@ -1,21 +1,35 @@
 use cgmath::Vector2;
+use image::DynamicImage;
 use mlua::{Result, Table};
-use ruin_assets::{Handle, ImageTexture};
+use ruin_assets::{
+    normalize_asset_id, AssetServer, AssetSource, AssetWatcher, LevelFile, LevelObject,
+    PropertyValue, SpriteSheet, TextureRegion,
+};
+use ruin_audio::{
+    Audio, Bus, Emitter, Listener, MusicStream, Sound, SoundLoader, SoundSettings, VoiceId,
+};
 use ruin_bitmaps::vecbool_to_u8;
-use ruin_camera::{Camera2D, Camera2DConfig, CameraOption, Dimensions};
+use ruin_camera::{Camera2D, Camera2DConfig, CameraOption, Dimensions, ScaleMode};
 use ruin_canvas::{parse_canvas_view_from_lua, Canvas};
 use ruin_debug::{debug_log, Debug};
-use ruin_ecs::physics_2d::{Area2D, Body2D, BodyType2D, PhysicsWorld, Point2D, Shape2D, Vector2D};
+use ruin_ecs::physics_2d::{
+    Area2D, Body2D, BodyType2D, PhysicsWorld, Point2D, Shape2D, TileCollision, Vector2D,
+};
 use ruin_ecs::world::World;
 use ruin_ecs::{
-    animation_system_update_frames, set_entity_state, ActionState, ActionStateComponent, Animation,
-    AnimationComponent, Entity, FlipComponent, HealthComponent, Transform2D,
+    animation_system_update_frames, light_system_update, particle_system_update, set_entity_state,
+    sprite_effect_system_update, ActionState, ActionStateComponent, Animation, AnimationComponent,
+    ColorMatrix, Entity, FlipComponent, HealthComponent, LightComponent, ParticleEmitterComponent,
+    ParticleEmitterConfig, RenderLayer, RenderLayerComponent, SortMode, TileLayer,
+    TilemapComponent, Tileset, Transform2D, EMPTY_TILE,
 };
-use ruin_graphics::graphics_2d::Graphics2D;
+use ruin_graphics::graphics_2d::{Graphics2D, PostEffect, PostEffects};
 use ruin_graphics::Graphics;
 use ruin_lua_runtime::LuaExtendedExecutor;
 use ruin_player_controller::{keycode_to_str, mousebutton_to_str};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::str::FromStr;
 use std::sync::Arc;
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use winit::application::ApplicationHandler;
@@ -26,6 +40,10 @@ use winit::keyboard::{KeyCode, PhysicalKey};
 use winit::window::Window;
 
 static SAFETY_MAX_FOR_DEV: u64 = 10000;
+static WINDOW_TITLE: &str = "ruin";
+// default emitter range, in multiples of half the view width
+static EMITTER_MIN_DISTANCE: f32 = 0.25;
+static EMITTER_MAX_DISTANCE: f32 = 2.0;
 
 #[derive(Debug)]
 struct FPS {
@@ -57,16 +75,37 @@ pub struct Engine {
     width: u32,
     height: u32,
     fps: FPS,
+    virtual_resolution: [u32; 2],
+    scale_mode: ScaleMode,
     camera2d_config: Camera2DConfig,
+    // ids of the textures the current scene holds, released on unload_scene
+    scene_textures: HashSet<String>,
+    // counts unloaded scenes, so loads that land after theirs is gone can be dropped
+    scene: u32,
+    // textures loading in the background and the scene that asked for them
+    pending_textures: HashMap<String, u32>,
+    asset_watcher: Option<AssetWatcher>,
+    script_watcher: Option<AssetWatcher>,
+    asset_source: Arc<dyn AssetSource>,
+    // every file the game loads goes through here, the renderer only keeps what's on the GPU
+    assets: AssetServer,
+    audio: Audio,
+    // positional sounds and the entities they follow
+    sound_emitters: HashMap<VoiceId, Entity>,
+    // sprite sheet ids and the normal maps lighting shades them with, kept across scenes
+    normal_maps: HashMap<String, String>,
 }
 
 pub struct EngineConfig {
     pub fps: String,
     pub debug_enabled: bool,
+    pub hot_reload: bool,
+    pub asset_source: Arc<dyn AssetSource>,
     pub window_width: u32,
     pub window_height: u32,
     pub virtual_resolution_width: u32,
     pub virtual_resolution_height: u32,
+    pub scale_mode: ScaleMode,
     pub dimensions: Dimensions,
     pub camera: CameraOption,
     pub camera2d_config: Camera2DConfig,
@@ -82,6 +121,22 @@ impl Engine {
 
         let target_rate = fps_opt.map(|fps| Duration::from_millis(1000 / fps));
 
+        let (asset_watcher, script_watcher) = if config.hot_reload {
+            (
+                AssetWatcher::new("./assets")
+                    .map_err(|err| println!("Asset hot reload disabled: {}", err))
+                    .ok(),
+                AssetWatcher::new("./lua_runtime/scripts")
+                    .map_err(|err| println!("Script hot reload disabled: {}", err))
+                    .ok(),
+            )
+        } else {
+            (None, None)
+        };
+
+        let mut assets = AssetServer::new(config.asset_source.clone());
+        assets.register_loader(SoundLoader);
+
         Self {
             mouse_pos: [0.0, 0.0],
             player: 0,
@@ -110,23 +165,739 @@ impl Engine {
                 frame_count: 0,
                 time_accum: 0.0,
             },
+            virtual_resolution: [
+                config.virtual_resolution_width,
+                config.virtual_resolution_height,
+            ],
+            scale_mode: config.scale_mode,
             camera2d_config: config.camera2d_config,
+            scene_textures: HashSet::new(),
+            scene: 0,
+            pending_textures: HashMap::new(),
+            asset_watcher,
+            script_watcher,
+            asset_source: config.asset_source,
+            assets,
+            audio: Audio::new(),
+            sound_emitters: HashMap::new(),
+            normal_maps: HashMap::new(),
+        }
+    }
+
+    // Returns a placeholder region until the image has been decoded in the background, then
+    // poll_loaded_textures swaps the real one in.
+    fn load_texture(&mut self, id: String) -> TextureRegion {
+        let id = normalize_asset_id(&id);
+        let graphics = self.graphics.as_mut().expect("Graphics not initialized");
+        let region = match graphics.texture_region(&id) {
+            Some(region) => region,
+            None => match self
+                .assets
+                .load_in_background::<DynamicImage>(&format!("assets/{}", id))
+            {
+                Ok(()) => {
+                    self.pending_textures.insert(id.clone(), self.scene);
+                    graphics.placeholder_region()
+                }
+                Err(err) => {
+                    println!("Failed to load {}: {}", id, err);
+                    graphics.set_missing_texture(&id)
+                }
+            },
+        };
+        if self.scene_textures.insert(id.clone()) {
+            graphics.acquire_texture(&id);
+        }
+        region
+    }
+
+    // Decodes an image through the asset server without keeping it there, the renderer holds
+    // the pixels once they're uploaded.
+    fn load_image(&mut self, id: &str) -> anyhow::Result<DynamicImage> {
+        let source_id = format!("assets/{}", id);
+        self.assets.load::<DynamicImage>(&source_id)?;
+        self.assets
+            .take::<DynamicImage>(&source_id)
+            .ok_or_else(|| anyhow::anyhow!("{} was unloaded", id))
+    }
+
+    fn poll_asset_changes(&mut self) {
+        let changed_scripts: Vec<String> = match self.script_watcher.as_mut() {
+            Some(watcher) => watcher
+                .poll_changed()
+                .into_iter()
+                .filter(|id| id.ends_with(".lua"))
+                .collect(),
+            None => Vec::new(),
+        };
+        if !changed_scripts.is_empty() {
+            self.reload_scripts(changed_scripts);
+        }
+
+        let changed = match self.asset_watcher.as_mut() {
+            Some(watcher) => watcher.poll_changed(),
+            None => return,
+        };
+
+        // assets built from a changed file are reloaded after it, e.g. a sheet's json after its png
+        let mut affected = Vec::new();
+        let mut seen = HashSet::new();
+        for id in changed {
+            let source_id = format!("assets/{}", id);
+            if let Err(err) = self.assets.reload(&source_id) {
+                println!("Failed to reload {}: {}", id, err);
+            }
+            if seen.insert(id.clone()) {
+                affected.push(id);
+            }
+            for dependent in self.assets.dependents(&source_id) {
+                if let Err(err) = self.assets.reload(&dependent) {
+                    println!("Failed to reload {}: {}", dependent, err);
+                }
+                if let Some(dependent) = dependent.strip_prefix("assets/") {
+                    if seen.insert(dependent.to_string()) {
+                        affected.push(dependent.to_string());
+                    }
+                }
+            }
+        }
+
+        for id in affected {
+            match Path::new(&id).extension().and_then(|ext| ext.to_str()) {
+                Some("json") => self.reload_animations(&id),
+                Some("png") | Some("jpg") | Some("jpeg") => self.reload_texture(&id),
+                _ => {}
+            }
+        }
+    }
+
+    // Reloads the changed scripts and the ones that require them. A bad edit keeps the previous
+    // scripts running and shows the error in the title bar until a reload succeeds.
+    fn reload_scripts(&mut self, changed: Vec<String>) {
+        let error = match self
+            .lua_context
+            .get_function("ENGINE_reload_scripts")
+            .call::<Option<String>>(changed)
+        {
+            Ok(error) => error,
+            Err(err) => Some(err.to_string()),
+        };
+
+        let title = match &error {
+            Some(err) => {
+                println!("Lua reload failed: {}", err);
+                let message = err.split_whitespace().collect::<Vec<_>>().join(" ");
+                format!("{} - Lua error: {}", WINDOW_TITLE, message)
+            }
+            None => {
+                debug_log!(self.debugger, "Reloaded Lua scripts");
+                WINDOW_TITLE.to_string()
+            }
+        };
+        if let Some(window) = &self.window {
+            window.set_title(&title);
+        }
+    }
+
+    // Re-uploads a changed sheet and points every animation drawn from it at the new region.
+    fn reload_texture(&mut self, id: &str) {
+        let sheets: Vec<String> = self
+            .normal_maps
+            .iter()
+            .filter(|(_, normal_map)| *normal_map == id)
+            .map(|(sheet, _)| sheet.clone())
+            .collect();
+        for sheet in sheets {
+            let region = self.load_texture(sheet.clone());
+            self.apply_normal_map(&sheet, region);
+        }
+
+        let uploaded = match self.graphics.as_mut() {
+            Some(graphics) => {
+                graphics.reload_lut(&format!("./assets/{}", id));
+                graphics.texture_region(id).is_some()
+            }
+            None => return,
+        };
+        if !uploaded {
+            return;
+        }
+        // exporters can leave a half written file behind, the next change will retry
+        let image = match self.load_image(id) {
+            Ok(image) => image,
+            Err(err) => {
+                println!("Failed to reload {}: {}", id, err);
+                return;
+            }
+        };
+        let region = match self.graphics.as_mut() {
+            Some(graphics) => graphics.reload_texture(id, &image),
+            None => return,
+        };
+        if let Some(region) = region {
+            self.retexture(id, region);
+            debug_log!(self.debugger, "Reloaded texture {}", id);
+        }
+    }
+
+    // Uploads images the asset server finished decoding and swaps them in for the placeholders
+    // handed out earlier. Ones that failed show the missing texture and are reported to Lua.
+    fn poll_loaded_textures(&mut self) {
+        for (source_id, result) in self.assets.poll_background() {
+            let id = match source_id.strip_prefix("assets/") {
+                Some(id) => id.to_string(),
+                None => continue,
+            };
+            let image = result.and_then(|()| {
+                self.assets
+                    .take::<DynamicImage>(&source_id)
+                    .ok_or_else(|| anyhow::anyhow!("it was unloaded"))
+            });
+            // the scene that asked for it was unloaded first, the next one loads it again
+            if self.pending_textures.remove(&id) != Some(self.scene) {
+                continue;
+            }
+            let graphics = match self.graphics.as_mut() {
+                Some(graphics) => graphics,
+                None => return,
+            };
+            let region = match image.and_then(|image| graphics.upload_texture(&id, &image)) {
+                Ok(region) => region,
+                Err(err) => {
+                    let error = format!("Failed to load {}: {}", id, err);
+                    println!("{}", error);
+                    let region = graphics.set_missing_texture(&id);
+                    self.report_asset_error(&id, error);
+                    region
+                }
+            };
+            self.retexture(&id, region);
+        }
+    }
+
+    fn report_asset_error(&self, id: &str, error: String) {
+        let _ = self
+            .lua_context
+            .get_function("ENGINE_asset_error")
+            .call::<()>((id, error));
+    }
+
+    // Points every animation drawn from `id` at its new region.
+    fn retexture(&mut self, id: &str, region: TextureRegion) {
+        for (entity, animation) in self.world.animations.iter_mut() {
+            if animation.retexture(id, &region) {
+                if let Some(action_state) = self.world.action_states.get(entity) {
+                    animation.refresh_current_frame(&action_state.state);
+                }
+            }
+        }
+        for tilemap in self.world.tilemaps.values_mut() {
+            tilemap.retexture(id, &region);
+        }
+        for emitter in self.world.particle_emitters.values_mut() {
+            emitter.retexture(id, &region);
+        }
+        self.canvas.retexture(id, &region);
+        self.apply_normal_map(id, region);
+    }
+
+    // Lighting shades the sheet `sprite` with the png at `normal_map`, which has to be the
+    // same size. It's set again whenever either of them is reloaded.
+    fn set_normal_map(&mut self, sprite: String, normal_map: String) {
+        let sprite = normalize_asset_id(&sprite);
+        self.normal_maps
+            .insert(sprite.clone(), normalize_asset_id(&normal_map));
+        let region = self.load_texture(sprite.clone());
+        self.apply_normal_map(&sprite, region);
+    }
+
+    fn apply_normal_map(&mut self, id: &str, region: TextureRegion) {
+        let normal_map = match self.normal_maps.get(id) {
+            Some(normal_map) => normal_map.clone(),
+            None => return,
+        };
+        let result = self
+            .load_image(&normal_map)
+            .and_then(|image| match self.graphics.as_mut() {
+                Some(graphics) => graphics.set_normal_map(region, &image),
+                None => Ok(()),
+            });
+        if let Err(err) = result {
+            println!("Failed to set normal map {} on {}: {}", normal_map, id, err);
+        }
+    }
+
+    // Starts loading every asset id in the list so a loading screen can wait on them.
+    fn preload(&mut self, assets: Table) {
+        for id in assets.sequence_values::<String>().flatten() {
+            self.load_texture(id);
+        }
+    }
+
+    fn load_progress(&self) -> [u32; 2] {
+        let (completed, requested) = self.assets.background_progress();
+        [completed as u32, requested as u32]
+    }
+
+    // Counts from the last frame, with bytes used and capacity per GPU buffer.
+    fn get_render_stats(&self) -> Result<Table> {
+        let table = self.lua_context.create_table();
+        let stats = match &self.graphics {
+            Some(graphics) => graphics.render_stats(),
+            None => return Ok(table),
+        };
+        table.set("draw_calls", stats.draw_calls)?;
+        table.set("sprites", stats.sprites)?;
+        table.set("particles", stats.particles)?;
+        table.set("lights", stats.lights)?;
+        table.set("tilemap_chunks", stats.tilemap_chunks)?;
+        let buffers = self.lua_context.create_table();
+        for (name, usage) in stats.buffers {
+            let buffer = self.lua_context.create_table();
+            buffer.set("used", usage.used)?;
+            buffer.set("capacity", usage.capacity)?;
+            buffer.set("grown", usage.grown)?;
+            buffers.set(name, buffer)?;
+        }
+        table.set("buffers", buffers)?;
+        Ok(table)
+    }
+
+    // Saves the next frame as a PNG at `path`, relative to the working directory.
+    fn screenshot(&mut self, path: String) {
+        match &mut self.graphics {
+            Some(graphics) => graphics.screenshot(&path),
+            None => println!("No renderer to take a screenshot with"),
+        }
+    }
+
+    // Asks Lua to rebuild animations parsed from a changed json file, then swaps them into
+    // every entity using them. Entity state, timers and positions are left alone.
+    fn reload_animations(&mut self, id: &str) {
+        let rebuilt = match self
+            .lua_context
+            .get_function("ENGINE_reload_animations")
+            .call::<mlua::Table>(id)
+        {
+            Ok(rebuilt) => rebuilt,
+            Err(err) => {
+                println!("Failed to reload {}: {}", id, err);
+                return;
+            }
+        };
+
+        for pair in rebuilt.pairs::<String, mlua::Table>() {
+            let (source, table) = match pair {
+                Ok(pair) => pair,
+                Err(_) => continue,
+            };
+            let animation =
+                Animation::from_lua_table(table, &mut |path: String| self.load_texture(path));
+
+            for (entity, component) in self.world.animations.iter_mut() {
+                let mut changed = false;
+                for existing in component.animations.values_mut() {
+                    if existing.source.as_deref() == Some(source.as_str()) {
+                        *existing = animation.clone();
+                        changed = true;
+                    }
+                }
+                if changed {
+                    if let Some(action_state) = self.world.action_states.get(entity) {
+                        component.refresh_current_frame(&action_state.state);
+                    }
+                }
+            }
+        }
+        debug_log!(self.debugger, "Reloaded animations {}", id);
+    }
+
+    // The frames, tags and slices of an Aseprite json export, for the animation parser. Returns
+    // nil and the error when it can't be loaded.
+    fn load_sprite_sheet(&mut self, id: String) -> std::result::Result<Table, String> {
+        let handle = self
+            .assets
+            .load::<SpriteSheet>(&format!("assets/{}", normalize_asset_id(&id)))
+            .map_err(|err| err.to_string())?;
+        let sheet = self.assets.get(handle).expect("sprite sheet just loaded");
+        sprite_sheet_table(&self.lua_context, sheet).map_err(|err| err.to_string())
+    }
+
+    // `options` can set volume, pitch and bus ("sfx", "ui" or "music"). Returns a voice id for
+    // stop_sound, 0 when the sound couldn't be loaded.
+    fn play_sound(&mut self, id: String, options: Option<Table>) -> u32 {
+        let settings = sound_settings(options.as_ref());
+        match self.load_sound(&id) {
+            Some(sound) => self.audio.play_sound(&sound, settings).0,
+            None => 0,
+        }
+    }
+
+    // Plays a sound that follows an entity around, panned and attenuated relative to the
+    // camera. On top of play_sound's options, `min_distance` is how close it has to be for full
+    // volume and `max_distance` where it goes silent. Returns 0 when out of range.
+    fn play_sound_at(&mut self, entity: u32, id: String, options: Option<Table>) -> u32 {
+        let position = match self.world.transforms_2d.get(&entity) {
+            Some(transform) => [transform.position.x, transform.position.y],
+            None => return 0,
+        };
+        let half_width = self.view_half_width();
+        let emitter = Emitter {
+            position,
+            min_distance: options
+                .as_ref()
+                .and_then(|o| o.get("min_distance").ok())
+                .unwrap_or(half_width * EMITTER_MIN_DISTANCE),
+            max_distance: options
+                .as_ref()
+                .and_then(|o| o.get("max_distance").ok())
+                .unwrap_or(half_width * EMITTER_MAX_DISTANCE),
+        };
+        let settings = sound_settings(options.as_ref());
+
+        let sound = match self.load_sound(&id) {
+            Some(sound) => sound,
+            None => return 0,
+        };
+        match self.audio.play_sound_at(&sound, settings, emitter) {
+            Some(voice) => {
+                self.sound_emitters.insert(voice, entity);
+                voice.0
+            }
+            None => 0,
+        }
+    }
+
+    // Sounds are loaded the first time they play and kept across scenes, unloading them with
+    // a scene would only mean decoding them again on their next play.
+    fn load_sound(&mut self, id: &str) -> Option<Sound> {
+        let id = normalize_asset_id(id);
+        match self.assets.load::<Sound>(&format!("assets/{}", id)) {
+            Ok(handle) => self.assets.get(handle).cloned(),
+            Err(err) => {
+                let error = format!("Failed to load {}: {}", id, err);
+                println!("{}", error);
+                self.report_asset_error(&id, error);
+                None
+            }
         }
     }
 
-    fn load_texture(&mut self, id: String) -> Handle<ImageTexture> {
-        let path = format!("./assets/{}", id);
-        self.graphics
-            .as_mut()
-            .expect("Graphics not initialized")
-            .load_texture_from_path(&id, &path)
+    // Half the width of the world the camera shows.
+    fn view_half_width(&self) -> f32 {
+        let zoom = match &self.graphics {
+            Some(graphics) => graphics.get_camera_info().zoom,
+            None => return 1.0,
+        };
+        zoom * self.width as f32 / self.height.max(1) as f32
+    }
+
+    // Moves positional sounds along with their entities and hears them from the camera.
+    fn update_audio(&mut self, dt: f32) {
+        if let Some(graphics) = &self.graphics {
+            let camera = graphics.get_camera_info();
+            self.audio.set_listener(Listener {
+                position: [camera.position[0], camera.position[1]],
+                pan_width: self.view_half_width(),
+            });
+        }
+
+        let playing: HashSet<VoiceId> = self.audio.emitters().collect();
+        self.sound_emitters
+            .retain(|voice, _| playing.contains(voice));
+        for (voice, entity) in &self.sound_emitters {
+            match self.world.transforms_2d.get(entity) {
+                Some(transform) => self
+                    .audio
+                    .move_emitter(*voice, [transform.position.x, transform.position.y]),
+                // the entity went away with its scene
+                None => self.audio.stop_sound(*voice),
+            }
+        }
+        self.audio.update(dt);
+    }
+
+    fn stop_sound(&mut self, voice: u32) {
+        self.audio.stop_sound(VoiceId(voice));
+    }
+
+    // Crossfades from the current track over `fade` seconds. Asking for the track that's
+    // already playing does nothing, so scenes can share music without restarting it.
+    fn play_music(&mut self, id: String, fade: Option<f32>) {
+        let id = normalize_asset_id(&id);
+        if self.audio.music_id().as_deref() == Some(id.as_str()) {
+            return;
+        }
+        let stream = self
+            .asset_source
+            .read(&format!("assets/{}", id))
+            .map_err(anyhow::Error::from)
+            .and_then(|bytes| MusicStream::new(&id, bytes, true));
+        match stream {
+            Ok(stream) => self.audio.play_music(stream, fade.unwrap_or(0.0)),
+            Err(err) => {
+                let error = format!("Failed to load {}: {}", id, err);
+                println!("{}", error);
+                self.report_asset_error(&id, error);
+            }
+        }
+    }
+
+    fn stop_music(&mut self, fade: Option<f32>) {
+        self.audio.stop_music(fade.unwrap_or(0.0));
+    }
+
+    fn set_bus_volume(&mut self, bus: String, volume: f32) {
+        match Bus::from_name(&bus) {
+            Some(bus) => self.audio.set_bus_volume(bus, volume),
+            None => println!("Unknown audio bus {}", bus),
+        }
     }
 
     fn flip(&mut self, entity: u32, x: bool, y: bool) {
         self.world.flips.insert(entity, FlipComponent { x, y });
+    }
+
+    fn set_rotation(&mut self, entity: u32, radians: f32) {
         if let Some(t) = self.world.transforms_2d.get_mut(&entity) {
-            t.scale.x = if x { -t.scale.x.abs() } else { t.scale.x.abs() };
-            t.scale.y = if y { -t.scale.y.abs() } else { t.scale.y.abs() };
+            t.rotation_radians = radians;
+        }
+    }
+
+    fn get_rotation(&self, entity: u32) -> f32 {
+        self.world
+            .transforms_2d
+            .get(&entity)
+            .map(|t| t.rotation_radians)
+            .unwrap_or(0.0)
+    }
+
+    // Fractions of the sprite's size from its centre, (-0.5, -0.5) being its bottom-left corner.
+    fn set_pivot(&mut self, entity: u32, x: f32, y: f32) {
+        if let Some(t) = self.world.transforms_2d.get_mut(&entity) {
+            t.pivot = Vector2::new(x, y);
+        }
+    }
+
+    fn set_tint(&mut self, entity: u32, r: f32, g: f32, b: f32, a: Option<f32>) {
+        let effect = self.world.sprite_effects.entry(entity).or_default();
+        effect.tint = [r, g, b, a.unwrap_or(effect.tint[3])];
+    }
+
+    fn set_opacity(&mut self, entity: u32, opacity: f32) {
+        let effect = self.world.sprite_effects.entry(entity).or_default();
+        effect.tint[3] = opacity.clamp(0.0, 1.0);
+    }
+
+    // Adds `r`, `g`, `b` on top of the sprite, fading out over `duration` seconds.
+    fn flash(&mut self, entity: u32, r: f32, g: f32, b: f32, duration: f32) {
+        self.world
+            .sprite_effects
+            .entry(entity)
+            .or_default()
+            .flash([r, g, b], duration);
+    }
+
+    // A preset name ("grayscale", "sepia", "invert"), 16 or 20 numbers, or nil to clear it.
+    fn set_color_matrix(&mut self, entity: u32, matrix: mlua::Value) -> Result<()> {
+        let matrix = match matrix {
+            mlua::Value::Nil => ColorMatrix::IDENTITY,
+            matrix => ColorMatrix::from_lua_value(matrix)?,
+        };
+        self.world
+            .sprite_effects
+            .entry(entity)
+            .or_default()
+            .color_matrix = matrix;
+        Ok(())
+    }
+
+    // Options are {z, sort_offset, sort_pivot}, see RenderLayerComponent.
+    fn set_render_layer(
+        &mut self,
+        entity: u32,
+        layer: String,
+        options: Option<Table>,
+    ) -> Result<()> {
+        let layer = match RenderLayer::from_str(&layer) {
+            Ok(layer) => layer,
+            Err(()) => {
+                println!("Unknown render layer {}", layer);
+                return Ok(());
+            }
+        };
+        let mut component = self
+            .world
+            .render_layers
+            .get(&entity)
+            .copied()
+            .unwrap_or_else(|| RenderLayerComponent::new(layer));
+        component.layer = layer;
+        if let Some(options) = options {
+            component.apply_lua_table(&options)?;
+        }
+        self.world.render_layers.insert(entity, component);
+        Ok(())
+    }
+
+    fn set_layer_sort_mode(&mut self, layer: String, mode: String) {
+        match (RenderLayer::from_str(&layer), SortMode::from_str(&mode)) {
+            (Ok(layer), Ok(mode)) => self.world.layer_sort_modes[layer.index()] = mode,
+            (Err(()), _) => println!("Unknown render layer {}", layer),
+            (_, Err(())) => println!("Unknown sort mode {}", mode),
+        }
+    }
+
+    // Takes everything ParticleEmitterConfig reads, plus `x` and `y`, or `follow` to move along
+    // with an entity. It starts right away unless `emitting` is false.
+    fn create_emitter(&mut self, lua_config: Table) -> Result<u32> {
+        let config = ParticleEmitterConfig::from_lua_table(&lua_config, &mut |path: String| {
+            self.load_texture(path)
+        })?;
+        let x: f32 = lua_config.get::<Option<f32>>("x")?.unwrap_or(0.0);
+        let y: f32 = lua_config.get::<Option<f32>>("y")?.unwrap_or(0.0);
+
+        let entity = self.world.new_entity();
+        let seed = entity
+            ^ SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|time| time.subsec_nanos())
+                .unwrap_or(0);
+        let mut emitter = ParticleEmitterComponent::new(config, Vector2::new(x, y), seed);
+        emitter.follow = lua_config.get("follow")?;
+        if lua_config.get::<Option<bool>>("emitting")?.unwrap_or(true) {
+            emitter.start();
+        }
+        self.world.particle_emitters.insert(entity, emitter);
+        Ok(entity)
+    }
+
+    fn emit_particles(&mut self, emitter: u32, count: u32) {
+        if let Some(emitter) = self.world.particle_emitters.get_mut(&emitter) {
+            emitter.burst(count);
+        }
+    }
+
+    fn start_emitter(&mut self, emitter: u32) {
+        if let Some(emitter) = self.world.particle_emitters.get_mut(&emitter) {
+            emitter.start();
+        }
+    }
+
+    // Particles already out live out their lifetime.
+    fn stop_emitter(&mut self, emitter: u32) {
+        if let Some(emitter) = self.world.particle_emitters.get_mut(&emitter) {
+            emitter.emitting = false;
+        }
+    }
+
+    fn move_emitter(&mut self, emitter: u32, x: f32, y: f32) {
+        if let Some(emitter) = self.world.particle_emitters.get_mut(&emitter) {
+            emitter.follow = None;
+            emitter.position = Vector2::new(x, y);
+        }
+    }
+
+    fn attach_emitter(&mut self, emitter: u32, entity: u32) {
+        if let Some(emitter) = self.world.particle_emitters.get_mut(&emitter) {
+            emitter.follow = Some(entity);
+        }
+    }
+
+    fn remove_emitter(&mut self, emitter: u32) {
+        self.world.particle_emitters.remove(&emitter);
+    }
+
+    // Takes everything LightComponent reads, plus `follow` to move along with an entity.
+    fn create_light(&mut self, lua_config: Table) -> Result<u32> {
+        let mut light = LightComponent::default();
+        light.apply_lua_table(&lua_config)?;
+        light.follow = lua_config.get("follow")?;
+        let entity = self.world.new_entity();
+        self.world.lights.insert(entity, light);
+        Ok(entity)
+    }
+
+    // Keys left out keep their current value.
+    fn set_light(&mut self, light: u32, options: Table) -> Result<()> {
+        match self.world.lights.get_mut(&light) {
+            Some(light) => light.apply_lua_table(&options),
+            None => Ok(()),
+        }
+    }
+
+    fn move_light(&mut self, light: u32, x: f32, y: f32) {
+        if let Some(light) = self.world.lights.get_mut(&light) {
+            light.follow = None;
+            light.position = Vector2::new(x, y);
+        }
+    }
+
+    fn attach_light(&mut self, light: u32, entity: u32) {
+        if let Some(light) = self.world.lights.get_mut(&light) {
+            light.follow = Some(entity);
+        }
+    }
+
+    fn remove_light(&mut self, light: u32) {
+        self.world.lights.remove(&light);
+    }
+
+    // Also turns lighting on, everything is this lit where no light reaches.
+    fn set_ambient_light(&mut self, r: f32, g: f32, b: f32) {
+        self.world.lighting.ambient = [r, g, b];
+        self.world.lighting.enabled = true;
+    }
+
+    fn set_lighting(&mut self, enabled: bool) {
+        self.world.lighting.enabled = enabled;
+    }
+
+    // Turns `effect` on, or off with {enabled = false}. Keys left out keep their current
+    // value, see apply_post_effect_options for what each effect reads.
+    fn set_post_effect(&mut self, effect: String, options: Option<Table>) -> Result<()> {
+        let effect_kind = match PostEffect::from_str(&effect) {
+            Ok(effect) => effect,
+            Err(()) => {
+                println!("Unknown post effect {}", effect);
+                return Ok(());
+            }
+        };
+        let effects = match self.graphics.as_mut() {
+            Some(graphics) => graphics.post_effects(),
+            None => return Ok(()),
+        };
+        let enabled = match &options {
+            Some(options) => {
+                apply_post_effect_options(effects, effect_kind, options)?;
+                options.get::<Option<bool>>("enabled")?.unwrap_or(true)
+            }
+            None => true,
+        };
+        effects.set_enabled(effect_kind, enabled);
+        Ok(())
+    }
+
+    // Effects run in the order listed, ones left out are skipped even while enabled.
+    fn set_post_effect_order(&mut self, order: Vec<String>) {
+        let effects = match self.graphics.as_mut() {
+            Some(graphics) => graphics.post_effects(),
+            None => return,
+        };
+        effects.order = order
+            .iter()
+            .filter_map(|name| {
+                PostEffect::from_str(name)
+                    .inspect_err(|_| println!("Unknown post effect {}", name))
+                    .ok()
+            })
+            .collect();
+    }
+
+    // Covers the screen in the colour, fading out over `duration` seconds.
+    fn flash_screen(&mut self, r: f32, g: f32, b: f32, duration: f32) {
+        if let Some(graphics) = self.graphics.as_mut() {
+            graphics.post_effects().flash([r, g, b], duration);
         }
     }
 
@@ -168,6 +939,7 @@ impl Engine {
             self.physics_accumulator -= self.physics_tick_rate;
 
             if self.dimensions == Dimensions::Two {
+                self.world.sync_tile_collisions(&mut self.physics);
                 self.physics.step(self.physics_tick_rate);
                 if self.camera_mode == CameraOption::Follow {
                     self.update_camera_follow_player(self.physics_tick_rate);
@@ -182,6 +954,9 @@ impl Engine {
             .call::<()>(dt32);
 
         animation_system_update_frames(&mut self.world, dt32);
+        sprite_effect_system_update(&mut self.world, dt32);
+        particle_system_update(&mut self.world, dt32);
+        light_system_update(&mut self.world);
         return Ok(());
     }
 
@@ -290,6 +1065,14 @@ impl Engine {
         self.canvas.unload();
         self.world.unload();
         self.physics.unload();
+
+        if let Some(graphics) = self.graphics.as_mut() {
+            for id in self.scene_textures.drain() {
+                graphics.release_texture(&id);
+            }
+            graphics.unload_unused_textures();
+        }
+        self.scene += 1;
     }
 
     fn create_body(&mut self, lua_element: mlua::Table) -> [u32; 2] {
@@ -335,8 +1118,12 @@ impl Engine {
                 let numeric_key =
                     key.as_u32()
                         .expect("Numeric key required for Action States") as u8;
+                let normal_map: Option<String> = tbl.get("normal_map").unwrap_or(None);
                 let animation =
                     Animation::from_lua_table(tbl, &mut |path: String| self.load_texture(path));
+                if let Some(normal_map) = normal_map {
+                    self.set_normal_map(animation.sprite.clone(), normal_map);
+                }
                 let action_state = ActionState::from(numeric_key);
                 animations_map.insert(action_state, animation);
             }
@@ -364,6 +1151,7 @@ impl Engine {
                         half_extents: Vector2 { x: 0.5, y: 0.5 },
                     },
                     rotation_radians: 0.0,
+                    pivot: Vector2::new(0.0, 0.0),
                 },
             );
             self.world.health_bars.insert(
@@ -376,6 +1164,15 @@ impl Engine {
             self.world
                 .action_states
                 .insert(entity.clone(), ActionStateComponent { state });
+            if let Ok(render_layer) = lua_element.get::<Table>("render_layer") {
+                let mut component = RenderLayerComponent::new(RenderLayer::Entities);
+                match component.apply_lua_table(&render_layer) {
+                    Ok(()) => {
+                        self.world.render_layers.insert(entity, component);
+                    }
+                    Err(err) => println!("Ignoring render layer of body {}: {}", entity, err),
+                }
+            }
 
             self.physics.add_body(
                 entity.clone(),
@@ -408,29 +1205,371 @@ impl Engine {
         [entity.into(), 0]
     }
 
-    pub fn screen_to_world(&self, loc: [f32; 2]) -> [f32; 2] {
-        let size = self.get_window_size();
-        let half_screen = [size[0] as f32 * 0.5, size[1] as f32 * 0.5];
-        // Pixel offset from screen center
-        let offset = [loc[0] - half_screen[0], loc[1] - half_screen[1]];
+    // `x` and `y` place the top-left corner of the map.
+    fn create_tilemap(&mut self, lua_tilemap: mlua::Table) -> Result<u32> {
+        let x: f32 = lua_tilemap.get("x").unwrap_or(0.0);
+        let y: f32 = lua_tilemap.get("y").unwrap_or(0.0);
+        let collision: Option<Table> = lua_tilemap.get("collision").ok();
+        let layers_table: Table = lua_tilemap.get("layers")?;
+        let tilemap = TilemapComponent::from_lua_table(lua_tilemap, &mut |path: String| {
+            self.load_texture(path)
+        })?;
+
+        let entity = self.world.new_entity();
+        self.world.transforms_2d.insert(
+            entity,
+            Transform2D {
+                position: Vector2::new(x, y),
+                scale: Vector2::new(1.0, 1.0),
+                shape: Shape2D::Rectangle {
+                    half_extents: Vector2 { x: 0.5, y: 0.5 },
+                },
+                rotation_radians: 0.0,
+                pivot: Vector2::new(0.0, 0.0),
+            },
+        );
 
-        let graphics = match &self.graphics {
-            Some(canvas) => canvas,
-            None => return [0.0, 0.0],
+        // layers marked `collision = true` are solid wherever a tile is set
+        let (masks, layers) = self.collision_masks(collision);
+        for (layer_table, layer) in layers_table.sequence_values::<Table>().zip(&tilemap.layers) {
+            if !layer_table?.get::<bool>("collision").unwrap_or(false) {
+                continue;
+            }
+            let mut tile_collision = TileCollision::new(
+                layer.name.clone(),
+                [layer.width, layer.height],
+                layer.tiles.iter().map(|tile| *tile != EMPTY_TILE).collect(),
+                [x, y],
+                tilemap.tile_world_size,
+                masks,
+                layers,
+            );
+            tile_collision.tilemaps = vec![entity];
+            let collision_entity = self.world.new_entity();
+            self.world
+                .tile_collisions
+                .insert(collision_entity, tile_collision);
+        }
+        self.world.tilemaps.insert(entity, tilemap);
+        Ok(entity)
+    }
+
+    // `entity` is a tilemap or a collision layer returned by load_level. Solid layers keep
+    // their colliders in step with the tiles.
+    fn set_tile(&mut self, entity: u32, layer: String, x: u32, y: u32, tile: u32) -> bool {
+        let mut in_layer = false;
+        if let Some(tilemap) = self.world.tilemaps.get_mut(&entity) {
+            if let Some(index) = tilemap.layer_index(&layer) {
+                in_layer = tilemap.set_tile(index, x, y, tile);
+            }
+        }
+        let in_collision = self.update_tile_collision(entity, &layer, x, y, tile);
+        in_layer || in_collision
+    }
+
+    // Paints a cell with one of the tileset's autotiles, or clears it when `terrain` is nil,
+    // and re-picks the neighbouring tiles to match.
+    fn paint_tile(
+        &mut self,
+        entity: u32,
+        layer: String,
+        x: u32,
+        y: u32,
+        terrain: Option<String>,
+    ) -> bool {
+        let tilemap = match self.world.tilemaps.get_mut(&entity) {
+            Some(tilemap) => tilemap,
+            None => return false,
         };
+        let index = match tilemap.layer_index(&layer) {
+            Some(index) => index,
+            None => return false,
+        };
+        let changed = match tilemap.paint(index, x, y, terrain.as_deref()) {
+            Some(changed) => changed,
+            None => return false,
+        };
+        for [cx, cy] in changed {
+            let tile = self.world.tilemaps[&entity].layers[index].tile(cx, cy);
+            self.update_tile_collision(entity, &layer, cx, cy, tile);
+        }
+        true
+    }
+
+    // Returns true when `entity` is a hidden collision layer holding the cell.
+    fn update_tile_collision(
+        &mut self,
+        entity: u32,
+        layer: &str,
+        x: u32,
+        y: u32,
+        tile: u32,
+    ) -> bool {
+        let mut in_layer = false;
+        for (collision_entity, collision) in self.world.tile_collisions.iter_mut() {
+            if collision.layer != layer {
+                continue;
+            }
+            if *collision_entity == entity && collision.tilemaps.is_empty() {
+                in_layer = collision.set_solid(x, y, tile != EMPTY_TILE);
+            } else if collision.tilemaps.contains(&entity) {
+                // the layer may be split over several tilemaps, one per tileset
+                let solid = collision.tilemaps.iter().any(|tilemap| {
+                    self.world.tilemaps.get(tilemap).is_some_and(|tilemap| {
+                        tilemap
+                            .layer_index(layer)
+                            .is_some_and(|index| tilemap.layers[index].tile(x, y) != EMPTY_TILE)
+                    })
+                });
+                collision.set_solid(x, y, solid);
+            }
+        }
+        in_layer
+    }
+
+    // Builds a Tiled (.tmx, .tmj) or LDtk level with its top-left corner at options x and y,
+    // `tile_size` world units per tile. Tile layers become tilemaps, collision layers merged
+    // static colliders using options.collision masks and layers, and everything else in object
+    // layers is handed to ruin.spawn_level_object. Returns the tilemaps and hidden collision
+    // layers, bottom layer first, for set_tile.
+    fn load_level(&mut self, id: String, options: Option<Table>) -> Result<Vec<u32>> {
+        let options = options.unwrap_or_else(|| self.lua_context.create_table());
+        let name: Option<String> = options.get("level").ok();
+        let x: f32 = options.get("x").unwrap_or(0.0);
+        let y: f32 = options.get("y").unwrap_or(0.0);
+        let tile_size: f32 = options.get("tile_size").unwrap_or(1.0);
+        let collision: Option<Table> = options.get("collision").ok();
+
+        let id = normalize_asset_id(&id);
+        let loaded = self
+            .assets
+            .load::<LevelFile>(&format!("assets/{}", id))
+            .and_then(|handle| {
+                Ok(self
+                    .assets
+                    .get(handle)
+                    .unwrap()
+                    .level(name.as_deref())?
+                    .clone())
+            });
+        let level = match loaded {
+            Ok(level) => level,
+            Err(err) => {
+                println!("Failed to load level {}: {}", id, err);
+                self.report_asset_error(&id, err.to_string());
+                return Err(mlua::Error::external(err));
+            }
+        };
+
+        // pixels to world units, y goes up in the world
+        let scale = tile_size / level.tile_size[0].max(1) as f32;
+        let to_world = |px: f32, py: f32| [x + px * scale, y - py * scale];
+
+        let mut tilemaps = Vec::new();
+        let mut layer_tilemaps = vec![Vec::new(); level.tile_layers.len()];
+        for (index, layer) in level.tile_layers.iter().enumerate() {
+            if !layer.visible {
+                continue;
+            }
+            // a "render_layer" property puts the layer over or under other things, e.g. foreground
+            let render_layer = layer
+                .properties
+                .iter()
+                .find_map(|(key, value)| match value {
+                    PropertyValue::String(name) if key == "render_layer" => {
+                        RenderLayer::from_str(name).ok()
+                    }
+                    _ => None,
+                });
+            // a tilemap draws from one tileset, so layers mixing tilesets are split up
+            let mut split: Vec<(usize, Vec<u32>)> = Vec::new();
+            for (cell, tile) in layer.tiles.iter().enumerate() {
+                if let Some((tileset, local)) = level.tileset_for(*tile) {
+                    let index = match split.iter().position(|(used, _)| *used == tileset) {
+                        Some(index) => index,
+                        None => {
+                            split.push((tileset, vec![0; layer.tiles.len()]));
+                            split.len() - 1
+                        }
+                    };
+                    split[index].1[cell] = local;
+                }
+            }
+
+            for (tileset, tiles) in split {
+                let tileset = &level.tilesets[tileset];
+                let sprite = tileset
+                    .image
+                    .strip_prefix("assets/")
+                    .unwrap_or(&tileset.image)
+                    .to_string();
+                let region = self.load_texture(sprite.clone());
+                let entity = self.world.new_entity();
+                self.world.transforms_2d.insert(
+                    entity,
+                    Transform2D {
+                        position: to_world(layer.offset[0], layer.offset[1]).into(),
+                        scale: Vector2::new(1.0, 1.0),
+                        shape: Shape2D::Rectangle {
+                            half_extents: Vector2 { x: 0.5, y: 0.5 },
+                        },
+                        rotation_radians: 0.0,
+                        pivot: Vector2::new(0.0, 0.0),
+                    },
+                );
+                self.world.tilemaps.insert(
+                    entity,
+                    TilemapComponent {
+                        tileset: Tileset::new(
+                            sprite,
+                            &region,
+                            tileset.tile_size,
+                            tileset.margin,
+                            tileset.spacing,
+                        ),
+                        tile_world_size: level.tile_size.map(|size| size as f32 * scale),
+                        layers: vec![TileLayer {
+                            name: layer.name.clone(),
+                            width: layer.width,
+                            height: layer.height,
+                            tiles,
+                        }],
+                        revision: 0,
+                    },
+                );
+                if let Some(render_layer) = render_layer {
+                    self.world
+                        .render_layers
+                        .insert(entity, RenderLayerComponent::new(render_layer));
+                }
+                tilemaps.push(entity);
+                layer_tilemaps[index].push(entity);
+            }
+        }
 
-        let camera = graphics.get_camera_info();
-        // World units per pixel
-        let units_per_pixel = (camera.zoom * 2.0) / size[1] as f32;
+        let (masks, layers) = self.collision_masks(collision);
+        let cell_size = level.tile_size.map(|size| size as f32 * scale);
+        for (index, layer) in level.tile_layers.iter().enumerate() {
+            if !layer.collision {
+                continue;
+            }
+            let mut tile_collision = TileCollision::new(
+                layer.name.clone(),
+                [layer.width, layer.height],
+                layer.tiles.iter().map(|tile| *tile != EMPTY_TILE).collect(),
+                to_world(layer.offset[0], layer.offset[1]),
+                cell_size,
+                masks,
+                layers,
+            );
+            tile_collision.tilemaps = layer_tilemaps[index].clone();
+            let entity = self.world.new_entity();
+            self.world.tile_collisions.insert(entity, tile_collision);
+            // nothing is drawn for hidden collision layers, so hand out the collision itself
+            if !layer.visible {
+                tilemaps.push(entity);
+            }
+        }
+        self.world.sync_tile_collisions(&mut self.physics);
 
-        // Scaled world offset
-        let world_offset = [offset[0] * units_per_pixel, offset[1] * units_per_pixel];
+        for [px, py, pw, ph] in level.colliders.iter() {
+            let center = to_world(px + pw / 2.0, py + ph / 2.0);
+            self.add_static_collider(center, [pw * scale / 2.0, ph * scale / 2.0], masks, layers);
+        }
+
+        for object in level.objects.iter() {
+            let table = self.level_object_table(object, &to_world, scale)?;
+            self.lua_context
+                .get_function("ENGINE_spawn_level_object")
+                .call::<()>(table)?;
+        }
+
+        Ok(tilemaps)
+    }
 
-        // Final world position is camera center + offset
-        [
-            camera.position[0] + world_offset[0],
-            camera.position[1] - world_offset[1], // Y is flipped (screen Y-down vs world Y-up)
-        ]
+    // Masks and layers from a MaskAndLayerBuilder table, none when missing.
+    fn collision_masks(&self, collision: Option<Table>) -> (u8, u8) {
+        let bits = |key: &str| {
+            let table = collision
+                .as_ref()
+                .and_then(|collision| collision.get::<Table>(key).ok())
+                .unwrap_or_else(|| self.lua_context.create_table());
+            vecbool_to_u8(LuaExtendedExecutor::table_to_vec_8(table))
+        };
+        (bits("masks"), bits("layers"))
+    }
+
+    fn add_static_collider(
+        &mut self,
+        center: [f32; 2],
+        half_extents: [f32; 2],
+        masks: u8,
+        layers: u8,
+    ) {
+        let entity = self.world.new_entity();
+        self.physics.add_body(
+            entity,
+            Body2D::new(
+                Point2D {
+                    x: center[0],
+                    y: center[1],
+                },
+                Vector2D { x: 0.0, y: 0.0 },
+                BodyType2D::Static,
+                true,
+            ),
+        );
+        self.physics.add_collider(
+            &entity,
+            Area2D {
+                shape: Shape2D::Rectangle {
+                    half_extents: Vector2::from(half_extents),
+                },
+                offset: Vector2 { x: 0.0, y: 0.0 },
+                masks,
+                layers,
+                active: true,
+            },
+        );
+    }
+
+    // x and y are the object's centre in world units.
+    fn level_object_table(
+        &self,
+        object: &LevelObject,
+        to_world: &impl Fn(f32, f32) -> [f32; 2],
+        scale: f32,
+    ) -> Result<Table> {
+        let [w, h] = object.size;
+        let center = to_world(object.position[0] + w / 2.0, object.position[1] + h / 2.0);
+
+        let properties = self.lua_context.create_table();
+        for (key, value) in object.properties.iter() {
+            match value {
+                PropertyValue::Bool(value) => properties.set(key.as_str(), *value)?,
+                PropertyValue::Int(value) => properties.set(key.as_str(), *value)?,
+                PropertyValue::Float(value) => properties.set(key.as_str(), *value)?,
+                PropertyValue::String(value) => properties.set(key.as_str(), value.as_str())?,
+            }
+        }
+
+        let table = self.lua_context.create_table();
+        table.set("name", object.name.as_str())?;
+        table.set("type", object.kind.as_str())?;
+        table.set("layer", object.layer.as_str())?;
+        table.set("x", center[0])?;
+        table.set("y", center[1])?;
+        table.set("width", w * scale)?;
+        table.set("height", h * scale)?;
+        table.set("properties", properties)?;
+        Ok(table)
+    }
+
+    pub fn screen_to_world(&self, loc: [f32; 2]) -> [f32; 2] {
+        match &self.graphics {
+            Some(graphics) => graphics.screen_to_world(loc),
+            None => [0.0, 0.0],
+        }
     }
 
     pub fn configure_camera(&mut self, _config: mlua::Table) -> Result<()> {
@@ -473,6 +1612,33 @@ impl Engine {
         let lua_engine = self.lua_context.create_table();
         expose_fn!(self.lua_context.lua, self_ptr, lua_engine, unload_scene, ());
         expose_fn!(self.lua_context.lua, self_ptr, lua_engine, flip, (id: u32, x: bool, y: bool));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, set_rotation, (id: u32, radians: f32));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, get_rotation, (id: u32) -> f32);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, set_pivot, (id: u32, x: f32, y: f32));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, set_tint, (id: u32, r: f32, g: f32, b: f32, a: Option<f32>));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, set_opacity, (id: u32, opacity: f32));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, flash, (id: u32, r: f32, g: f32, b: f32, duration: f32));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, set_color_matrix, (id: u32, matrix: mlua::Value) -> Result<()>);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, set_render_layer, (id: u32, layer: String, options: Option<Table>) -> Result<()>);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, set_layer_sort_mode, (layer: String, mode: String));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, create_emitter, (config: Table) -> Result<u32>);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, emit_particles, (id: u32, count: u32));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, start_emitter, (id: u32));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, stop_emitter, (id: u32));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, move_emitter, (id: u32, x: f32, y: f32));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, attach_emitter, (id: u32, entity: u32));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, remove_emitter, (id: u32));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, create_light, (config: Table) -> Result<u32>);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, set_light, (id: u32, options: Table) -> Result<()>);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, move_light, (id: u32, x: f32, y: f32));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, attach_light, (id: u32, entity: u32));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, remove_light, (id: u32));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, set_ambient_light, (r: f32, g: f32, b: f32));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, set_lighting, (enabled: bool));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, set_normal_map, (sprite: String, normal_map: String));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, set_post_effect, (effect: String, options: Option<Table>) -> Result<()>);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, set_post_effect_order, (order: Vec<String>));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, flash_screen, (r: f32, g: f32, b: f32, duration: f32));
         expose_fn!(self.lua_context.lua, self_ptr, lua_engine, apply_force_2d, (id: u32, x: f32, y: f32));
         expose_fn!(self.lua_context.lua, self_ptr, lua_engine, apply_impulse_2d, (id: u32, x: f32, y: f32));
         expose_fn!(self.lua_context.lua, self_ptr, lua_engine, apply_move_2d, (id: u32, x: f32, y: f32));
@@ -483,11 +1649,26 @@ impl Engine {
         expose_fn!(self.lua_context.lua, self_ptr, lua_engine, set_velocity_2d, (id: u32, x: f32, y: f32));
         expose_fn!(self.lua_context.lua, self_ptr, lua_engine, set_state, (id: u32, state: u8));
         expose_fn!(self.lua_context.lua, self_ptr, lua_engine, get_window_size, () -> [u32; 2]);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, preload, (assets: Table));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, load_sprite_sheet, (id: String) -> std::result::Result<Table, String>);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, load_progress, () -> [u32; 2]);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, get_render_stats, () -> Result<Table>);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, screenshot, (path: String));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, play_sound, (id: String, options: Option<Table>) -> u32);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, play_sound_at, (entity: u32, id: String, options: Option<Table>) -> u32);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, stop_sound, (voice: u32));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, play_music, (id: String, fade: Option<f32>));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, stop_music, (fade: Option<f32>));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, set_bus_volume, (bus: String, volume: f32));
         expose_fn!(self.lua_context.lua, self_ptr, lua_engine, get_velocity_2d, (id: u32) -> [f32; 2]);
         expose_fn!(self.lua_context.lua, self_ptr, lua_engine, get_position_2d, (id: u32) -> [f32; 2]);
         expose_fn!(self.lua_context.lua, self_ptr, lua_engine, damage, (id: u32, amount: u16) -> bool);
         expose_fn!(self.lua_context.lua, self_ptr, lua_engine, get_health_table, (id: u32) -> Table);
         expose_fn!(self.lua_context.lua, self_ptr, lua_engine, create_body, (data: Table) -> [u32; 2]);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, create_tilemap, (data: Table) -> Result<u32>);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, load_level, (id: String, options: Option<Table>) -> Result<Vec<u32>>);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, set_tile, (id: u32, layer: String, x: u32, y: u32, tile: u32) -> bool);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, paint_tile, (id: u32, layer: String, x: u32, y: u32, terrain: Option<String>) -> bool);
         expose_fn!(self.lua_context.lua, self_ptr, lua_engine, create_canvas_view, (data: Table) -> [u32; 1]);
         expose_fn!(self.lua_context.lua, self_ptr, lua_engine, configure_camera, (data: Table) -> Result<()>);
         expose_fn!(
@@ -577,6 +1758,10 @@ impl Engine {
         self.last_frame = now;
         let bp = Instant::now();
 
+        self.poll_loaded_textures();
+        self.poll_asset_changes();
+        self.update_audio(dt.as_secs_f32());
+
         if !self.physics_paused {
             let _ = self.update(dt);
         }
@@ -588,6 +1773,7 @@ impl Engine {
             None => return,
         };
         let _ = graphics.update_camera();
+        graphics.post_effects().update(dt.as_secs_f32());
         let bg = Instant::now();
         let _ = graphics.render(&self.world, &self.canvas, &self.physics);
         //println!("Render: {:?}", bg.elapsed().as_secs_f64());
@@ -601,14 +1787,22 @@ impl Engine {
     }
 
     fn initialize(&mut self, event_loop: &ActiveEventLoop) {
-        let window_attributes =
-            Window::default_attributes().with_inner_size(LogicalSize::new(self.width, self.height));
+        let window_attributes = Window::default_attributes()
+            .with_title(WINDOW_TITLE)
+            .with_inner_size(LogicalSize::new(self.width, self.height));
         let window = Arc::new(event_loop.create_window(window_attributes).unwrap());
 
         if self.dimensions == Dimensions::Two {
             let camera_2d = Camera2D::new(&self.camera2d_config);
             self.graphics = Some(Box::new(
-                pollster::block_on(Graphics2D::new(window.clone(), camera_2d)).unwrap(),
+                pollster::block_on(Graphics2D::new(
+                    window.clone(),
+                    camera_2d,
+                    self.virtual_resolution,
+                    self.scale_mode,
+                    self.asset_source.clone(),
+                ))
+                .unwrap(),
             ));
         }
 
@@ -686,3 +1880,144 @@ impl ApplicationHandler<Graphics2D> for Engine {
         }
     }
 }
+
+fn sound_settings(options: Option<&Table>) -> SoundSettings {
+    let mut settings = SoundSettings::default();
+    if let Some(options) = options {
+        settings.volume = options.get("volume").unwrap_or(settings.volume);
+        settings.pitch = options.get("pitch").unwrap_or(settings.pitch);
+        if let Ok(bus) = options.get::<String>("bus") {
+            settings.bus = Bus::from_name(&bus).unwrap_or(settings.bus);
+        }
+    }
+    settings
+}
+
+// bloom: threshold, intensity, spread. color_grade: lut (an asset id), amount. vignette:
+// strength, radius, softness, color. scanlines: intensity, spacing, curvature. flash: color,
+// duration, starting it.
+fn apply_post_effect_options(
+    effects: &mut PostEffects,
+    effect: PostEffect,
+    options: &Table,
+) -> Result<()> {
+    let color = |current: [f32; 3]| -> Result<[f32; 3]> {
+        match options.get::<Option<Table>>("color")? {
+            Some(color) => Ok([color.get(1)?, color.get(2)?, color.get(3)?]),
+            None => Ok(current),
+        }
+    };
+    let number = |key: &str, current: f32| -> Result<f32> {
+        Ok(options.get::<Option<f32>>(key)?.unwrap_or(current))
+    };
+    match effect {
+        PostEffect::Bloom => {
+            let bloom = &mut effects.bloom;
+            bloom.threshold = number("threshold", bloom.threshold)?;
+            bloom.intensity = number("intensity", bloom.intensity)?;
+            bloom.spread = number("spread", bloom.spread)?;
+        }
+        PostEffect::ColorGrade => {
+            let grade = &mut effects.color_grade;
+            if let Some(lut) = options.get::<Option<String>>("lut")? {
+                grade.lut = Some(format!("./assets/{}", normalize_asset_id(&lut)));
+            }
+            grade.amount = number("amount", grade.amount)?;
+        }
+        PostEffect::Vignette => {
+            let vignette = &mut effects.vignette;
+            vignette.strength = number("strength", vignette.strength)?;
+            vignette.radius = number("radius", vignette.radius)?;
+            vignette.softness = number("softness", vignette.softness)?;
+            vignette.color = color(vignette.color)?;
+        }
+        PostEffect::Scanlines => {
+            let scanlines = &mut effects.scanlines;
+            scanlines.intensity = number("intensity", scanlines.intensity)?;
+            scanlines.spacing = number("spacing", scanlines.spacing)?;
+            scanlines.curvature = number("curvature", scanlines.curvature)?;
+        }
+        PostEffect::Flash => {
+            let duration = number("duration", effects.flash.duration)?;
+            let color = color(effects.flash.color)?;
+            effects.flash(color, duration);
+        }
+    }
+    Ok(())
+}
+
+// Named like the json's own fields, with `image` the sheet's asset id.
+fn sprite_sheet_table(lua: &LuaExtendedExecutor, sheet: &SpriteSheet) -> Result<Table> {
+    let rect = |[x, y, w, h]: [u32; 4]| -> Result<Table> {
+        let table = lua.create_table();
+        table.set("x", x)?;
+        table.set("y", y)?;
+        table.set("w", w)?;
+        table.set("h", h)?;
+        Ok(table)
+    };
+    let size = |[w, h]: [u32; 2]| -> Result<Table> {
+        let table = lua.create_table();
+        table.set("w", w)?;
+        table.set("h", h)?;
+        Ok(table)
+    };
+
+    let frames = lua.create_table();
+    for frame in &sheet.frames {
+        let table = lua.create_table();
+        table.set("name", frame.name.as_str())?;
+        table.set("frame", rect(frame.rect)?)?;
+        table.set("sourceSize", size(frame.source_size)?)?;
+        let [x, y] = frame.trim;
+        table.set(
+            "spriteSourceSize",
+            rect([x, y, frame.rect[2], frame.rect[3]])?,
+        )?;
+        table.set("duration", frame.duration_ms)?;
+        frames.push(table)?;
+    }
+
+    let tags = lua.create_table();
+    for tag in &sheet.tags {
+        let table = lua.create_table();
+        table.set("name", tag.name.as_str())?;
+        table.set("from", tag.from)?;
+        table.set("to", tag.to)?;
+        table.set("direction", tag.direction.as_str())?;
+        tags.push(table)?;
+    }
+
+    let slices = lua.create_table();
+    for slice in &sheet.slices {
+        let keys = lua.create_table();
+        for key in &slice.keys {
+            let table = lua.create_table();
+            table.set("frame", key.frame)?;
+            table.set("bounds", rect(key.bounds)?)?;
+            if let Some([x, y]) = key.pivot {
+                let pivot = lua.create_table();
+                pivot.set("x", x)?;
+                pivot.set("y", y)?;
+                table.set("pivot", pivot)?;
+            }
+            keys.push(table)?;
+        }
+        let table = lua.create_table();
+        table.set("name", slice.name.as_str())?;
+        table.set("data", slice.data.as_deref())?;
+        table.set("keys", keys)?;
+        slices.push(table)?;
+    }
+
+    let meta = lua.create_table();
+    meta.set("size", size(sheet.size)?)?;
+    meta.set("frameTags", tags)?;
+    meta.set("slices", slices)?;
+
+    let table = lua.create_table();
+    table.set("image", sheet.image.strip_prefix("assets/"))?;
+    table.set("frames", frames)?;
+    table.set("meta", meta)?;
+    Ok(table)
+}
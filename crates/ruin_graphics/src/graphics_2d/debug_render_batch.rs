@@ -3,13 +3,13 @@ use std::collections::HashMap;
 use wgpu::include_wgsl;
 
 use crate::graphics_2d::{
+    gpu_buffer::{GpuMesh, GrowableBuffer},
     shape_pipelines::create_2d_pipeline,
     shape_tesselation::TessellatedShape2D,
     vertex::{DebugInstanceVertex, Vertex},
 };
 
 pub type ThicknessKey = u32;
-pub type Index = u16;
 
 #[derive(Debug, Eq, Hash, PartialEq)]
 pub enum ShapeType {
@@ -21,10 +21,9 @@ pub enum ShapeType {
 
 pub struct DebugRenderBatch {
     pipeline: wgpu::RenderPipeline,
-    vertex_buffer: wgpu::Buffer,
-    index_buffer: wgpu::Buffer,
-    instance_buffer: wgpu::Buffer,
-    shape_meshes: HashMap<ShapeType, TessellatedShape2D>,
+    instance_buffer: GrowableBuffer,
+    // uploaded once, only the instances change between frames
+    shape_meshes: HashMap<ShapeType, GpuMesh>,
     instance_batches: HashMap<ShapeType, Vec<DebugInstanceVertex>>,
 }
 
@@ -34,49 +33,54 @@ impl DebugRenderBatch {
         camera_bind_group_layout: &wgpu::BindGroupLayout,
         format: wgpu::TextureFormat,
     ) -> Self {
-        let initial_vertex_index_capacity: u64 = 256 * 1024;
-        let initial_capacity: u64 = 1024 * 1024 * 100;
-
-        let vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Sprite Vertex Buffer"),
-            size: initial_vertex_index_capacity as u64,
-            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false, // Don't map immediately
-        });
-
-        let index_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Sprite Index Buffer"),
-            size: initial_vertex_index_capacity as u64,
-            usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false, // Don't map immediately
-        });
-
-        let instance_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Instance Buffer"),
-            size: initial_capacity as u64,
-            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false, // Don't map immediately
-        });
+        let instance_buffer = GrowableBuffer::new(
+            device,
+            "Debug Instance Buffer",
+            wgpu::BufferUsages::VERTEX,
+            64 * 1024,
+        );
 
-        let mut shape_meshes = HashMap::new();
         let world_thickness = 1;
         let thickness = 0.1;
         let segments = 200;
         let unit = 1.0;
 
-        shape_meshes.insert(ShapeType::Rectangle, TessellatedShape2D::rect(unit, unit));
-        shape_meshes.insert(
-            ShapeType::RectangleOutline(world_thickness),
-            TessellatedShape2D::rect_outline(unit, unit, thickness),
-        );
-        shape_meshes.insert(
-            ShapeType::Circle,
-            TessellatedShape2D::circle(unit, segments),
-        );
-        shape_meshes.insert(
-            ShapeType::CircleOutline(world_thickness),
-            TessellatedShape2D::circle_outline(unit, thickness, segments),
-        );
+        let meshes = [
+            (ShapeType::Rectangle, TessellatedShape2D::rect(unit, unit)),
+            (
+                ShapeType::RectangleOutline(world_thickness),
+                TessellatedShape2D::rect_outline(unit, unit, thickness),
+            ),
+            (
+                ShapeType::Circle,
+                TessellatedShape2D::circle(unit, segments),
+            ),
+            (
+                ShapeType::CircleOutline(world_thickness),
+                TessellatedShape2D::circle_outline(unit, thickness, segments),
+            ),
+        ];
+        let mut shape_meshes = HashMap::new();
+        for (shape_type, mesh) in meshes {
+            let vertices: Vec<Vertex> = mesh
+                .vertices
+                .iter()
+                .map(|v| Vertex {
+                    position: [v.x, v.y],
+                })
+                .collect();
+            let indices: Vec<u32> = mesh.indices.iter().map(|i| *i as u32).collect();
+            shape_meshes.insert(
+                shape_type,
+                GpuMesh::new(
+                    device,
+                    "Debug Shape",
+                    bytemuck::cast_slice(&vertices),
+                    vertices.len(),
+                    &indices,
+                ),
+            );
+        }
 
         let mut instance_batches = HashMap::new();
         instance_batches.insert(ShapeType::Rectangle, Vec::new());
@@ -97,8 +101,6 @@ impl DebugRenderBatch {
 
         DebugRenderBatch {
             pipeline,
-            vertex_buffer,
-            index_buffer,
             instance_buffer,
             shape_meshes,
             instance_batches,
@@ -112,37 +114,46 @@ impl DebugRenderBatch {
             .push(instance);
     }
 
+    // Returns the number of draw calls.
     pub fn flush_batch(
         &mut self,
+        device: &wgpu::Device,
         queue: &mut wgpu::Queue,
         pass: &mut wgpu::RenderPass,
         camera_bind_group: &wgpu::BindGroup,
-    ) {
+    ) -> u32 {
+        self.instance_buffer.reset();
+        let mut draw_calls = 0;
         for (shape_type, instances) in &mut self.instance_batches {
             if instances.is_empty() {
                 continue;
             }
             let mesh = self.shape_meshes.get(&shape_type).unwrap();
-            let vertices: Vec<Vertex> = mesh
-                .vertices
-                .iter()
-                .map(|v| Vertex {
-                    position: [v.x, v.y],
-                })
-                .collect();
-
-            queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&vertices));
-            queue.write_buffer(&self.index_buffer, 0, bytemuck::cast_slice(&mesh.indices));
-            queue.write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(&instances));
+            let range =
+                match self
+                    .instance_buffer
+                    .push(device, queue, bytemuck::cast_slice(&instances))
+                {
+                    Some(range) => range,
+                    None => {
+                        instances.clear();
+                        continue;
+                    }
+                };
 
             pass.set_pipeline(&self.pipeline);
             pass.set_bind_group(0, camera_bind_group, &[]);
-            pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-            pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
-            pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
-            pass.draw_indexed(0..mesh.indices.len() as u32, 0, 0..instances.len() as u32);
+            mesh.bind(pass);
+            pass.set_vertex_buffer(1, self.instance_buffer.buffer().slice(range));
+            pass.draw_indexed(0..mesh.index_count, 0, 0..instances.len() as u32);
+            draw_calls += 1;
 
             instances.clear();
         }
+        draw_calls
+    }
+
+    pub fn instance_usage(&self) -> crate::BufferUsage {
+        self.instance_buffer.usage()
     }
 }
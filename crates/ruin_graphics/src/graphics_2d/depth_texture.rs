@@ -1,7 +1,7 @@
 use wgpu::{
     AddressMode, CompareFunction, Device, Extent3d, FilterMode, Origin3d, Queue, Sampler,
-    SamplerDescriptor, SurfaceConfiguration, TexelCopyBufferLayout, TexelCopyTextureInfo,
-    TextureAspect, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages, TextureView,
+    SamplerDescriptor, TexelCopyBufferLayout, TexelCopyTextureInfo, TextureAspect,
+    TextureDescriptor, TextureDimension, TextureFormat, TextureUsages, TextureView,
     TextureViewDescriptor,
 };
 
@@ -15,10 +15,10 @@ pub struct DepthTexture {
 impl DepthTexture {
     pub const DEPTH_FORMAT: TextureFormat = TextureFormat::Depth32Float;
 
-    pub fn new(device: &Device, config: &SurfaceConfiguration, label: &str) -> Self {
+    pub fn new(device: &Device, size: [u32; 2], label: &str) -> Self {
         let size = Extent3d {
-            width: config.width.max(1),
-            height: config.height.max(1),
+            width: size[0].max(1),
+            height: size[1].max(1),
             depth_or_array_layers: 1,
         };
         let desc = TextureDescriptor {
@@ -0,0 +1,228 @@
+use std::ops::Range;
+
+use wgpu::util::DeviceExt;
+
+use crate::BufferUsage;
+
+// A buffer filled front to back over a frame. When a frame needs more than fits, it's swapped
+// for one at least twice the size and filling starts over at its front. Draws already recorded
+// keep the old buffer alive until they're submitted, so nothing needs copying.
+pub struct GrowableBuffer {
+    label: &'static str,
+    usage: wgpu::BufferUsages,
+    buffer: wgpu::Buffer,
+    offset: u64,
+    frame_bytes: u64,
+    grown: u32,
+}
+
+impl GrowableBuffer {
+    pub fn new(
+        device: &wgpu::Device,
+        label: &'static str,
+        usage: wgpu::BufferUsages,
+        capacity: u64,
+    ) -> Self {
+        let usage = usage | wgpu::BufferUsages::COPY_DST;
+        Self {
+            label,
+            usage,
+            buffer: create_buffer(device, label, usage, capacity),
+            offset: 0,
+            frame_bytes: 0,
+            grown: 0,
+        }
+    }
+
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+
+    // Writes `data` after everything else written this frame and returns where it went, or
+    // None when it's bigger than the device allows a buffer to be.
+    pub fn push(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        data: &[u8],
+    ) -> Option<Range<u64>> {
+        let size = data.len() as u64;
+        let padded = size.next_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT);
+        let mut offset = self.offset.next_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT);
+
+        if offset + padded > self.buffer.size() {
+            let limit = device.limits().max_buffer_size;
+            if padded > limit {
+                return None;
+            }
+            let capacity = (self.buffer.size() * 2)
+                .max(padded.next_power_of_two())
+                .min(limit);
+            println!(
+                "Growing {} from {} to {} bytes",
+                self.label,
+                self.buffer.size(),
+                capacity
+            );
+            self.buffer = create_buffer(device, self.label, self.usage, capacity);
+            self.grown += 1;
+            offset = 0;
+        }
+
+        if padded == size {
+            queue.write_buffer(&self.buffer, offset, data);
+        } else {
+            let mut bytes = data.to_vec();
+            bytes.resize(padded as usize, 0);
+            queue.write_buffer(&self.buffer, offset, &bytes);
+        }
+        self.offset = offset + padded;
+        self.frame_bytes += padded;
+        Some(offset..offset + size)
+    }
+
+    pub fn reset(&mut self) {
+        self.offset = 0;
+        self.frame_bytes = 0;
+    }
+
+    pub fn usage(&self) -> BufferUsage {
+        BufferUsage {
+            used: self.frame_bytes,
+            capacity: self.buffer.size(),
+            grown: self.grown,
+        }
+    }
+}
+
+fn create_buffer(
+    device: &wgpu::Device,
+    label: &str,
+    usage: wgpu::BufferUsages,
+    capacity: u64,
+) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some(label),
+        size: capacity.next_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT),
+        usage,
+        mapped_at_creation: false,
+    })
+}
+
+// Vertices and indices uploaded once. Indices are stored as u16 unless there are too many
+// vertices for them.
+pub struct GpuMesh {
+    pub vertex_buffer: wgpu::Buffer,
+    pub index_buffer: wgpu::Buffer,
+    pub index_count: u32,
+    pub index_format: wgpu::IndexFormat,
+}
+
+impl GpuMesh {
+    pub fn new(
+        device: &wgpu::Device,
+        label: &str,
+        vertices: &[u8],
+        vertex_count: usize,
+        indices: &[u32],
+    ) -> Self {
+        let (index_bytes, index_format) = if vertex_count <= u16::MAX as usize + 1 {
+            let narrow: Vec<u16> = indices.iter().map(|i| *i as u16).collect();
+            (
+                bytemuck::cast_slice(&narrow).to_vec(),
+                wgpu::IndexFormat::Uint16,
+            )
+        } else {
+            (
+                bytemuck::cast_slice(indices).to_vec(),
+                wgpu::IndexFormat::Uint32,
+            )
+        };
+
+        Self {
+            vertex_buffer: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(&format!("{} Vertex Buffer", label)),
+                contents: vertices,
+                usage: wgpu::BufferUsages::VERTEX,
+            }),
+            index_buffer: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(&format!("{} Index Buffer", label)),
+                contents: &index_bytes,
+                usage: wgpu::BufferUsages::INDEX,
+            }),
+            index_count: indices.len() as u32,
+            index_format,
+        }
+    }
+
+    pub fn bind(&self, pass: &mut wgpu::RenderPass) {
+        pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        pass.set_index_buffer(self.index_buffer.slice(..), self.index_format);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A device that only allows 256 byte buffers, None on machines without any adapter.
+    fn device() -> Option<(wgpu::Device, wgpu::Queue)> {
+        let instance = wgpu::Instance::default();
+        let adapter =
+            pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default()))
+                .ok()?;
+        let descriptor = wgpu::DeviceDescriptor {
+            required_limits: wgpu::Limits {
+                max_buffer_size: 256,
+                ..wgpu::Limits::downlevel_defaults()
+            },
+            ..Default::default()
+        };
+        pollster::block_on(adapter.request_device(&descriptor)).ok()
+    }
+
+    #[test]
+    fn grows_when_a_frame_runs_out_of_room() {
+        let Some((device, queue)) = device() else {
+            println!("Skipping buffer test, no adapter");
+            return;
+        };
+        let mut buffer =
+            GrowableBuffer::new(&device, "Test Buffer", wgpu::BufferUsages::VERTEX, 16);
+
+        assert_eq!(buffer.push(&device, &queue, &[1; 12]), Some(0..12));
+        // odd sizes are padded to the copy alignment
+        assert_eq!(buffer.push(&device, &queue, &[2; 2]), Some(12..14));
+        assert_eq!(buffer.usage().capacity, 16);
+        assert_eq!(buffer.usage().grown, 0);
+
+        // doesn't fit, the new buffer is twice the size and filled from the front
+        assert_eq!(buffer.push(&device, &queue, &[3; 8]), Some(0..8));
+        let usage = buffer.usage();
+        assert_eq!((usage.used, usage.capacity, usage.grown), (24, 32, 1));
+
+        buffer.reset();
+        assert_eq!(buffer.usage().used, 0);
+        assert_eq!(buffer.push(&device, &queue, &[4; 32]), Some(0..32));
+
+        // one push larger than double the buffer grows straight to fit it
+        assert_eq!(buffer.push(&device, &queue, &[5; 100]), Some(0..100));
+        assert_eq!(buffer.usage().capacity, 128);
+    }
+
+    #[test]
+    fn growth_stops_at_the_device_limit() {
+        let Some((device, queue)) = device() else {
+            println!("Skipping buffer test, no adapter");
+            return;
+        };
+        let mut buffer =
+            GrowableBuffer::new(&device, "Test Buffer", wgpu::BufferUsages::VERTEX, 128);
+
+        assert_eq!(buffer.push(&device, &queue, &[1; 100]), Some(0..100));
+        assert_eq!(buffer.push(&device, &queue, &[2; 200]), Some(0..200));
+        assert_eq!(buffer.usage().capacity, 256);
+        assert_eq!(buffer.push(&device, &queue, &[3; 257]), None);
+        assert_eq!(buffer.usage().grown, 1);
+    }
+}
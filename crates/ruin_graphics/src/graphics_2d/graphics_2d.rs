@@ -3,57 +3,94 @@ use std::sync::Arc;
 use std::time::Instant;
 
 use cgmath::{ElementWise, Vector2};
-use ruin_assets::{AssetCache, AssetPath, Handle, ImageTexture};
-use ruin_camera::Camera2D;
+use image::{DynamicImage, Rgba, RgbaImage};
+use ruin_assets::{
+    AssetCache, AssetPath, AssetSource, AtlasPacker, AtlasSlot, Handle, ImageTexture, TextureRegion,
+};
+use ruin_camera::{Camera2D, ScaleMode};
 use ruin_canvas::Canvas;
 use ruin_ecs::physics_2d::PhysicsWorld;
 use ruin_ecs::world::World;
+use ruin_ecs::RenderLayer;
 use wgpu::util::DeviceExt;
 use wgpu::*;
 use winit::window::Window;
 
 use crate::graphics_2d::debug_render_batch::ShapeType;
-use crate::graphics_2d::shape_pipelines::create_2d_pipeline;
+use crate::graphics_2d::gpu_buffer::GrowableBuffer;
+use crate::graphics_2d::light_renderer::{normal_target, LightRenderer};
+use crate::graphics_2d::particle_renderer::ParticleRenderer;
+use crate::graphics_2d::post_effects::PostEffects;
+use crate::graphics_2d::post_processor::PostProcessor;
+use crate::graphics_2d::render_target::{Readback, RenderTarget};
+use crate::graphics_2d::shape_pipelines::{create_2d_pipeline, create_2d_pipeline_for_target};
 use crate::graphics_2d::space::Space;
-use crate::graphics_2d::vertex::{DebugInstanceVertex, Vertex};
+use crate::graphics_2d::tilemap_renderer::TilemapRenderer;
+use crate::graphics_2d::vertex::{DebugInstanceVertex, SpriteInstance, Vertex};
+use crate::graphics_2d::virtual_screen::VirtualScreen;
 use crate::graphics_2d::world_render_batch::WorldRenderBatch;
 use crate::graphics_2d::DebugRenderBatch;
 use crate::graphics_2d::DepthTexture;
 use crate::graphics_2d::{CameraUniform2D, ColorVertex, TextureVertex};
-use crate::Graphics;
+use crate::{Graphics, RenderStats};
 
 pub type TextureId = u32;
 
+const ATLAS_PAGE_SIZE: u32 = 2048;
+const ATLAS_PADDING: u32 = 2; // keeps neighbouring sheets from bleeding into each other
+
 pub struct Graphics2D {
-    surface: Surface<'static>,
+    target: RenderTarget,
     device: Device,
     queue: Queue,
-    config: SurfaceConfiguration,
-    is_surface_configured: bool,
+    pending_screenshots: Vec<String>,
     background_color: Color,
     pub camera: Camera2D,
-    texture_assets: AssetCache<ImageTexture>,
+    source: Arc<dyn AssetSource>,
+    // GPU textures: atlas pages, sheets too large for one and the built-in ones
+    textures: AssetCache<ImageTexture>,
+    texture_atlas: AtlasPacker,
+    atlas_pages: Vec<Option<Handle<ImageTexture>>>,
+    texture_regions: HashMap<AssetPath, (TextureRegion, TextureId)>,
+    // how many owners (e.g. the loaded scene) hold each texture id, unused ones are freed
+    texture_users: HashMap<AssetPath, u32>,
+    placeholder_texture: Handle<ImageTexture>,
+    missing_texture: Handle<ImageTexture>,
     camera_buffer: Buffer,
     camera_bind_group: BindGroup,
     static_camera_buffer: Buffer,
     static_camera_bind_group: BindGroup,
-    vertex_buffer: Buffer,
-    index_buffer: Buffer,
-    instance_buffer: Buffer,
+    instance_buffer: GrowableBuffer,
     texture_bind_group_layout: BindGroupLayout,
     render_pipeline: RenderPipeline,
+    sprite_pipeline: RenderPipeline,
     canvas_pipeline: RenderPipeline,
     depth_texture: DepthTexture,
+    virtual_screen: VirtualScreen,
     texture_batch_context: WorldRenderBatch,
+    tilemap_renderer: TilemapRenderer,
+    particle_renderer: ParticleRenderer,
+    light_renderer: LightRenderer,
+    // normal maps, keyed by the texture (usually an atlas page) they line up with
+    normal_maps: HashMap<Handle<ImageTexture>, ImageTexture>,
+    post_effects: PostEffects,
+    post_processor: PostProcessor,
     color_shapes_pipeline: RenderPipeline,
     test_pipe: RenderPipeline,
     debug_render_batch: DebugRenderBatch,
     texture_lookup: HashMap<TextureId, String>,
     next_texture_id: TextureId,
+    stats: RenderStats,
 }
 
 impl Graphics2D {
-    pub async fn new(window: Arc<Window>, camera: Camera2D) -> anyhow::Result<Self> {
+    pub async fn new(
+        window: Arc<Window>,
+        camera: Camera2D,
+        virtual_resolution: [u32; 2],
+        scale_mode: ScaleMode,
+        source: Arc<dyn AssetSource>,
+    ) -> anyhow::Result<Self> {
         let size = window.inner_size();
         let instance = wgpu::Instance::default();
         let surface = instance.create_surface(window.clone())?;
@@ -71,7 +108,9 @@ impl Graphics2D {
         let surface_format = surface_caps.formats[0];
 
         let config = SurfaceConfiguration {
-            usage: TextureUsages::RENDER_ATTACHMENT,
+            // copying out of the surface is what makes screenshots possible
+            usage: TextureUsages::RENDER_ATTACHMENT
+                | (surface_caps.usages & TextureUsages::COPY_SRC),
             format: surface_format,
             width: size.width,
             height: size.height,
@@ -81,6 +120,64 @@ impl Graphics2D {
             desired_maximum_frame_latency: 2,
         };
 
+        let target = RenderTarget::Surface {
+            surface,
+            config,
+            configured: false,
+        };
+        Self::with_target(
+            device,
+            queue,
+            target,
+            camera,
+            virtual_resolution,
+            scale_mode,
+            source,
+        )
+    }
+
+    // Draws into an offscreen texture instead of a window, on whatever adapter is around,
+    // software ones like llvmpipe included. Frames are read with render_to_image.
+    pub async fn new_headless(
+        size: [u32; 2],
+        camera: Camera2D,
+        virtual_resolution: [u32; 2],
+        scale_mode: ScaleMode,
+        source: Arc<dyn AssetSource>,
+    ) -> anyhow::Result<Self> {
+        let instance = wgpu::Instance::default();
+        let adapter = instance
+            .request_adapter(&RequestAdapterOptions {
+                power_preference: PowerPreference::HighPerformance,
+                compatible_surface: None,
+                force_fallback_adapter: false,
+            })
+            .await?;
+        let (device, queue) = adapter.request_device(&DeviceDescriptor::default()).await?;
+
+        let target = RenderTarget::offscreen(&device, size, TextureFormat::Rgba8UnormSrgb);
+        Self::with_target(
+            device,
+            queue,
+            target,
+            camera,
+            virtual_resolution,
+            scale_mode,
+            source,
+        )
+    }
+
+    fn with_target(
+        mut device: Device,
+        queue: Queue,
+        target: RenderTarget,
+        mut camera: Camera2D,
+        virtual_resolution: [u32; 2],
+        scale_mode: ScaleMode,
+        source: Arc<dyn AssetSource>,
+    ) -> anyhow::Result<Self> {
+        let format = target.format();
+
         let texture_bind_group_layout =
             device.create_bind_group_layout(&BindGroupLayoutDescriptor {
                 entries: &[
@@ -121,6 +218,8 @@ impl Graphics2D {
 
         let shader =
             device.create_shader_module(include_wgsl!("shaders/2d_camera_and_sprite.wgsl"));
+        let sprite_shader = device
+            .create_shader_module(include_wgsl!("shaders/2d_camera_and_sprite_instanced.wgsl"));
         let canvas_shader =
             device.create_shader_module(include_wgsl!("shaders/2d_canvas_sprite.wgsl"));
         let debug_shader =
@@ -128,15 +227,39 @@ impl Graphics2D {
         let debug_shader_instanced = device
             .create_shader_module(include_wgsl!("shaders/2d_camera_and_color_instanced.wgsl"));
 
-        let depth_texture = DepthTexture::new(&device, &config, "2d_depth_texture");
-
-        let render_pipeline = create_2d_pipeline(
+        // the world is drawn at the virtual resolution and scaled up afterwards
+        let virtual_screen = VirtualScreen::new(
+            &device,
+            virtual_resolution,
+            scale_mode,
+            format,
+            target.size(),
+            &texture_bind_group_layout,
+        );
+        camera.update_aspect_ratio(virtual_screen.size[0], virtual_screen.size[1]);
+        let depth_texture = DepthTexture::new(&device, virtual_screen.size, "2d_depth_texture");
+
+        // world pipelines write normals for lighting next to the colour
+        let world_targets = [
+            Some(ColorTargetState {
+                format,
+                blend: Some(BlendState::ALPHA_BLENDING),
+                write_mask: ColorWrites::ALL,
+            }),
+            Some(normal_target()),
+        ];
+        let world_layouts = Vec::from([
+            &texture_bind_group_layout,
+            &camera_bind_group_layout,
+            &texture_bind_group_layout, // normal map
+        ]);
+
+        let render_pipeline = create_2d_pipeline_for_target(
             "Texture Pipeline",
             &device,
-            config.format,
             &shader,
             &[TextureVertex::desc()],
-            &Vec::from([&texture_bind_group_layout, &camera_bind_group_layout]),
+            &world_layouts,
             Some(wgpu::DepthStencilState {
                 format: DepthTexture::DEPTH_FORMAT,
                 depth_write_enabled: true,
@@ -144,14 +267,32 @@ impl Graphics2D {
                 stencil: wgpu::StencilState::default(),
                 bias: wgpu::DepthBiasState::default(),
             }),
+            &world_targets,
+        );
+
+        // tilemap chunks keep their own vertices, sprites are instanced quads
+        let sprite_pipeline = create_2d_pipeline_for_target(
+            "Sprite Pipeline",
+            &device,
+            &sprite_shader,
+            &[Vertex::desc(), SpriteInstance::desc()],
+            &world_layouts,
+            Some(wgpu::DepthStencilState {
+                format: DepthTexture::DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::LessEqual,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            &world_targets,
         );
 
         let canvas_pipeline = create_2d_pipeline(
             "Canvas Pipeline",
             &device,
-            config.format,
+            format,
             &canvas_shader,
-            &[TextureVertex::desc()],
+            &[Vertex::desc(), SpriteInstance::desc()],
             &Vec::from([&texture_bind_group_layout]),
             None,
         );
@@ -159,7 +300,7 @@ impl Graphics2D {
         let color_shapes_pipeline = create_2d_pipeline(
             "Debug Pipeline",
             &device,
-            config.format,
+            format,
             &debug_shader_instanced,
             &[Vertex::desc(), DebugInstanceVertex::desc()],
             &Vec::from([&camera_bind_group_layout]),
@@ -169,7 +310,7 @@ impl Graphics2D {
         let test_pipe = create_2d_pipeline(
             "Debug Pipeline",
             &device,
-            config.format,
+            format,
             &debug_shader,
             &[ColorVertex::desc()],
             &Vec::from([&camera_bind_group_layout]),
@@ -208,41 +349,95 @@ impl Graphics2D {
             label: Some("Static 2D Camera Bind Group"),
         });
 
-        // Choose a generous initial capacity. You might need to resize if you hit limits.
-        let initial_vertex_capacity: u64 = 1024 * 1024; // e.g., 1MB for vertices
-        let initial_index_capacity: u64 = 1024 * 1024; // e.g., 256KB for indices
-
-        let vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Sprite Vertex Buffer"),
-            size: initial_vertex_capacity as u64,
-            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false, // Don't map immediately
-        });
-
-        let index_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Sprite Index Buffer"),
-            size: initial_index_capacity as u64,
-            usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false, // Don't map immediately
-        });
-
-        let instance_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Instance Buffer"),
-            size: initial_vertex_capacity as u64,
-            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false, // Don't map immediately
-        });
+        // grows when a frame needs more, ~20k sprites to start with
+        let instance_buffer = GrowableBuffer::new(
+            &device,
+            "Sprite Instance Buffer",
+            BufferUsages::VERTEX,
+            1024 * 1024,
+        );
 
-        let debug_render_batch =
-            DebugRenderBatch::new(&device, &camera_bind_group_layout, config.format);
+        let debug_render_batch = DebugRenderBatch::new(&device, &camera_bind_group_layout, format);
+
+        let atlas_page_size = ATLAS_PAGE_SIZE.min(device.limits().max_texture_dimension_2d);
+
+        let mut texture_batch_context = WorldRenderBatch::new(&device);
+        let mut textures = AssetCache::new();
+        // Stands in for textures that are still decoding. It's fully transparent so sprites
+        // using it are discarded.
+        let placeholder = ImageTexture::empty(&device, [1, 1], Some("Placeholder Texture"));
+        let placeholder_texture = add_builtin_texture(
+            &mut textures,
+            &mut texture_batch_context,
+            &mut device,
+            &texture_bind_group_layout,
+            placeholder,
+        );
+        let missing = ImageTexture::missing(&device, &queue);
+        let missing_texture = add_builtin_texture(
+            &mut textures,
+            &mut texture_batch_context,
+            &mut device,
+            &texture_bind_group_layout,
+            missing,
+        );
+        // tinted by particles that don't use a sprite
+        let blank = ImageTexture::from_image(
+            &device,
+            &queue,
+            &image::DynamicImage::ImageRgba8(RgbaImage::from_pixel(1, 1, Rgba([255; 4]))),
+            Some("Blank Texture"),
+        )?;
+        let blank_texture = add_builtin_texture(
+            &mut textures,
+            &mut texture_batch_context,
+            &mut device,
+            &texture_bind_group_layout,
+            blank,
+        );
+        // for sheets without a normal map, alpha 0 leaves them out of normal shading
+        let flat_normals = ImageTexture::empty_linear(&device, [1, 1], Some("Flat Normal Map"));
+        flat_normals.write_image(
+            &queue,
+            &image::DynamicImage::ImageRgba8(RgbaImage::from_pixel(1, 1, Rgba([128, 128, 255, 0]))),
+            [0, 0],
+        );
+        let flat_normals = add_builtin_texture(
+            &mut textures,
+            &mut texture_batch_context,
+            &mut device,
+            &texture_bind_group_layout,
+            flat_normals,
+        );
+        texture_batch_context.set_flat_normal_map(flat_normals);
+        let light_renderer = LightRenderer::new(
+            &device,
+            format,
+            virtual_screen.size,
+            &camera_bind_group_layout,
+        );
+        let post_processor = PostProcessor::new(&device, format);
+        let particle_renderer = ParticleRenderer::new(
+            &device,
+            format,
+            &texture_bind_group_layout,
+            &camera_bind_group_layout,
+            blank_texture,
+        );
 
         Ok(Self {
-            surface,
+            target,
             device,
             queue,
-            config,
-            texture_assets: AssetCache::<ImageTexture>::new(),
-            is_surface_configured: false,
+            texture_atlas: AtlasPacker::new(atlas_page_size, ATLAS_PADDING),
+            atlas_pages: Vec::new(),
+            texture_regions: HashMap::new(),
+            texture_users: HashMap::new(),
+            source,
+            textures,
+            placeholder_texture,
+            missing_texture,
+            pending_screenshots: Vec::new(),
             background_color: wgpu::Color {
                 r: 116.0 / 255.0,
                 b: 63.0 / 255.0,
@@ -254,28 +449,151 @@ impl Graphics2D {
             camera_bind_group,
             static_camera_buffer,
             static_camera_bind_group,
-            vertex_buffer,
-            index_buffer,
             instance_buffer,
             texture_bind_group_layout,
             depth_texture,
+            virtual_screen,
             render_pipeline,
+            sprite_pipeline,
             canvas_pipeline,
             test_pipe,
-            texture_batch_context: WorldRenderBatch::new(),
+            texture_batch_context,
+            tilemap_renderer: TilemapRenderer::new(),
+            particle_renderer,
+            light_renderer,
+            normal_maps: HashMap::new(),
+            post_effects: PostEffects::default(),
+            post_processor,
             texture_lookup: HashMap::new(),
             color_shapes_pipeline,
             debug_render_batch,
             next_texture_id: 0,
+            stats: RenderStats::default(),
         })
     }
 
     pub fn resize(&mut self, width: u32, height: u32) {
-        self.config.width = width;
-        self.config.height = height;
-        self.surface.configure(&self.device, &self.config);
-        self.depth_texture = DepthTexture::new(&self.device, &self.config, "depth_texture");
-        self.camera.update_aspect_ratio(width, height);
+        self.target.resize(&self.device, width, height);
+        self.virtual_screen.resize([width, height]);
+    }
+
+    // Window pixels to world units, going through the bars and scaling around the screen.
+    pub fn screen_to_world(&self, position: [f32; 2]) -> [f32; 2] {
+        let [x, y] = self.virtual_screen.window_to_virtual(position);
+        let [width, height] = self.virtual_screen.size;
+        let pixel = self.camera.pixel_size();
+        let camera = self.camera.snapped_position();
+        [
+            camera.x + (x - width as f32 * 0.5) * pixel,
+            camera.y - (y - height as f32 * 0.5) * pixel, // screen y points down
+        ]
+    }
+
+    fn atlas_page(&mut self, index: usize) -> Handle<ImageTexture> {
+        if self.atlas_pages.len() <= index {
+            self.atlas_pages.resize(index + 1, None);
+        }
+        if let Some(handle) = self.atlas_pages[index] {
+            return handle;
+        }
+
+        let size = self.texture_atlas.page_size();
+        let page = ImageTexture::empty(
+            &self.device,
+            [size, size],
+            Some(&format!("Atlas Page: {}", index)),
+        );
+        let handle = self.textures.insert(page, None);
+        self.add_texture_bind_group(handle);
+        self.atlas_pages[index] = Some(handle);
+        handle
+    }
+
+    fn add_texture_bind_group(&mut self, handle: Handle<ImageTexture>) {
+        let img_texture = self.textures.get(handle).unwrap();
+        self.texture_batch_context.add_texture(
+            handle,
+            img_texture,
+            &mut self.device,
+            &self.texture_bind_group_layout,
+        );
+    }
+
+    fn missing_texture_region(&self) -> TextureRegion {
+        let texture = self.textures.get(self.missing_texture).unwrap();
+        TextureRegion::full(self.missing_texture, texture.size())
+    }
+
+    // Packs the image into an atlas page, or gives it a texture of its own when too large.
+    fn pack_texture(&mut self, id: &str, image: &DynamicImage) -> anyhow::Result<TextureRegion> {
+        let region = match self.texture_atlas.pack(image.width(), image.height()) {
+            Some(slot) => {
+                let page = self.atlas_page(slot.page);
+                self.textures
+                    .get(page)
+                    .unwrap()
+                    .write_image(&self.queue, image, [slot.x, slot.y]);
+                TextureRegion {
+                    texture: page,
+                    rect: [slot.x, slot.y, image.width(), image.height()],
+                    texture_size: [self.texture_atlas.page_size(); 2],
+                }
+            }
+            // too large for a page, give it a texture of its own
+            None => {
+                let img_texture = ImageTexture::from_image(
+                    &self.device,
+                    &self.queue,
+                    image,
+                    Some(&format!("Image Texture: {:?}", id).to_string()),
+                )?;
+                let handle = self.textures.insert(img_texture, None);
+                self.add_texture_bind_group(handle);
+                TextureRegion::full(handle, [image.width(), image.height()])
+            }
+        };
+        Ok(region)
+    }
+
+    fn record_texture(&mut self, id: &str, region: TextureRegion) {
+        let texture_id = self.next_texture_id;
+        self.next_texture_id += 1;
+        self.texture_lookup.insert(texture_id, id.to_string());
+        self.texture_regions
+            .insert(AssetPath::new(id), (region, texture_id));
+    }
+
+    // Gives a region's space back, dropping its texture once nothing else is on it.
+    fn free_region(&mut self, region: TextureRegion) {
+        if region.texture == self.placeholder_texture || region.texture == self.missing_texture {
+            return;
+        }
+        let page = self
+            .atlas_pages
+            .iter()
+            .position(|page| *page == Some(region.texture));
+        let Some(page) = page else {
+            self.remove_texture(region.texture);
+            return;
+        };
+
+        let [x, y, width, height] = region.rect;
+        if self
+            .texture_atlas
+            .free(AtlasSlot { page, x, y }, width, height)
+        {
+            self.atlas_pages[page] = None;
+            self.remove_texture(region.texture);
+        } else if let Some(normals) = self.normal_maps.get(&region.texture) {
+            // the next sheet packed here may not have normals of its own
+            normals.write_image(&self.queue, &DynamicImage::new_rgba8(width, height), [x, y]);
+        }
+    }
+
+    fn remove_texture(&mut self, handle: Handle<ImageTexture>) {
+        self.textures.remove(handle);
+        self.texture_batch_context.remove_texture(&handle);
+        self.normal_maps.remove(&handle);
     }
 
     pub fn update_camera(&mut self, dt: f32, target: [f32; 2], velocity: [f32; 2]) {
@@ -303,46 +621,123 @@ impl Graphics2D {
         canvas: &Canvas,
         physics: &PhysicsWorld,
     ) -> Result<(), SurfaceError> {
-        if !self.is_surface_configured {
-            self.surface.configure(&self.device, &self.config);
-            self.is_surface_configured = true;
+        let capture = !self.pending_screenshots.is_empty();
+        if capture && !self.target.can_capture() {
+            println!("Screenshots aren't supported by this window surface");
+            self.pending_screenshots.clear();
+        }
+
+        let readback =
+            self.draw_frame(world, canvas, physics, self.target.can_capture() && capture)?;
+        if let Some(readback) = readback {
+            let image = readback.read(&self.device);
+            for path in self.pending_screenshots.drain(..) {
+                let saved = match &image {
+                    Ok(image) => save_png(image, &path),
+                    Err(err) => Err(anyhow::anyhow!("{}", err)),
+                };
+                match saved {
+                    Ok(()) => println!("Saved screenshot {}", path),
+                    Err(err) => println!("Failed to save screenshot {}: {}", path, err),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Renders a frame and reads it back, e.g. to compare against a known good image.
+    pub fn render_to_image(
+        &mut self,
+        world: &World,
+        canvas: &Canvas,
+        physics: &PhysicsWorld,
+    ) -> anyhow::Result<RgbaImage> {
+        if !self.target.can_capture() {
+            return Err(anyhow::anyhow!("render target can't be read back"));
+        }
+        match self.draw_frame(world, canvas, physics, true)? {
+            Some(readback) => readback.read(&self.device),
+            None => Err(anyhow::anyhow!("no frame to read back")),
         }
+    }
 
-        let output = self.surface.get_current_texture()?;
-        let view = output
-            .texture
-            .create_view(&TextureViewDescriptor::default());
+    fn draw_frame(
+        &mut self,
+        world: &World,
+        canvas: &Canvas,
+        physics: &PhysicsWorld,
+        capture: bool,
+    ) -> Result<Option<Readback>, SurfaceError> {
+        let frame = self.target.acquire(&self.device)?;
+        let view = &frame.view;
         let mut encoder = self
             .device
             .create_command_encoder(&CommandEncoderDescriptor {
                 label: Some("2D Render Encoder"),
             });
 
-        self.draw_game(world, &mut encoder, &view);
-        self.draw_canvas(canvas, &mut encoder, &view);
-        self.draw_debug_batch(world, physics, &mut encoder, &view);
+        self.stats = RenderStats::default();
+        self.instance_buffer.reset();
+        self.draw_game(world, &mut encoder);
+        if world.lighting.enabled {
+            self.draw_lights(world, physics, &mut encoder);
+        }
+        self.draw_post_effects(&mut encoder, view);
+        self.draw_canvas(canvas, &mut encoder, view);
+        self.draw_debug_batch(world, physics, &mut encoder, view);
+        let readback =
+            capture.then(|| Readback::encode(&self.device, &mut encoder, &frame.texture));
         self.queue.submit(Some(encoder.finish()));
+
+        self.stats.draw_calls += self.texture_batch_context.draw_calls
+            + self.stats.tilemap_chunks
+            + self.particle_renderer.draw_calls;
+        self.stats.sprites = self.texture_batch_context.sprites;
+        self.stats.particles = self.particle_renderer.particles;
+        self.stats.buffers = vec![
+            ("sprite_instances", self.instance_buffer.usage()),
+            ("debug_instances", self.debug_render_batch.instance_usage()),
+        ];
         self.texture_batch_context.reset_context();
-        output.present();
-        Ok(())
+        self.particle_renderer.reset_stats();
+        frame.present();
+        Ok(readback)
     }
 
-    fn draw_game(
-        &mut self,
-        world: &World,
-        encoder: &mut wgpu::CommandEncoder,
-        view: &wgpu::TextureView,
-    ) {
+    // Saved after the next frame is drawn.
+    pub fn screenshot(&mut self, path: &str) {
+        self.pending_screenshots.push(path.to_string());
+    }
+
+    fn draw_game(&mut self, world: &World, encoder: &mut wgpu::CommandEncoder) {
+        self.tilemap_renderer.prepare(world, &self.device);
+
         let mut pass = encoder.begin_render_pass(&RenderPassDescriptor {
             label: Some("2D Render Pass"),
-            color_attachments: &[Some(RenderPassColorAttachment {
-                view: view,
-                resolve_target: None,
-                ops: Operations {
-                    load: LoadOp::Clear(self.background_color),
-                    store: StoreOp::Store,
-                },
-            })],
+            color_attachments: &[
+                Some(RenderPassColorAttachment {
+                    view: self.virtual_screen.view(),
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Clear(self.background_color),
+                        store: StoreOp::Store,
+                    },
+                }),
+                Some(RenderPassColorAttachment {
+                    view: self.light_renderer.normal_view(),
+                    resolve_target: None,
+                    ops: Operations {
+                        // facing the camera, with no normal map
+                        load: LoadOp::Clear(Color {
+                            r: 0.5,
+                            g: 0.5,
+                            b: 1.0,
+                            a: 0.0,
+                        }),
+                        store: StoreOp::Store,
+                    },
+                }),
+            ],
             depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
                 view: &self.depth_texture.view,
                 depth_ops: Some(wgpu::Operations {
@@ -355,60 +750,113 @@ impl Graphics2D {
             timestamp_writes: None,
         });
 
-        pass.set_pipeline(&self.render_pipeline);
-        pass.set_bind_group(1, &self.camera_bind_group, &[]);
+        let [half_w, half_h] = self.camera.half_extents();
+        let center = self.camera.snapped_position();
+        let view = [
+            [center.x - half_w, center.y - half_h],
+            [center.x + half_w, center.y + half_h],
+        ];
 
         let render_queue = world.extract_render_queue_2d();
-        let mut opaque = render_queue.clone().opaque.clone();
-        opaque.sort_by(|a, b| {
-            b.z_order
-                .partial_cmp(&a.z_order)
-                .unwrap_or(std::cmp::Ordering::Equal)
+        let mut elements = render_queue.opaque;
+        elements.extend(render_queue.transparent);
+        elements.sort_by(|a, b| {
+            a.layer
+                .cmp(&b.layer)
+                .then(
+                    a.z_order
+                        .partial_cmp(&b.z_order)
+                        .unwrap_or(std::cmp::Ordering::Equal),
+                )
+                .then(a.order.cmp(&b.order))
         });
 
-        for element in opaque.iter() {
-            {
+        let particles = world.extract_particles_2d();
+        let mut particles = particles.iter().peekable();
+
+        // each layer's tilemaps go under its sprites, its particles over them
+        let mut elements = elements.iter().peekable();
+        self.texture_batch_context.normal_group = Some(2);
+        for layer in RenderLayer::ALL {
+            pass.set_pipeline(&self.render_pipeline);
+            pass.set_bind_group(1, &self.camera_bind_group, &[]);
+            self.stats.tilemap_chunks +=
+                self.tilemap_renderer
+                    .draw(&mut pass, &self.texture_batch_context, view, layer);
+
+            pass.set_pipeline(&self.sprite_pipeline);
+            pass.set_bind_group(1, &self.camera_bind_group, &[]);
+            while let Some(element) = elements.next_if(|element| element.layer == layer) {
                 self.texture_batch_context.enqueue_next_texture(
                     element,
+                    &self.device,
                     &mut self.queue,
                     &mut pass,
-                    &mut self.vertex_buffer,
-                    &mut self.index_buffer,
+                    &mut self.instance_buffer,
                 );
             }
+            self.texture_batch_context.flush_batch(
+                &self.device,
+                &mut self.queue,
+                &mut pass,
+                &mut self.instance_buffer,
+            );
+
+            self.particle_renderer.draw(
+                std::iter::from_fn(|| particles.next_if(|batch| batch.layer == layer)),
+                &self.texture_batch_context,
+                &self.device,
+                &self.queue,
+                &mut pass,
+                &mut self.instance_buffer,
+            );
         }
-        // flush opaque
-        self.texture_batch_context.flush_batch(
-            &mut self.queue,
-            &mut pass,
-            &mut self.vertex_buffer,
-            &mut self.index_buffer,
+        self.texture_batch_context.normal_group = None;
+    }
+
+    // Lights the virtual screen, before it's scaled up.
+    fn draw_lights(
+        &mut self,
+        world: &World,
+        physics: &PhysicsWorld,
+        encoder: &mut wgpu::CommandEncoder,
+    ) {
+        let lights = world.extract_lights_2d();
+        let occluders = physics.occluder_outlines();
+        self.light_renderer.prepare(
+            &lights,
+            &occluders,
+            world.lighting.ambient,
+            &self.device,
+            &self.queue,
+            &mut self.instance_buffer,
+        );
+        self.light_renderer.render(
+            encoder,
+            &self.camera_bind_group,
+            self.virtual_screen.view(),
+            &self.instance_buffer,
         );
+        self.stats.lights = self.light_renderer.lights;
+        self.stats.draw_calls += self.light_renderer.draw_calls;
+    }
 
-        let mut transparent = render_queue.transparent.clone();
-        transparent.sort_by(|a, b| {
-            a.z_order
-                .partial_cmp(&b.z_order)
-                .unwrap_or(std::cmp::Ordering::Equal)
-        });
-        for element in transparent.iter() {
-            {
-                self.texture_batch_context.enqueue_next_texture(
-                    element,
-                    &mut self.queue,
-                    &mut pass,
-                    &mut self.vertex_buffer,
-                    &mut self.index_buffer,
-                );
-            }
-        }
-        // do final flush
-        self.texture_batch_context.flush_batch(
-            &mut self.queue,
-            &mut pass,
-            &mut self.vertex_buffer,
-            &mut self.index_buffer,
+    // Scales the virtual screen up into `view`, through the post effects when any are on.
+    fn draw_post_effects(&mut self, encoder: &mut wgpu::CommandEncoder, view: &TextureView) {
+        self.post_processor.prepare(
+            &self.post_effects,
+            self.virtual_screen.viewport_size(),
+            self.source.as_ref(),
+            &self.device,
+            &self.queue,
         );
+        if !self.post_processor.is_active() {
+            self.virtual_screen.present(encoder, view);
+            return;
+        }
+        self.post_processor
+            .render(&self.virtual_screen, &self.device, encoder, view);
+        self.stats.draw_calls += self.post_processor.draw_calls;
     }
 
     fn draw_canvas(
@@ -432,6 +880,7 @@ impl Graphics2D {
             timestamp_writes: None,
         });
 
+        self.virtual_screen.set_viewport(&mut pass);
         pass.set_pipeline(&self.canvas_pipeline);
 
         let render_queue = canvas.extract_render_queue_2d();
@@ -446,19 +895,19 @@ impl Graphics2D {
             {
                 self.texture_batch_context.enqueue_next_texture(
                     element,
+                    &self.device,
                     &mut self.queue,
                     &mut pass,
-                    &mut self.vertex_buffer,
-                    &mut self.index_buffer,
+                    &mut self.instance_buffer,
                 );
             }
         }
 
         self.texture_batch_context.flush_batch(
+            &self.device,
             &mut self.queue,
             &mut pass,
-            &mut self.vertex_buffer,
-            &mut self.index_buffer,
+            &mut self.instance_buffer,
         );
     }
 
@@ -470,6 +919,7 @@ impl Graphics2D {
         for (entity, animation) in world.animations.iter() {
             if let Some(t) = world.transforms_2d.get(&entity) {
                 let current_frame = &animation.current_frame;
+                let mirror = world.flips.get(entity).copied().unwrap_or_default().signs();
 
                 // hitboxes
                 if world.debug.show_hitboxes {
@@ -477,8 +927,8 @@ impl Graphics2D {
                         if area.active {
                             let pixels_per_unit = Vector2::from(current_frame.frame_pixel_dims);
                             let world_offset = Vector2::new(
-                                (area.offset.x) * t.scale.x / pixels_per_unit.x,
-                                (area.offset.y) * t.scale.y / pixels_per_unit.y,
+                                area.offset.x * t.scale.x * mirror.x / pixels_per_unit.x,
+                                area.offset.y * t.scale.y * mirror.y / pixels_per_unit.y,
                             );
                             let world_half_extents = Vector2::new(
                                 area.shape.half_extents()[0] * t.scale.x.abs() / pixels_per_unit.x,
@@ -502,8 +952,8 @@ impl Graphics2D {
                         if area.active {
                             let pixels_per_unit = Vector2::from(current_frame.frame_pixel_dims);
                             let world_offset = Vector2::new(
-                                (area.offset.x) * t.scale.x / pixels_per_unit.x,
-                                (area.offset.y) * t.scale.y / pixels_per_unit.y,
+                                area.offset.x * t.scale.x * mirror.x / pixels_per_unit.x,
+                                area.offset.y * t.scale.y * mirror.y / pixels_per_unit.y,
                             );
                             let world_half_extents = Vector2::new(
                                 area.shape.half_extents()[0] * t.scale.x.abs() / pixels_per_unit.x,
@@ -589,7 +1039,10 @@ impl Graphics2D {
                 depth_stencil_attachment: None,
             });
 
-            self.debug_render_batch.flush_batch(
+            // over the upscaled world, at the window's resolution so thin outlines survive
+            self.virtual_screen.set_viewport(&mut render_pass);
+            self.stats.draw_calls += self.debug_render_batch.flush_batch(
+                &self.device,
                 &mut self.queue,
                 &mut render_pass,
                 &self.camera_bind_group,
@@ -669,6 +1122,18 @@ impl Graphics for Graphics2D {
         // Handled externally for 2D for now
     }
 
+    fn screenshot(&mut self, path: &str) {
+        self.screenshot(path);
+    }
+
+    fn screen_to_world(&self, position: [f32; 2]) -> [f32; 2] {
+        self.screen_to_world(position)
+    }
+
+    fn render_stats(&self) -> RenderStats {
+        self.stats.clone()
+    }
+
     fn get_camera_info(&self) -> crate::CameraInfo {
         return crate::CameraInfo {
             zoom: self.camera.zoom.clone(),
@@ -684,33 +1149,171 @@ impl Graphics for Graphics2D {
         let _ = self.render(world, canvas, physics);
     }
 
-    fn load_texture_from_path(&mut self, id: &str, path: &str) -> Handle<ImageTexture> {
-        let asset_path = AssetPath::new(path);
+    fn texture_region(&self, id: &str) -> Option<TextureRegion> {
+        self.texture_regions
+            .get(&AssetPath::new(id))
+            .map(|(region, _)| *region)
+    }
+
+    fn placeholder_region(&self) -> TextureRegion {
+        TextureRegion::full(self.placeholder_texture, [1, 1])
+    }
 
-        if let Some(handle) = self.texture_assets.get_handle_for_path(&asset_path) {
-            return handle;
+    fn upload_texture(&mut self, id: &str, image: &DynamicImage) -> anyhow::Result<TextureRegion> {
+        if let Some(region) = self.texture_region(id) {
+            return Ok(region);
         }
+        let region = self.pack_texture(id, image)?;
+        self.record_texture(id, region);
+        Ok(region)
+    }
 
-        let image = image::open(path).unwrap().flipv();
-        let img_texture = ImageTexture::from_image(
-            &self.device,
+    fn set_missing_texture(&mut self, id: &str) -> TextureRegion {
+        let region = self.missing_texture_region();
+        self.record_texture(id, region);
+        region
+    }
+
+    fn reload_texture(&mut self, id: &str, image: &DynamicImage) -> Option<TextureRegion> {
+        let asset_path = AssetPath::new(id);
+        let (region, texture_id) = *self.texture_regions.get(&asset_path)?;
+        let size = [image.width(), image.height()];
+
+        let region = if region.texture == self.missing_texture {
+            match self.pack_texture(id, image) {
+                Ok(region) => region,
+                Err(err) => {
+                    println!("Failed to reload {}: {}", id, err);
+                    return None;
+                }
+            }
+        } else if size == region.size() {
+            self.textures.get(region.texture)?.write_image(
+                &self.queue,
+                image,
+                [region.rect[0], region.rect[1]],
+            );
+            region
+        } else if self.atlas_pages.contains(&Some(region.texture)) {
+            let new_region = match self.pack_texture(id, image) {
+                Ok(new_region) => new_region,
+                Err(err) => {
+                    println!("Failed to reload {}: {}", id, err);
+                    return None;
+                }
+            };
+            self.free_region(region);
+            new_region
+        } else {
+            let img_texture = match ImageTexture::from_image(
+                &self.device,
+                &self.queue,
+                image,
+                Some(&format!("Image Texture: {:?}", id).to_string()),
+            ) {
+                Ok(img_texture) => img_texture,
+                Err(err) => {
+                    println!("Failed to reload {}: {}", id, err);
+                    return None;
+                }
+            };
+            self.textures.replace(region.texture, img_texture);
+            self.add_texture_bind_group(region.texture);
+            // it may not fit anymore, it's set again once the engine sees the new region
+            self.normal_maps.remove(&region.texture);
+            self.texture_batch_context
+                .remove_normal_map(&region.texture);
+            TextureRegion::full(region.texture, size)
+        };
+
+        self.texture_regions
+            .insert(asset_path, (region, texture_id));
+        Some(region)
+    }
+
+    fn reload_lut(&mut self, path: &str) {
+        self.post_processor.reload_lut(path);
+    }
+
+    fn set_normal_map(
+        &mut self,
+        region: TextureRegion,
+        image: &DynamicImage,
+    ) -> anyhow::Result<()> {
+        // nothing to shade yet, the engine sets it again once the sheet is in
+        if region.texture == self.placeholder_texture || region.texture == self.missing_texture {
+            return Ok(());
+        }
+        let size = [image.width(), image.height()];
+        if size != region.size() {
+            return Err(anyhow::anyhow!(
+                "it's {}x{}, its sprite sheet is {}x{}",
+                size[0],
+                size[1],
+                region.size()[0],
+                region.size()[1]
+            ));
+        }
+
+        if !self.normal_maps.contains_key(&region.texture) {
+            let texture_size = self
+                .textures
+                .get(region.texture)
+                .ok_or_else(|| anyhow::anyhow!("its sprite sheet was unloaded"))?
+                .size();
+            let normals =
+                ImageTexture::empty_linear(&self.device, texture_size, Some("Normal Map"));
+            self.texture_batch_context.add_normal_map(
+                region.texture,
+                &normals,
+                &self.device,
+                &self.texture_bind_group_layout,
+            );
+            self.normal_maps.insert(region.texture, normals);
+        }
+        self.normal_maps[&region.texture].write_image(
             &self.queue,
-            &image,
-            Some(&format!("Image Texture: {:?}", path).to_string()),
-        )
-        .unwrap();
-        let handle = self.texture_assets.insert(img_texture, Some(asset_path));
-        let img_texture = self.texture_assets.get(handle).unwrap();
-        self.texture_batch_context.add_texture(
-            handle,
-            &img_texture,
-            &mut self.device,
-            &self.texture_bind_group_layout,
+            image,
+            [region.rect[0], region.rect[1]],
         );
-        let texture_id = self.next_texture_id;
-        self.next_texture_id += 1;
-        self.texture_lookup.insert(texture_id, id.to_string());
-        handle
+        Ok(())
+    }
+
+    fn acquire_texture(&mut self, id: &str) {
+        *self.texture_users.entry(AssetPath::new(id)).or_insert(0) += 1;
+    }
+
+    fn release_texture(&mut self, id: &str) {
+        let path = AssetPath::new(id);
+        if let Some(users) = self.texture_users.get_mut(&path) {
+            *users -= 1;
+            if *users == 0 {
+                self.texture_users.remove(&path);
+            }
+        }
+    }
+
+    fn unload_unused_textures(&mut self) {
+        // files that failed to load get another try the next time they're asked for
+        let unused: Vec<AssetPath> = self
+            .texture_regions
+            .iter()
+            .filter(|(path, (region, _))| {
+                region.texture == self.missing_texture || !self.texture_users.contains_key(*path)
+            })
+            .map(|(path, _)| path.clone())
+            .collect();
+
+        for path in unused {
+            if let Some((region, texture_id)) = self.texture_regions.remove(&path) {
+                self.texture_lookup.remove(&texture_id);
+                self.free_region(region);
+            }
+        }
+    }
+
+    fn post_effects(&mut self) -> &mut PostEffects {
+        &mut self.post_effects
     }
 
     fn process_camera_event(&mut self, _event: &winit::event::WindowEvent) {}
@@ -726,3 +1329,30 @@ impl Graphics for Graphics2D {
         self.update_camera(dt, p[0..2].try_into().unwrap(), v[0..2].try_into().unwrap());
     }
 }
+
+fn save_png(image: &RgbaImage, path: &str) -> anyhow::Result<()> {
+    if let Some(parent) = std::path::Path::new(path).parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    image.save_with_format(path, image::ImageFormat::Png)?;
+    Ok(())
+}
+
+// Built-in textures are held forever so unloading never drops them.
+fn add_builtin_texture(
+    texture_assets: &mut AssetCache<ImageTexture>,
+    texture_batch_context: &mut WorldRenderBatch,
+    device: &mut Device,
+    texture_bind_group_layout: &BindGroupLayout,
+    texture: ImageTexture,
+) -> Handle<ImageTexture> {
+    let handle = texture_assets.insert(texture, None);
+    texture_assets.acquire(handle);
+    texture_batch_context.add_texture(
+        handle,
+        texture_assets.get(handle).unwrap(),
+        device,
+        texture_bind_group_layout,
+    );
+    handle
+}
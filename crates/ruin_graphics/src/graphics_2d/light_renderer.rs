@@ -0,0 +1,461 @@
+use std::ops::Range;
+
+use cgmath::{InnerSpace, Vector2};
+use ruin_ecs::world::LightElement2D;
+use wgpu::util::DeviceExt;
+
+use crate::graphics_2d::{
+    gpu_buffer::GrowableBuffer,
+    shape_pipelines::create_2d_pipeline_for_target,
+    shape_tesselation::TessellatedShape2D,
+    vertex::{LightInstance, Vertex},
+};
+
+pub const NORMAL_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+const LIGHT_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+const STENCIL_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Stencil8;
+// each light marks its shadows with its own stencil value, 0 being none
+const MAX_LIGHTS: usize = 255;
+
+// What world pipelines write their normals into, next to the colour.
+pub fn normal_target() -> wgpu::ColorTargetState {
+    wgpu::ColorTargetState {
+        format: NORMAL_FORMAT,
+        blend: None,
+        write_mask: wgpu::ColorWrites::ALL,
+    }
+}
+
+// Lights are added up in a texture cleared to the ambient colour, which is then multiplied
+// over the virtual screen. Shadows are the occluders' edges stretched away from each light,
+// stencilled out before the light is drawn.
+pub struct LightRenderer {
+    normal_view: wgpu::TextureView,
+    light_view: wgpu::TextureView,
+    stencil_view: wgpu::TextureView,
+    normal_bind_group: wgpu::BindGroup,
+    light_bind_group: wgpu::BindGroup,
+    shadow_pipeline: wgpu::RenderPipeline,
+    light_pipeline: wgpu::RenderPipeline,
+    composite_pipeline: wgpu::RenderPipeline,
+    quad_vertex_buffer: wgpu::Buffer,
+    quad_index_buffer: wgpu::Buffer,
+    quad_index_count: u32,
+    ambient: wgpu::Color,
+    instances: Vec<LightInstance>,
+    shadow_vertices: Vec<Vertex>,
+    // per light, into shadow_vertices
+    shadow_ranges: Vec<Range<u32>>,
+    instance_range: Option<Range<u64>>,
+    shadow_range: Option<Range<u64>>,
+    pub lights: u32,
+    pub draw_calls: u32,
+}
+
+impl LightRenderer {
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        size: [u32; 2],
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let screen_texture = |label: &str, format: wgpu::TextureFormat| {
+            device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some(label),
+                    size: wgpu::Extent3d {
+                        width: size[0],
+                        height: size[1],
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                        | wgpu::TextureUsages::TEXTURE_BINDING,
+                    view_formats: &[],
+                })
+                .create_view(&wgpu::TextureViewDescriptor::default())
+        };
+        let normal_view = screen_texture("Normal Buffer", NORMAL_FORMAT);
+        let light_view = screen_texture("Light Buffer", LIGHT_FORMAT);
+        let stencil_view = screen_texture("Shadow Stencil", STENCIL_FORMAT);
+
+        // read a texel per pixel, both are the size of the virtual screen
+        let screen_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                },
+                count: None,
+            }],
+            label: Some("screen_texture_bind_group_layout"),
+        });
+        let screen_bind_group = |label: &str, view: &wgpu::TextureView| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &screen_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(view),
+                }],
+                label: Some(label),
+            })
+        };
+        let normal_bind_group = screen_bind_group("Normal Buffer Bind Group", &normal_view);
+        let light_bind_group = screen_bind_group("Light Buffer Bind Group", &light_view);
+
+        let stencil = |compare: wgpu::CompareFunction, pass_op: wgpu::StencilOperation| {
+            let face = wgpu::StencilFaceState {
+                compare,
+                fail_op: wgpu::StencilOperation::Keep,
+                depth_fail_op: wgpu::StencilOperation::Keep,
+                pass_op,
+            };
+            Some(wgpu::DepthStencilState {
+                format: STENCIL_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: wgpu::StencilState {
+                    front: face,
+                    back: face,
+                    read_mask: 0xff,
+                    write_mask: 0xff,
+                },
+                bias: wgpu::DepthBiasState::default(),
+            })
+        };
+        let layouts = Vec::from([&screen_layout, camera_bind_group_layout]);
+
+        let shadow_shader =
+            device.create_shader_module(wgpu::include_wgsl!("shaders/2d_light_shadow.wgsl"));
+        let shadow_pipeline = create_2d_pipeline_for_target(
+            "Shadow Pipeline",
+            device,
+            &shadow_shader,
+            &[Vertex::desc()],
+            &layouts,
+            stencil(
+                wgpu::CompareFunction::Always,
+                wgpu::StencilOperation::Replace,
+            ),
+            &[Some(wgpu::ColorTargetState {
+                format: LIGHT_FORMAT,
+                blend: None,
+                write_mask: wgpu::ColorWrites::empty(),
+            })],
+        );
+
+        let light_shader =
+            device.create_shader_module(wgpu::include_wgsl!("shaders/2d_light.wgsl"));
+        let light_pipeline = create_2d_pipeline_for_target(
+            "Light Pipeline",
+            device,
+            &light_shader,
+            &[Vertex::desc(), LightInstance::desc()],
+            &layouts,
+            // lit wherever this light's shadows didn't mark
+            stencil(
+                wgpu::CompareFunction::NotEqual,
+                wgpu::StencilOperation::Keep,
+            ),
+            &[Some(wgpu::ColorTargetState {
+                format: LIGHT_FORMAT,
+                blend: Some(wgpu::BlendState {
+                    color: wgpu::BlendComponent {
+                        src_factor: wgpu::BlendFactor::One,
+                        dst_factor: wgpu::BlendFactor::One,
+                        operation: wgpu::BlendOperation::Add,
+                    },
+                    alpha: wgpu::BlendComponent::REPLACE,
+                }),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        );
+
+        let composite_shader =
+            device.create_shader_module(wgpu::include_wgsl!("shaders/2d_light_composite.wgsl"));
+        let composite_pipeline = create_2d_pipeline_for_target(
+            "Light Composite Pipeline",
+            device,
+            &composite_shader,
+            &[],
+            &Vec::from([&screen_layout]),
+            None,
+            &[Some(wgpu::ColorTargetState {
+                format,
+                // screen colour times light, its alpha left alone
+                blend: Some(wgpu::BlendState {
+                    color: wgpu::BlendComponent {
+                        src_factor: wgpu::BlendFactor::Dst,
+                        dst_factor: wgpu::BlendFactor::Zero,
+                        operation: wgpu::BlendOperation::Add,
+                    },
+                    alpha: wgpu::BlendComponent {
+                        src_factor: wgpu::BlendFactor::Zero,
+                        dst_factor: wgpu::BlendFactor::One,
+                        operation: wgpu::BlendOperation::Add,
+                    },
+                }),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        );
+
+        let quad = TessellatedShape2D::rect(0.5, 0.5);
+        let vertices: Vec<Vertex> = quad
+            .vertices
+            .iter()
+            .map(|v| Vertex {
+                position: [v.x, v.y],
+            })
+            .collect();
+
+        Self {
+            normal_view,
+            light_view,
+            stencil_view,
+            normal_bind_group,
+            light_bind_group,
+            shadow_pipeline,
+            light_pipeline,
+            composite_pipeline,
+            quad_vertex_buffer: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Light Quad Vertex Buffer"),
+                contents: bytemuck::cast_slice(&vertices),
+                usage: wgpu::BufferUsages::VERTEX,
+            }),
+            quad_index_buffer: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Light Quad Index Buffer"),
+                contents: bytemuck::cast_slice(&quad.indices),
+                usage: wgpu::BufferUsages::INDEX,
+            }),
+            quad_index_count: quad.indices.len() as u32,
+            ambient: wgpu::Color::WHITE,
+            instances: Vec::new(),
+            shadow_vertices: Vec::new(),
+            shadow_ranges: Vec::new(),
+            instance_range: None,
+            shadow_range: None,
+            lights: 0,
+            draw_calls: 0,
+        }
+    }
+
+    // Where the world pass writes its normals.
+    pub fn normal_view(&self) -> &wgpu::TextureView {
+        &self.normal_view
+    }
+
+    // Builds the light quads and shadow geometry for the frame. `occluders` are outlines,
+    // counter-clockwise.
+    pub fn prepare(
+        &mut self,
+        lights: &[LightElement2D],
+        occluders: &[Vec<Vector2<f32>>],
+        ambient: [f32; 3],
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        instance_buffer: &mut GrowableBuffer,
+    ) {
+        if lights.len() > MAX_LIGHTS {
+            println!(
+                "Only {} lights are drawn, dropping {}",
+                MAX_LIGHTS,
+                lights.len() - MAX_LIGHTS
+            );
+        }
+        let lights = &lights[..lights.len().min(MAX_LIGHTS)];
+
+        self.ambient = wgpu::Color {
+            r: ambient[0] as f64,
+            g: ambient[1] as f64,
+            b: ambient[2] as f64,
+            a: 1.0,
+        };
+        self.instances.clear();
+        self.shadow_vertices.clear();
+        self.shadow_ranges.clear();
+        for light in lights {
+            let [r, g, b] = light.color;
+            self.instances.push(LightInstance {
+                position: light.position,
+                params: [light.radius, light.falloff, light.height, 0.0],
+                color: [r, g, b, 1.0],
+                spot: [
+                    light.direction[0],
+                    light.direction[1],
+                    light.cone[0],
+                    light.cone[1],
+                ],
+            });
+
+            let start = self.shadow_vertices.len() as u32;
+            if light.cast_shadows {
+                for outline in occluders {
+                    push_shadow(&mut self.shadow_vertices, light, outline);
+                }
+            }
+            self.shadow_ranges
+                .push(start..self.shadow_vertices.len() as u32);
+        }
+
+        // empty buffer slices aren't allowed
+        let mut push = |data: &[u8]| match data.is_empty() {
+            true => None,
+            false => instance_buffer.push(device, queue, data),
+        };
+        self.instance_range = push(bytemuck::cast_slice(&self.instances));
+        self.shadow_range = push(bytemuck::cast_slice(&self.shadow_vertices));
+        self.lights = self.instances.len() as u32;
+        // a quad per light and one for its shadows, then the composite
+        self.draw_calls = self.lights
+            + self
+                .shadow_ranges
+                .iter()
+                .filter(|range| !range.is_empty())
+                .count() as u32
+            + 1;
+    }
+
+    // Draws the lights prepared for this frame and multiplies them over `screen`.
+    pub fn render(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        camera_bind_group: &wgpu::BindGroup,
+        screen: &wgpu::TextureView,
+        instance_buffer: &GrowableBuffer,
+    ) {
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Light Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &self.light_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(self.ambient),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.stencil_view,
+                    depth_ops: None,
+                    stencil_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(0),
+                        store: wgpu::StoreOp::Discard,
+                    }),
+                }),
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+
+            if let Some(instance_range) = self.instance_range.clone() {
+                pass.set_bind_group(0, &self.normal_bind_group, &[]);
+                pass.set_bind_group(1, camera_bind_group, &[]);
+                for (index, shadow) in self.shadow_ranges.iter().enumerate() {
+                    pass.set_stencil_reference(index as u32 + 1);
+                    if let (Some(shadow_range), false) =
+                        (self.shadow_range.clone(), shadow.is_empty())
+                    {
+                        pass.set_pipeline(&self.shadow_pipeline);
+                        pass.set_vertex_buffer(0, instance_buffer.buffer().slice(shadow_range));
+                        pass.draw(shadow.clone(), 0..1);
+                    }
+
+                    pass.set_pipeline(&self.light_pipeline);
+                    pass.set_vertex_buffer(0, self.quad_vertex_buffer.slice(..));
+                    pass.set_vertex_buffer(
+                        1,
+                        instance_buffer.buffer().slice(instance_range.clone()),
+                    );
+                    pass.set_index_buffer(
+                        self.quad_index_buffer.slice(..),
+                        wgpu::IndexFormat::Uint16,
+                    );
+                    pass.draw_indexed(0..self.quad_index_count, 0, index as u32..index as u32 + 1);
+                }
+            }
+        }
+
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Light Composite Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: screen,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+        pass.set_pipeline(&self.composite_pipeline);
+        pass.set_bind_group(0, &self.light_bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+}
+
+// Each edge of `outline` stretched away from the light until it's past its radius, as
+// triangles. A far point on the bisector keeps edges close to the light from cutting back in.
+fn push_shadow(vertices: &mut Vec<Vertex>, light: &LightElement2D, outline: &[Vector2<f32>]) {
+    let center = Vector2::from(light.position);
+    let reach = light.radius * 2.0;
+
+    let (min, max) = outline.iter().fold(
+        (
+            Vector2::new(f32::MAX, f32::MAX),
+            Vector2::new(f32::MIN, f32::MIN),
+        ),
+        |(min, max), point| {
+            (
+                Vector2::new(min.x.min(point.x), min.y.min(point.y)),
+                Vector2::new(max.x.max(point.x), max.y.max(point.y)),
+            )
+        },
+    );
+    if max.x < center.x - light.radius
+        || min.x > center.x + light.radius
+        || max.y < center.y - light.radius
+        || min.y > center.y + light.radius
+    {
+        return;
+    }
+
+    // a light inside an occluder would be shadowed everywhere, let it shine out instead
+    let inside = outline.iter().enumerate().all(|(i, a)| {
+        let b = outline[(i + 1) % outline.len()];
+        let (edge, to_light) = (b - a, center - a);
+        edge.x * to_light.y - edge.y * to_light.x >= 0.0
+    });
+    if inside {
+        return;
+    }
+
+    let far = |direction: Vector2<f32>, distance: f32| -> Option<Vector2<f32>> {
+        (direction.magnitude2() > f32::EPSILON)
+            .then(|| center + direction.normalize() * (distance + reach))
+    };
+    for (i, a) in outline.iter().copied().enumerate() {
+        let b = outline[(i + 1) % outline.len()];
+        let (to_a, to_b) = (a - center, b - center);
+        let (far_a, far_b) = match (far(to_a, to_a.magnitude()), far(to_b, to_b.magnitude())) {
+            (Some(far_a), Some(far_b)) => (far_a, far_b),
+            _ => continue,
+        };
+        let middle = far(
+            to_a.normalize() + to_b.normalize(),
+            to_a.magnitude().max(to_b.magnitude()),
+        )
+        .unwrap_or((far_a + far_b) * 0.5);
+        for position in [a, b, far_b, a, far_b, middle, a, middle, far_a] {
+            vertices.push(Vertex {
+                position: position.into(),
+            });
+        }
+    }
+}
@@ -1,15 +1,26 @@
 mod camera_uniform;
 mod debug_render_batch;
 mod depth_texture;
+mod gpu_buffer;
 mod graphics_2d;
+mod light_renderer;
+mod particle_renderer;
+mod post_effects;
+mod post_processor;
+mod render_target;
 mod shape_pipelines;
 mod shape_tesselation;
 mod space;
+mod tilemap_renderer;
 mod vertex;
+mod virtual_screen;
 mod world_render_batch;
 
 use camera_uniform::CameraUniform2D;
 use debug_render_batch::DebugRenderBatch;
 use depth_texture::DepthTexture;
 pub use graphics_2d::{Graphics2D, TextureId};
+pub use post_effects::{
+    Bloom, ColorGrade, PostEffect, PostEffects, Scanlines, ScreenFlash, Vignette,
+};
 use vertex::{ColorVertex, TextureVertex};
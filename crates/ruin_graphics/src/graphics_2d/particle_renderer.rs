@@ -0,0 +1,170 @@
+use ruin_assets::{Handle, ImageTexture};
+use ruin_ecs::world::ParticleBatch2D;
+use ruin_ecs::ParticleBlend;
+use wgpu::util::DeviceExt;
+
+use crate::graphics_2d::{
+    gpu_buffer::GrowableBuffer,
+    light_renderer::normal_target,
+    shape_pipelines::create_2d_pipeline_for_target,
+    shape_tesselation::TessellatedShape2D,
+    vertex::{ParticleInstance, Vertex},
+    world_render_batch::WorldRenderBatch,
+    DepthTexture,
+};
+
+// Particles are instanced quads like sprites, one draw call per emitter. They're drawn over
+// the sprites of their layer without touching the depth buffer.
+pub struct ParticleRenderer {
+    quad_vertex_buffer: wgpu::Buffer,
+    quad_index_buffer: wgpu::Buffer,
+    quad_index_count: u32,
+    alpha_pipeline: wgpu::RenderPipeline,
+    additive_pipeline: wgpu::RenderPipeline,
+    instances: Vec<ParticleInstance>,
+    // white, drawn for batches without a sprite
+    blank_texture: Handle<ImageTexture>,
+    pub draw_calls: u32,
+    pub particles: u32,
+}
+
+impl ParticleRenderer {
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        texture_bind_group_layout: &wgpu::BindGroupLayout,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+        blank_texture: Handle<ImageTexture>,
+    ) -> Self {
+        let quad = TessellatedShape2D::rect(0.5, 0.5);
+        let vertices: Vec<Vertex> = quad
+            .vertices
+            .iter()
+            .map(|v| Vertex {
+                position: [v.x, v.y],
+            })
+            .collect();
+
+        let shader =
+            device.create_shader_module(wgpu::include_wgsl!("shaders/2d_particle_instanced.wgsl"));
+        let pipeline = |label: &str, blend: wgpu::BlendState| {
+            create_2d_pipeline_for_target(
+                label,
+                device,
+                &shader,
+                &[Vertex::desc(), ParticleInstance::desc()],
+                &Vec::from([texture_bind_group_layout, camera_bind_group_layout]),
+                Some(wgpu::DepthStencilState {
+                    format: DepthTexture::DEPTH_FORMAT,
+                    depth_write_enabled: false,
+                    depth_compare: wgpu::CompareFunction::Always,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                &[
+                    Some(wgpu::ColorTargetState {
+                        format,
+                        blend: Some(blend),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    }),
+                    // particles have no normals, what's under them keeps its own
+                    Some(wgpu::ColorTargetState {
+                        write_mask: wgpu::ColorWrites::empty(),
+                        ..normal_target()
+                    }),
+                ],
+            )
+        };
+        let alpha_pipeline = pipeline("Particle Pipeline", wgpu::BlendState::ALPHA_BLENDING);
+        // adds light, so overlapping sparks glow
+        let additive_pipeline = pipeline(
+            "Additive Particle Pipeline",
+            wgpu::BlendState {
+                color: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::SrcAlpha,
+                    dst_factor: wgpu::BlendFactor::One,
+                    operation: wgpu::BlendOperation::Add,
+                },
+                alpha: wgpu::BlendComponent::OVER,
+            },
+        );
+
+        Self {
+            quad_vertex_buffer: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Particle Quad Vertex Buffer"),
+                contents: bytemuck::cast_slice(&vertices),
+                usage: wgpu::BufferUsages::VERTEX,
+            }),
+            quad_index_buffer: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Particle Quad Index Buffer"),
+                contents: bytemuck::cast_slice(&quad.indices),
+                usage: wgpu::BufferUsages::INDEX,
+            }),
+            quad_index_count: quad.indices.len() as u32,
+            alpha_pipeline,
+            additive_pipeline,
+            instances: Vec::new(),
+            blank_texture,
+            draw_calls: 0,
+            particles: 0,
+        }
+    }
+
+    // Expects the camera bound at group 1, as the sprite pipeline leaves it.
+    pub fn draw<'a>(
+        &mut self,
+        batches: impl Iterator<Item = &'a ParticleBatch2D>,
+        textures: &WorldRenderBatch,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        pass: &mut wgpu::RenderPass,
+        instance_buffer: &mut GrowableBuffer,
+    ) {
+        for batch in batches {
+            let bind_group = match textures.bind_group(&batch.texture.unwrap_or(self.blank_texture))
+            {
+                Some(bind_group) => bind_group,
+                None => continue,
+            };
+
+            self.instances.clear();
+            self.instances
+                .extend(batch.particles.iter().map(|particle| ParticleInstance {
+                    position: particle.position,
+                    size: particle.size,
+                    rotation: [particle.rotation.cos(), particle.rotation.sin()],
+                    uv_top_left: particle.uv_coords[0],
+                    uv_bottom_right: particle.uv_coords[1],
+                    color: particle.color,
+                }));
+            let range =
+                match instance_buffer.push(device, queue, bytemuck::cast_slice(&self.instances)) {
+                    Some(range) => range,
+                    None => {
+                        println!(
+                            "Too many particles for one buffer, dropping {}",
+                            self.instances.len()
+                        );
+                        continue;
+                    }
+                };
+
+            pass.set_pipeline(match batch.blend {
+                ParticleBlend::Alpha => &self.alpha_pipeline,
+                ParticleBlend::Additive => &self.additive_pipeline,
+            });
+            pass.set_bind_group(0, bind_group, &[]);
+            pass.set_vertex_buffer(0, self.quad_vertex_buffer.slice(..));
+            pass.set_vertex_buffer(1, instance_buffer.buffer().slice(range));
+            pass.set_index_buffer(self.quad_index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+            pass.draw_indexed(0..self.quad_index_count, 0, 0..self.instances.len() as u32);
+            self.draw_calls += 1;
+            self.particles += self.instances.len() as u32;
+        }
+    }
+
+    pub fn reset_stats(&mut self) {
+        self.draw_calls = 0;
+        self.particles = 0;
+    }
+}
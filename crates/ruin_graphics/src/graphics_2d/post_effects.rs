@@ -0,0 +1,184 @@
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PostEffect {
+    Bloom,
+    ColorGrade,
+    Vignette,
+    Scanlines,
+    Flash,
+}
+
+impl PostEffect {
+    pub const ALL: [PostEffect; 5] = [
+        PostEffect::Bloom,
+        PostEffect::ColorGrade,
+        PostEffect::Vignette,
+        PostEffect::Scanlines,
+        PostEffect::Flash,
+    ];
+}
+
+impl FromStr for PostEffect {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "bloom" => Ok(PostEffect::Bloom),
+            "color_grade" | "colour_grade" | "lut" => Ok(PostEffect::ColorGrade),
+            "vignette" => Ok(PostEffect::Vignette),
+            "scanlines" | "crt" => Ok(PostEffect::Scanlines),
+            "flash" => Ok(PostEffect::Flash),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bloom {
+    pub enabled: bool,
+    pub threshold: f32, // brightness it starts glowing from
+    pub intensity: f32,
+    pub spread: f32, // blur step in half resolution pixels
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorGrade {
+    pub enabled: bool,
+    // a strip of N slices of N x N, red across a slice, green down it, blue from slice to slice
+    pub lut: Option<String>,
+    pub amount: f32, // 0 keeps the original colours, 1 is fully graded
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Vignette {
+    pub enabled: bool,
+    pub strength: f32,
+    pub radius: f32,   // from the centre, where darkening starts, 1 being a corner
+    pub softness: f32, // how far past the radius it takes to reach full strength
+    pub color: [f32; 3],
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scanlines {
+    pub enabled: bool,
+    pub intensity: f32,
+    pub spacing: f32,   // window pixels from one line to the next
+    pub curvature: f32, // bulges the screen like an old tube, 0 keeps it flat
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScreenFlash {
+    pub color: [f32; 3],
+    pub duration: f32,
+    pub time: f32, // left until it has faded out
+}
+
+impl ScreenFlash {
+    pub fn strength(&self) -> f32 {
+        if self.duration > 0.0 {
+            (self.time / self.duration).clamp(0.0, 1.0)
+        } else {
+            0.0
+        }
+    }
+}
+
+// Fullscreen effects applied to the upscaled game, before the canvas is drawn over it. Each is
+// skipped while disabled, and effects left out of `order` never run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PostEffects {
+    pub order: Vec<PostEffect>,
+    pub bloom: Bloom,
+    pub color_grade: ColorGrade,
+    pub vignette: Vignette,
+    pub scanlines: Scanlines,
+    pub flash: ScreenFlash,
+}
+
+impl Default for PostEffects {
+    fn default() -> Self {
+        Self {
+            order: PostEffect::ALL.to_vec(),
+            bloom: Bloom {
+                enabled: false,
+                threshold: 0.7,
+                intensity: 0.8,
+                spread: 1.5,
+            },
+            color_grade: ColorGrade {
+                enabled: false,
+                lut: None,
+                amount: 1.0,
+            },
+            vignette: Vignette {
+                enabled: false,
+                strength: 0.5,
+                radius: 0.6,
+                softness: 0.5,
+                color: [0.0; 3],
+            },
+            scanlines: Scanlines {
+                enabled: false,
+                intensity: 0.25,
+                spacing: 3.0,
+                curvature: 0.0,
+            },
+            flash: ScreenFlash {
+                color: [1.0; 3],
+                duration: 0.0,
+                time: 0.0,
+            },
+        }
+    }
+}
+
+impl PostEffects {
+    pub fn enabled(&self, effect: PostEffect) -> bool {
+        match effect {
+            PostEffect::Bloom => self.bloom.enabled,
+            PostEffect::ColorGrade => self.color_grade.enabled && self.color_grade.lut.is_some(),
+            PostEffect::Vignette => self.vignette.enabled,
+            PostEffect::Scanlines => self.scanlines.enabled,
+            PostEffect::Flash => self.flash.time > 0.0,
+        }
+    }
+
+    pub fn set_enabled(&mut self, effect: PostEffect, enabled: bool) {
+        match effect {
+            PostEffect::Bloom => self.bloom.enabled = enabled,
+            PostEffect::ColorGrade => self.color_grade.enabled = enabled,
+            PostEffect::Vignette => self.vignette.enabled = enabled,
+            PostEffect::Scanlines => self.scanlines.enabled = enabled,
+            PostEffect::Flash => {
+                if !enabled {
+                    self.flash.time = 0.0;
+                }
+            }
+        }
+    }
+
+    // The effects to run this frame, in order.
+    pub fn active(&self) -> Vec<PostEffect> {
+        let mut active: Vec<PostEffect> = Vec::new();
+        for effect in &self.order {
+            if self.enabled(*effect) && !active.contains(effect) {
+                active.push(*effect);
+            }
+        }
+        active
+    }
+
+    // Covers the screen in `color`, fading out over `duration` seconds.
+    pub fn flash(&mut self, color: [f32; 3], duration: f32) {
+        self.flash = ScreenFlash {
+            color,
+            duration: duration.max(0.0),
+            time: duration.max(0.0),
+        };
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.flash.time = (self.flash.time - dt).max(0.0);
+    }
+}
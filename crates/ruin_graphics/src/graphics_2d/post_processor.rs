@@ -0,0 +1,553 @@
+use ruin_assets::{load_image, AssetSource, ImageTexture};
+use wgpu::util::DeviceExt;
+
+use crate::graphics_2d::{
+    post_effects::{PostEffect, PostEffects},
+    shape_pipelines::create_2d_pipeline_for_target,
+    virtual_screen::VirtualScreen,
+};
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default, bytemuck::Pod, bytemuck::Zeroable)]
+struct PostUniform {
+    size: [f32; 4], // width, height, 1 / width, 1 / height of the texture drawn into
+    params: [f32; 4],
+    color: [f32; 4],
+}
+
+struct EffectPass {
+    pipeline: wgpu::RenderPipeline,
+    uniform: wgpu::Buffer,
+}
+
+// Textures the size of the viewport the chain bounces between, and half size ones for bloom.
+struct Targets {
+    size: [u32; 2],
+    screens: [wgpu::TextureView; 2],
+    bloom: [wgpu::TextureView; 2],
+}
+
+struct Lut {
+    path: String,
+    // None when it failed to load, so it isn't retried every frame
+    texture: Option<(ImageTexture, u32)>,
+}
+
+enum Output<'a> {
+    Texture(&'a wgpu::TextureView),
+    // the swapchain, drawn inside the virtual screen's viewport with the bars cleared
+    Screen(&'a wgpu::TextureView, &'a VirtualScreen),
+}
+
+// Runs the enabled post effects one after the other. The virtual screen is scaled up into an
+// intermediate texture first so effects work at the window's resolution, and the last effect
+// writes straight into the frame.
+pub struct PostProcessor {
+    format: wgpu::TextureFormat,
+    layout: wgpu::BindGroupLayout,
+    blend_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    targets: Option<Targets>,
+    lut: Option<Lut>,
+    vignette: EffectPass,
+    scanlines: EffectPass,
+    flash: EffectPass,
+    color_grade: EffectPass,
+    bloom_extract: EffectPass,
+    bloom_blur: [EffectPass; 2],
+    bloom_combine: EffectPass,
+    active: Vec<PostEffect>,
+    pub draw_calls: u32,
+}
+
+impl PostProcessor {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let mut entries = vec![
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+        ];
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &entries,
+            label: Some("post_effect_bind_group_layout"),
+        });
+        // effects reading a second texture, the LUT or the bloom
+        entries.push(wgpu::BindGroupLayoutEntry {
+            binding: 3,
+            ..entries[0]
+        });
+        let blend_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &entries,
+            label: Some("post_blend_bind_group_layout"),
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Post Effect Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let effect = |label: &str, shader: wgpu::ShaderModuleDescriptor, blend: bool| {
+            let shader = device.create_shader_module(shader);
+            let layout = if blend { &blend_layout } else { &layout };
+            EffectPass {
+                pipeline: create_2d_pipeline_for_target(
+                    label,
+                    device,
+                    &shader,
+                    &[],
+                    &Vec::from([layout]),
+                    None,
+                    &[Some(wgpu::ColorTargetState {
+                        format,
+                        blend: None,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                ),
+                uniform: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some(label),
+                    contents: bytemuck::cast_slice(&[PostUniform::default()]),
+                    usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                }),
+            }
+        };
+        let bloom_blur = effect(
+            "Bloom Blur Pipeline",
+            wgpu::include_wgsl!("shaders/2d_post_bloom_blur.wgsl"),
+            false,
+        );
+
+        Self {
+            format,
+            sampler,
+            targets: None,
+            lut: None,
+            vignette: effect(
+                "Vignette Pipeline",
+                wgpu::include_wgsl!("shaders/2d_post_vignette.wgsl"),
+                false,
+            ),
+            scanlines: effect(
+                "Scanlines Pipeline",
+                wgpu::include_wgsl!("shaders/2d_post_scanlines.wgsl"),
+                false,
+            ),
+            flash: effect(
+                "Flash Pipeline",
+                wgpu::include_wgsl!("shaders/2d_post_flash.wgsl"),
+                false,
+            ),
+            color_grade: effect(
+                "Color Grade Pipeline",
+                wgpu::include_wgsl!("shaders/2d_post_color_grade.wgsl"),
+                true,
+            ),
+            bloom_extract: effect(
+                "Bloom Extract Pipeline",
+                wgpu::include_wgsl!("shaders/2d_post_bloom_extract.wgsl"),
+                false,
+            ),
+            bloom_blur: [
+                EffectPass {
+                    pipeline: bloom_blur.pipeline.clone(),
+                    uniform: device.create_buffer(&wgpu::BufferDescriptor {
+                        label: Some("Bloom Blur Pipeline"),
+                        size: std::mem::size_of::<PostUniform>() as u64,
+                        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                        mapped_at_creation: false,
+                    }),
+                },
+                bloom_blur,
+            ],
+            bloom_combine: effect(
+                "Bloom Combine Pipeline",
+                wgpu::include_wgsl!("shaders/2d_post_bloom_combine.wgsl"),
+                true,
+            ),
+            layout,
+            blend_layout,
+            active: Vec::new(),
+            draw_calls: 0,
+        }
+    }
+
+    // Whether render has anything to do this frame, otherwise the virtual screen is presented
+    // as it is.
+    pub fn is_active(&self) -> bool {
+        !self.active.is_empty()
+    }
+
+    // Makes the LUT at `path` load again, when it's the one in use.
+    pub fn reload_lut(&mut self, path: &str) {
+        if self.lut.as_ref().is_some_and(|lut| lut.path == path) {
+            self.lut = None;
+        }
+    }
+
+    pub fn prepare(
+        &mut self,
+        effects: &PostEffects,
+        size: [u32; 2],
+        source: &dyn AssetSource,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) {
+        if let Some(path) = &effects.color_grade.lut {
+            if self.lut.as_ref().is_none_or(|lut| &lut.path != path) {
+                let texture = load_lut(source, path, device, queue)
+                    .inspect_err(|err| println!("Failed to load LUT {}: {}", path, err))
+                    .ok();
+                self.lut = Some(Lut {
+                    path: path.clone(),
+                    texture,
+                });
+            }
+        }
+        let lut_slices = self
+            .lut
+            .as_ref()
+            .and_then(|lut| lut.texture.as_ref())
+            .map(|(_, slices)| *slices);
+
+        self.active = effects.active();
+        if lut_slices.is_none() {
+            self.active
+                .retain(|effect| *effect != PostEffect::ColorGrade);
+        }
+        if self.active.is_empty() {
+            return;
+        }
+
+        let size = [size[0].max(1), size[1].max(1)];
+        if self
+            .targets
+            .as_ref()
+            .is_none_or(|targets| targets.size != size)
+        {
+            self.targets = Some(self.create_targets(device, size));
+        }
+        let full = uniform_size(size);
+        let half = uniform_size(half_size(size));
+
+        let write = |pass: &EffectPass, size: [f32; 4], params: [f32; 4], color: [f32; 3]| {
+            let uniform = PostUniform {
+                size,
+                params,
+                color: [color[0], color[1], color[2], 1.0],
+            };
+            queue.write_buffer(&pass.uniform, 0, bytemuck::cast_slice(&[uniform]));
+        };
+        for effect in &self.active {
+            match effect {
+                PostEffect::Bloom => {
+                    let bloom = &effects.bloom;
+                    write(
+                        &self.bloom_extract,
+                        half,
+                        [bloom.threshold, 0.0, 0.0, 0.0],
+                        [0.0; 3],
+                    );
+                    write(
+                        &self.bloom_blur[0],
+                        half,
+                        [bloom.spread * half[2], 0.0, 0.0, 0.0],
+                        [0.0; 3],
+                    );
+                    write(
+                        &self.bloom_blur[1],
+                        half,
+                        [0.0, bloom.spread * half[3], 0.0, 0.0],
+                        [0.0; 3],
+                    );
+                    write(
+                        &self.bloom_combine,
+                        full,
+                        [bloom.intensity, 0.0, 0.0, 0.0],
+                        [0.0; 3],
+                    );
+                }
+                PostEffect::ColorGrade => write(
+                    &self.color_grade,
+                    full,
+                    [
+                        effects.color_grade.amount.clamp(0.0, 1.0),
+                        lut_slices.unwrap_or(2) as f32,
+                        if self.format.is_srgb() { 1.0 } else { 0.0 },
+                        0.0,
+                    ],
+                    [0.0; 3],
+                ),
+                PostEffect::Vignette => {
+                    let vignette = &effects.vignette;
+                    write(
+                        &self.vignette,
+                        full,
+                        [
+                            vignette.strength.clamp(0.0, 1.0),
+                            vignette.radius,
+                            vignette.softness.max(0.001),
+                            0.0,
+                        ],
+                        vignette.color,
+                    );
+                }
+                PostEffect::Scanlines => {
+                    let scanlines = &effects.scanlines;
+                    write(
+                        &self.scanlines,
+                        full,
+                        [
+                            scanlines.intensity.clamp(0.0, 1.0),
+                            scanlines.spacing.max(1.0),
+                            scanlines.curvature.max(0.0),
+                            0.0,
+                        ],
+                        [0.0; 3],
+                    );
+                }
+                PostEffect::Flash => write(
+                    &self.flash,
+                    full,
+                    [effects.flash.strength(), 0.0, 0.0, 0.0],
+                    effects.flash.color,
+                ),
+            }
+        }
+    }
+
+    pub fn render(
+        &mut self,
+        virtual_screen: &VirtualScreen,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+    ) {
+        self.draw_calls = 0;
+        let Some(targets) = &self.targets else {
+            return;
+        };
+        {
+            let mut pass = begin_pass(encoder, "Post Upscale Pass", &targets.screens[0]);
+            virtual_screen.draw(&mut pass);
+        }
+        let mut draw_calls = 1;
+
+        let mut source = 0;
+        for (i, effect) in self.active.iter().enumerate() {
+            let input = &targets.screens[source];
+            let output = if i + 1 == self.active.len() {
+                Output::Screen(view, virtual_screen)
+            } else {
+                Output::Texture(&targets.screens[1 - source])
+            };
+            let [bloom, blurred] = &targets.bloom;
+            match effect {
+                PostEffect::Bloom => {
+                    let extract = &self.bloom_extract;
+                    self.draw(device, encoder, extract, &[input], Output::Texture(bloom));
+                    let horizontal = &self.bloom_blur[0];
+                    self.draw(
+                        device,
+                        encoder,
+                        horizontal,
+                        &[bloom],
+                        Output::Texture(blurred),
+                    );
+                    let vertical = &self.bloom_blur[1];
+                    self.draw(
+                        device,
+                        encoder,
+                        vertical,
+                        &[blurred],
+                        Output::Texture(bloom),
+                    );
+                    let combine = &self.bloom_combine;
+                    self.draw(device, encoder, combine, &[input, bloom], output);
+                    draw_calls += 4;
+                }
+                PostEffect::ColorGrade => {
+                    let lut = self.lut.as_ref().and_then(|lut| lut.texture.as_ref());
+                    let Some((lut, _)) = lut else {
+                        continue;
+                    };
+                    let grade = &self.color_grade;
+                    self.draw(device, encoder, grade, &[input, lut.view()], output);
+                    draw_calls += 1;
+                }
+                PostEffect::Vignette => {
+                    self.draw(device, encoder, &self.vignette, &[input], output);
+                    draw_calls += 1;
+                }
+                PostEffect::Scanlines => {
+                    self.draw(device, encoder, &self.scanlines, &[input], output);
+                    draw_calls += 1;
+                }
+                PostEffect::Flash => {
+                    self.draw(device, encoder, &self.flash, &[input], output);
+                    draw_calls += 1;
+                }
+            }
+            source = 1 - source;
+        }
+        self.draw_calls = draw_calls;
+    }
+
+    // Draws `effect` reading `inputs`, one texture or two for blend effects.
+    fn draw(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        effect: &EffectPass,
+        inputs: &[&wgpu::TextureView],
+        output: Output,
+    ) {
+        let mut entries = vec![
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(inputs[0]),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(&self.sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: effect.uniform.as_entire_binding(),
+            },
+        ];
+        if let Some(second) = inputs.get(1) {
+            entries.push(wgpu::BindGroupEntry {
+                binding: 3,
+                resource: wgpu::BindingResource::TextureView(second),
+            });
+        }
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: if inputs.len() > 1 {
+                &self.blend_layout
+            } else {
+                &self.layout
+            },
+            entries: &entries,
+            label: Some("Post Effect Bind Group"),
+        });
+
+        let mut pass = match output {
+            Output::Texture(view) => begin_pass(encoder, "Post Effect Pass", view),
+            Output::Screen(view, virtual_screen) => {
+                let mut pass = begin_pass(encoder, "Post Effect Pass", view);
+                virtual_screen.set_viewport(&mut pass);
+                pass
+            }
+        };
+        pass.set_pipeline(&effect.pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+
+    fn create_targets(&self, device: &wgpu::Device, size: [u32; 2]) -> Targets {
+        let texture = |label: &str, size: [u32; 2]| {
+            device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some(label),
+                    size: wgpu::Extent3d {
+                        width: size[0],
+                        height: size[1],
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: self.format,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                        | wgpu::TextureUsages::TEXTURE_BINDING,
+                    view_formats: &[],
+                })
+                .create_view(&wgpu::TextureViewDescriptor::default())
+        };
+        let half = half_size(size);
+        Targets {
+            size,
+            screens: [texture("Post Screen", size), texture("Post Screen", size)],
+            bloom: [texture("Bloom", half), texture("Bloom", half)],
+        }
+    }
+}
+
+// Clears `view` to black, which is also the colour of the bars around the viewport.
+fn begin_pass<'a>(
+    encoder: &'a mut wgpu::CommandEncoder,
+    label: &str,
+    view: &wgpu::TextureView,
+) -> wgpu::RenderPass<'a> {
+    encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some(label),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                store: wgpu::StoreOp::Store,
+            },
+        })],
+        depth_stencil_attachment: None,
+        occlusion_query_set: None,
+        timestamp_writes: None,
+    })
+}
+
+fn half_size(size: [u32; 2]) -> [u32; 2] {
+    [(size[0] / 2).max(1), (size[1] / 2).max(1)]
+}
+
+fn uniform_size(size: [u32; 2]) -> [f32; 4] {
+    let [width, height] = [size[0] as f32, size[1] as f32];
+    [width, height, 1.0 / width, 1.0 / height]
+}
+
+// A strip of N slices of N x N, so N x N wide and N high. Stored as data, not as sRGB.
+fn load_lut(
+    source: &dyn AssetSource,
+    path: &str,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+) -> anyhow::Result<(ImageTexture, u32)> {
+    // load_image flips images for sprite UVs, the LUT is looked up the way it was drawn
+    let image = load_image(source, path)?.flipv();
+    let slices = image.height();
+    if slices < 2 || image.width() != slices * slices {
+        return Err(anyhow::anyhow!(
+            "expected a strip of N slices of N x N pixels, got {}x{}",
+            image.width(),
+            image.height()
+        ));
+    }
+    let texture = ImageTexture::empty_linear(device, [image.width(), slices], Some(path));
+    texture.write_image(queue, &image, [0, 0]);
+    Ok((texture, slices))
+}
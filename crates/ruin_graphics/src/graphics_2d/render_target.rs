@@ -0,0 +1,203 @@
+use anyhow::{anyhow, Result};
+use image::RgbaImage;
+use wgpu::{
+    Device, Extent3d, SurfaceConfiguration, SurfaceError, SurfaceTexture, Texture, TextureFormat,
+    TextureUsages, TextureView, TextureViewDescriptor,
+};
+
+// Where frames are drawn: the window's surface, or a texture nobody sees so the renderer can
+// run with no window at all.
+pub enum RenderTarget {
+    Surface {
+        surface: wgpu::Surface<'static>,
+        config: SurfaceConfiguration,
+        configured: bool,
+    },
+    Offscreen {
+        texture: Texture,
+    },
+}
+
+pub struct RenderFrame {
+    pub view: TextureView,
+    pub texture: Texture,
+    surface_texture: Option<SurfaceTexture>,
+}
+
+impl RenderFrame {
+    pub fn present(self) {
+        if let Some(surface_texture) = self.surface_texture {
+            surface_texture.present();
+        }
+    }
+}
+
+impl RenderTarget {
+    pub fn offscreen(device: &Device, size: [u32; 2], format: TextureFormat) -> Self {
+        Self::Offscreen {
+            texture: create_offscreen_texture(device, size, format),
+        }
+    }
+
+    pub fn format(&self) -> TextureFormat {
+        match self {
+            RenderTarget::Surface { config, .. } => config.format,
+            RenderTarget::Offscreen { texture } => texture.format(),
+        }
+    }
+
+    pub fn size(&self) -> [u32; 2] {
+        match self {
+            RenderTarget::Surface { config, .. } => [config.width, config.height],
+            RenderTarget::Offscreen { texture } => [texture.width(), texture.height()],
+        }
+    }
+
+    // Surfaces only allow reading frames back when the platform lets them be copied from.
+    pub fn can_capture(&self) -> bool {
+        match self {
+            RenderTarget::Surface { config, .. } => config.usage.contains(TextureUsages::COPY_SRC),
+            RenderTarget::Offscreen { .. } => true,
+        }
+    }
+
+    pub fn resize(&mut self, device: &Device, width: u32, height: u32) {
+        match self {
+            RenderTarget::Surface {
+                surface,
+                config,
+                configured,
+            } => {
+                config.width = width;
+                config.height = height;
+                surface.configure(device, config);
+                *configured = true;
+            }
+            RenderTarget::Offscreen { texture } => {
+                *texture = create_offscreen_texture(device, [width, height], texture.format());
+            }
+        }
+    }
+
+    pub fn acquire(&mut self, device: &Device) -> Result<RenderFrame, SurfaceError> {
+        match self {
+            RenderTarget::Surface {
+                surface,
+                config,
+                configured,
+            } => {
+                if !*configured {
+                    surface.configure(device, config);
+                    *configured = true;
+                }
+                let output = surface.get_current_texture()?;
+                Ok(RenderFrame {
+                    view: output
+                        .texture
+                        .create_view(&TextureViewDescriptor::default()),
+                    texture: output.texture.clone(),
+                    surface_texture: Some(output),
+                })
+            }
+            RenderTarget::Offscreen { texture } => Ok(RenderFrame {
+                view: texture.create_view(&TextureViewDescriptor::default()),
+                texture: texture.clone(),
+                surface_texture: None,
+            }),
+        }
+    }
+}
+
+fn create_offscreen_texture(device: &Device, size: [u32; 2], format: TextureFormat) -> Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Offscreen Render Target"),
+        size: Extent3d {
+            width: size[0].max(1),
+            height: size[1].max(1),
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+        view_formats: &[],
+    })
+}
+
+// A frame copied into a buffer the CPU can map, read once the copy has been submitted.
+pub struct Readback {
+    buffer: wgpu::Buffer,
+    size: [u32; 2],
+    padded_bytes_per_row: u32,
+    format: TextureFormat,
+}
+
+impl Readback {
+    pub fn encode(device: &Device, encoder: &mut wgpu::CommandEncoder, texture: &Texture) -> Self {
+        let size = [texture.width(), texture.height()];
+        // rows of a texture copy have to start on 256 byte boundaries
+        let padded_bytes_per_row =
+            (size[0] * 4).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Readback Buffer"),
+            size: (padded_bytes_per_row * size[1]) as u64,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::TexelCopyBufferInfo {
+                buffer: &buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: Some(size[1]),
+                },
+            },
+            texture.size(),
+        );
+
+        Self {
+            buffer,
+            size,
+            padded_bytes_per_row,
+            format: texture.format(),
+        }
+    }
+
+    // Blocks until the GPU is done with the frame.
+    pub fn read(self, device: &Device) -> Result<RgbaImage> {
+        let swap_red_blue = match self.format {
+            TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => false,
+            TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb => true,
+            other => return Err(anyhow!("can't read back {:?} frames", other)),
+        };
+
+        let slice = self.buffer.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        device.poll(wgpu::PollType::Wait)?;
+        receiver.recv()??;
+
+        let [width, height] = self.size;
+        let mut pixels = Vec::with_capacity((width * height * 4) as usize);
+        {
+            let data = slice.get_mapped_range();
+            for row in data.chunks(self.padded_bytes_per_row as usize) {
+                pixels.extend_from_slice(&row[..(width * 4) as usize]);
+            }
+        }
+        self.buffer.unmap();
+
+        if swap_red_blue {
+            for pixel in pixels.chunks_exact_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+        RgbaImage::from_raw(width, height, pixels).ok_or_else(|| anyhow!("short frame readback"))
+    }
+}
@@ -32,12 +32,24 @@ fn vs_main(
 var t_diffuse: texture_2d<f32>;
 @group(0) @binding(1)
 var s_diffuse: sampler;
+@group(2) @binding(0)
+var t_normal: texture_2d<f32>;
+@group(2) @binding(1)
+var s_normal: sampler;
+
+struct FragmentOutput {
+    @location(0) color: vec4<f32>,
+    @location(1) normal: vec4<f32>, // alpha 0 where there's no normal map
+};
 
 @fragment
-fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+fn fs_main(in: VertexOutput) -> FragmentOutput {
 let color = textureSample(t_diffuse, s_diffuse, in.tex_coords);
    if (color.a < .01) {
         discard;
     }
-    return color;
+    var out: FragmentOutput;
+    out.color = color;
+    out.normal = textureSample(t_normal, s_normal, in.tex_coords);
+    return out;
 }
@@ -0,0 +1,115 @@
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+};
+@group(1) @binding(0)
+var<uniform> camera: CameraUniform;
+
+struct VertexInput {
+    @location(0) position: vec2<f32>,        // Vertex position (unit quad coords)
+    @location(1) instance_pos: vec2<f32>,    // Instance position
+    @location(2) instance_size: vec2<f32>,   // Instance size
+    @location(3) uv_top_left: vec2<f32>,
+    @location(4) uv_bottom_right: vec2<f32>,
+    @location(5) tint: vec4<f32>,
+    @location(6) flash: vec4<f32>,           // added colour, alpha is its strength
+    @location(7) color_row_r: vec4<f32>,
+    @location(8) color_row_g: vec4<f32>,
+    @location(9) color_row_b: vec4<f32>,
+    @location(10) color_row_a: vec4<f32>,
+    @location(11) color_offset: vec4<f32>,
+    @location(12) pivot_rotation: vec4<f32>, // pivot from instance_pos, then cos and sin
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) tint: vec4<f32>,
+    @location(2) flash: vec4<f32>,
+    @location(3) color_row_r: vec4<f32>,
+    @location(4) color_row_g: vec4<f32>,
+    @location(5) color_row_b: vec4<f32>,
+    @location(6) color_row_a: vec4<f32>,
+    @location(7) color_offset: vec4<f32>,
+    @location(8) mirror_rotation: vec4<f32>, // -1 along flipped axes, then cos and sin
+};
+
+@vertex
+fn vs_main(input: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+
+    // rotate the corner around the pivot, counter-clockwise
+    let pivot = input.pivot_rotation.xy;
+    let c = input.pivot_rotation.z;
+    let s = input.pivot_rotation.w;
+    let corner = input.position * input.instance_size - pivot;
+    let rotated = vec2<f32>(corner.x * c - corner.y * s, corner.x * s + corner.y * c);
+    let world_pos = rotated + pivot + input.instance_pos;
+    // quad corners run from -0.5 to 0.5, y up
+    let t = vec2<f32>(input.position.x + 0.5, 0.5 - input.position.y);
+    out.tex_coords = mix(input.uv_top_left, input.uv_bottom_right, t);
+    out.tint = input.tint;
+    out.flash = input.flash;
+    out.color_row_r = input.color_row_r;
+    out.color_row_g = input.color_row_g;
+    out.color_row_b = input.color_row_b;
+    out.color_row_a = input.color_row_a;
+    out.color_offset = input.color_offset;
+    // flipped sprites have their UVs swapped
+    out.mirror_rotation = vec4<f32>(
+        select(1.0, -1.0, input.uv_top_left.x > input.uv_bottom_right.x),
+        select(1.0, -1.0, input.uv_top_left.y < input.uv_bottom_right.y),
+        c,
+        s,
+    );
+    out.clip_position = camera.view_proj * vec4<f32>(world_pos, 0.0, 1.0);
+
+    return out;
+}
+
+// Fragment shader
+@group(0) @binding(0)
+var t_diffuse: texture_2d<f32>;
+@group(0) @binding(1)
+var s_diffuse: sampler;
+@group(2) @binding(0)
+var t_normal: texture_2d<f32>;
+@group(2) @binding(1)
+var s_normal: sampler;
+
+struct FragmentOutput {
+    @location(0) color: vec4<f32>,
+    @location(1) normal: vec4<f32>, // alpha 0 where there's no normal map
+};
+
+@fragment
+fn fs_main(in: VertexOutput) -> FragmentOutput {
+    let sampled = textureSample(t_diffuse, s_diffuse, in.tex_coords);
+    // color matrix, then tint, then the flash added on top
+    let matrixed = vec4<f32>(
+        dot(in.color_row_r, sampled),
+        dot(in.color_row_g, sampled),
+        dot(in.color_row_b, sampled),
+        dot(in.color_row_a, sampled),
+    ) + in.color_offset;
+    var color = clamp(matrixed, vec4<f32>(0.0), vec4<f32>(1.0)) * in.tint;
+    if (color.a < .01) {
+        discard;
+    }
+    color = vec4<f32>(min(color.rgb + in.flash.rgb * in.flash.a, vec3<f32>(1.0)), color.a);
+
+    // normals turn with the sprite, so they stay facing the same way on screen
+    var normal = textureSample(t_normal, s_normal, in.tex_coords);
+    let n = (normal.xy * 2.0 - 1.0) * in.mirror_rotation.xy;
+    let c = in.mirror_rotation.z;
+    let s = in.mirror_rotation.w;
+    normal = vec4<f32>(
+        vec2<f32>(n.x * c - n.y * s, n.x * s + n.y * c) * 0.5 + 0.5,
+        normal.z,
+        normal.a,
+    );
+
+    var out: FragmentOutput;
+    out.color = color;
+    out.normal = normal;
+    return out;
+}
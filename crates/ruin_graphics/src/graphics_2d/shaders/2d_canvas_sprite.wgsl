@@ -1,22 +1,46 @@
-// Vertex shader
+// Same instances as the world sprites, positioned straight in clip space.
 struct VertexInput {
-    @location(0) position: vec3<f32>,
-    @location(1) tex_coords: vec2<f32>,
-}
+    @location(0) position: vec2<f32>,        // Vertex position (unit quad coords)
+    @location(1) instance_pos: vec2<f32>,    // Instance position
+    @location(2) instance_size: vec2<f32>,   // Instance size
+    @location(3) uv_top_left: vec2<f32>,
+    @location(4) uv_bottom_right: vec2<f32>,
+    @location(5) tint: vec4<f32>,
+    @location(6) flash: vec4<f32>,           // added colour, alpha is its strength
+    @location(7) color_row_r: vec4<f32>,
+    @location(8) color_row_g: vec4<f32>,
+    @location(9) color_row_b: vec4<f32>,
+    @location(10) color_row_a: vec4<f32>,
+    @location(11) color_offset: vec4<f32>,
+};
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) tex_coords: vec2<f32>,
-}
+    @location(1) tint: vec4<f32>,
+    @location(2) flash: vec4<f32>,
+    @location(3) color_row_r: vec4<f32>,
+    @location(4) color_row_g: vec4<f32>,
+    @location(5) color_row_b: vec4<f32>,
+    @location(6) color_row_a: vec4<f32>,
+    @location(7) color_offset: vec4<f32>,
+};
 
 @vertex
-fn vs_main(
-    model: VertexInput,
-) -> VertexOutput {
+fn vs_main(input: VertexInput) -> VertexOutput {
     var out: VertexOutput;
-    out.tex_coords = model.tex_coords;
-    //Multiplication order is important when it comes to matrices. The vector goes on the right, and the matrices go on the left in order of importance.
-    out.clip_position = vec4<f32>(model.position, 1.0);
+
+    let clip_pos = input.position * input.instance_size + input.instance_pos;
+    let t = vec2<f32>(input.position.x + 0.5, 0.5 - input.position.y);
+    out.tex_coords = mix(input.uv_top_left, input.uv_bottom_right, t);
+    out.tint = input.tint;
+    out.flash = input.flash;
+    out.color_row_r = input.color_row_r;
+    out.color_row_g = input.color_row_g;
+    out.color_row_b = input.color_row_b;
+    out.color_row_a = input.color_row_a;
+    out.color_offset = input.color_offset;
+    out.clip_position = vec4<f32>(clip_pos, 0.0, 1.0);
 
     return out;
 }
@@ -29,9 +53,18 @@ var s_diffuse: sampler;
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-let color = textureSample(t_diffuse, s_diffuse, in.tex_coords);
-   if (color.a < .01) {
+    let sampled = textureSample(t_diffuse, s_diffuse, in.tex_coords);
+    // color matrix, then tint, then the flash added on top
+    let matrixed = vec4<f32>(
+        dot(in.color_row_r, sampled),
+        dot(in.color_row_g, sampled),
+        dot(in.color_row_b, sampled),
+        dot(in.color_row_a, sampled),
+    ) + in.color_offset;
+    var color = clamp(matrixed, vec4<f32>(0.0), vec4<f32>(1.0)) * in.tint;
+    if (color.a < .01) {
         discard;
     }
+    color = vec4<f32>(min(color.rgb + in.flash.rgb * in.flash.a, vec3<f32>(1.0)), color.a);
     return color;
 }
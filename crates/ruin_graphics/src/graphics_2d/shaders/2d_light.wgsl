@@ -0,0 +1,57 @@
+// One quad per light, added into the light texture.
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+};
+@group(1) @binding(0)
+var<uniform> camera: CameraUniform;
+
+struct VertexInput {
+    @location(0) position: vec2<f32>, // Vertex position (unit quad coords)
+    @location(1) light_pos: vec2<f32>,
+    @location(2) params: vec4<f32>,   // radius, falloff, height
+    @location(3) color: vec4<f32>,
+    @location(4) spot: vec4<f32>,     // direction, cosines of the outer and inner cone
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) offset: vec2<f32>, // world units from the light
+    @location(1) params: vec4<f32>,
+    @location(2) color: vec4<f32>,
+    @location(3) spot: vec4<f32>,
+};
+
+@vertex
+fn vs_main(input: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    out.offset = input.position * 2.0 * input.params.x;
+    out.params = input.params;
+    out.color = input.color;
+    out.spot = input.spot;
+    out.clip_position = camera.view_proj * vec4<f32>(input.light_pos + out.offset, 0.0, 1.0);
+    return out;
+}
+
+// normals the world was drawn with, alpha 0 where there weren't any
+@group(0) @binding(0)
+var t_normal: texture_2d<f32>;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let radius = in.params.x;
+    let distance = length(in.offset);
+    if (distance >= radius) {
+        discard;
+    }
+
+    var amount = pow(1.0 - distance / radius, in.params.y);
+    let to_pixel = in.offset / max(distance, 0.0001);
+    amount *= smoothstep(in.spot.z, in.spot.w, dot(to_pixel, in.spot.xy));
+
+    let normal = textureLoad(t_normal, vec2<i32>(in.clip_position.xy), 0);
+    if (normal.a > 0.5) {
+        let to_light = normalize(vec3<f32>(-in.offset, in.params.z));
+        amount *= max(dot(normalize(normal.xyz * 2.0 - 1.0), to_light), 0.0);
+    }
+    return vec4<f32>(in.color.rgb * amount, 1.0);
+}
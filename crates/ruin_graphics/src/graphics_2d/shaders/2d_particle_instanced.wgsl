@@ -0,0 +1,55 @@
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+};
+@group(1) @binding(0)
+var<uniform> camera: CameraUniform;
+
+struct VertexInput {
+    @location(0) position: vec2<f32>,        // Vertex position (unit quad coords)
+    @location(1) instance_pos: vec2<f32>,
+    @location(2) instance_size: vec2<f32>,
+    @location(3) rotation: vec2<f32>,        // cos, sin
+    @location(4) uv_top_left: vec2<f32>,
+    @location(5) uv_bottom_right: vec2<f32>,
+    @location(6) color: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) color: vec4<f32>,
+};
+
+@vertex
+fn vs_main(input: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+
+    // particles spin around their centre
+    let corner = input.position * input.instance_size;
+    let c = input.rotation.x;
+    let s = input.rotation.y;
+    let rotated = vec2<f32>(corner.x * c - corner.y * s, corner.x * s + corner.y * c);
+    let world_pos = rotated + input.instance_pos;
+    // quad corners run from -0.5 to 0.5, y up
+    let t = vec2<f32>(input.position.x + 0.5, 0.5 - input.position.y);
+    out.tex_coords = mix(input.uv_top_left, input.uv_bottom_right, t);
+    out.color = input.color;
+    out.clip_position = camera.view_proj * vec4<f32>(world_pos, 0.0, 1.0);
+
+    return out;
+}
+
+// Fragment shader
+@group(0) @binding(0)
+var t_diffuse: texture_2d<f32>;
+@group(0) @binding(1)
+var s_diffuse: sampler;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(t_diffuse, s_diffuse, in.tex_coords) * in.color;
+    if (color.a < .01) {
+        discard;
+    }
+    return color;
+}
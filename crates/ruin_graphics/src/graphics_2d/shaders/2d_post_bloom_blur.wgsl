@@ -0,0 +1,42 @@
+// One direction of a gaussian blur. params: the step between taps, in texture coordinates.
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    var out: VertexOutput;
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    out.tex_coords = uv;
+    out.clip_position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    return out;
+}
+
+// size is width, height, 1 / width, 1 / height of the texture drawn into
+struct PostUniform {
+    size: vec4<f32>,
+    params: vec4<f32>,
+    color: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var t_screen: texture_2d<f32>;
+@group(0) @binding(1)
+var s_screen: sampler;
+@group(0) @binding(2)
+var<uniform> post: PostUniform;
+
+const WEIGHTS = array<f32, 5>(0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let step = post.params.xy;
+    var color = textureSample(t_screen, s_screen, in.tex_coords).rgb * WEIGHTS[0];
+    for (var i = 1; i < 5; i++) {
+        let offset = step * f32(i);
+        color += textureSample(t_screen, s_screen, in.tex_coords + offset).rgb * WEIGHTS[i];
+        color += textureSample(t_screen, s_screen, in.tex_coords - offset).rgb * WEIGHTS[i];
+    }
+    return vec4<f32>(color, 1.0);
+}
@@ -0,0 +1,38 @@
+// Adds the blurred glow back over the screen. params: intensity.
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    var out: VertexOutput;
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    out.tex_coords = uv;
+    out.clip_position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    return out;
+}
+
+// size is width, height, 1 / width, 1 / height of the texture drawn into
+struct PostUniform {
+    size: vec4<f32>,
+    params: vec4<f32>,
+    color: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var t_screen: texture_2d<f32>;
+@group(0) @binding(1)
+var s_screen: sampler;
+@group(0) @binding(2)
+var<uniform> post: PostUniform;
+
+@group(0) @binding(3)
+var t_bloom: texture_2d<f32>;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(t_screen, s_screen, in.tex_coords);
+    let glow = textureSample(t_bloom, s_screen, in.tex_coords).rgb;
+    return vec4<f32>(color.rgb + glow * post.params.x, color.a);
+}
@@ -0,0 +1,36 @@
+// Keeps what's brighter than the threshold, into a half size texture. params: threshold.
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    var out: VertexOutput;
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    out.tex_coords = uv;
+    out.clip_position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    return out;
+}
+
+// size is width, height, 1 / width, 1 / height of the texture drawn into
+struct PostUniform {
+    size: vec4<f32>,
+    params: vec4<f32>,
+    color: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var t_screen: texture_2d<f32>;
+@group(0) @binding(1)
+var s_screen: sampler;
+@group(0) @binding(2)
+var<uniform> post: PostUniform;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(t_screen, s_screen, in.tex_coords).rgb;
+    let brightness = max(color.r, max(color.g, color.b));
+    let glow = max(brightness - post.params.x, 0.0) / max(brightness, 0.0001);
+    return vec4<f32>(color * glow, 1.0);
+}
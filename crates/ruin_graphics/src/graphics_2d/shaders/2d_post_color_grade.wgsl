@@ -0,0 +1,69 @@
+// Looks colours up in a LUT strip. params: amount, slices, whether the screen is sRGB.
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    var out: VertexOutput;
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    out.tex_coords = uv;
+    out.clip_position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    return out;
+}
+
+// size is width, height, 1 / width, 1 / height of the texture drawn into
+struct PostUniform {
+    size: vec4<f32>,
+    params: vec4<f32>,
+    color: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var t_screen: texture_2d<f32>;
+@group(0) @binding(1)
+var s_screen: sampler;
+@group(0) @binding(2)
+var<uniform> post: PostUniform;
+
+@group(0) @binding(3)
+var t_lut: texture_2d<f32>;
+
+fn to_srgb(color: vec3<f32>) -> vec3<f32> {
+    let low = color * 12.92;
+    let high = 1.055 * pow(color, vec3<f32>(1.0 / 2.4)) - 0.055;
+    return select(high, low, color <= vec3<f32>(0.0031308));
+}
+
+fn from_srgb(color: vec3<f32>) -> vec3<f32> {
+    let low = color / 12.92;
+    let high = pow((color + 0.055) / 1.055, vec3<f32>(2.4));
+    return select(high, low, color <= vec3<f32>(0.04045));
+}
+
+fn lut_sample(cell: vec2<f32>, slice: f32, slices: f32) -> vec3<f32> {
+    let uv = vec2<f32>((cell.x + 0.5) / (slices * slices) + slice / slices, (cell.y + 0.5) / slices);
+    return textureSampleLevel(t_lut, s_screen, uv, 0.0).rgb;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(t_screen, s_screen, in.tex_coords);
+    let srgb = post.params.z > 0.5;
+    // the LUT holds encoded colours, like the image it was made from
+    let encoded = select(color.rgb, to_srgb(color.rgb), srgb);
+
+    let slices = post.params.y;
+    let cell = clamp(encoded, vec3<f32>(0.0), vec3<f32>(1.0)) * (slices - 1.0);
+    let slice = floor(cell.b);
+    let next = min(slice + 1.0, slices - 1.0);
+    let graded = mix(
+        lut_sample(cell.rg, slice, slices),
+        lut_sample(cell.rg, next, slices),
+        cell.b - slice,
+    );
+
+    let decoded = select(graded, from_srgb(graded), srgb);
+    return vec4<f32>(mix(color.rgb, decoded, post.params.x), color.a);
+}
@@ -0,0 +1,44 @@
+// CRT scanlines over an optionally curved screen. params: intensity, spacing, curvature.
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    var out: VertexOutput;
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    out.tex_coords = uv;
+    out.clip_position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    return out;
+}
+
+// size is width, height, 1 / width, 1 / height of the texture drawn into
+struct PostUniform {
+    size: vec4<f32>,
+    params: vec4<f32>,
+    color: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var t_screen: texture_2d<f32>;
+@group(0) @binding(1)
+var s_screen: sampler;
+@group(0) @binding(2)
+var<uniform> post: PostUniform;
+
+const TAU: f32 = 6.28318530718;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    var centered = in.tex_coords * 2.0 - 1.0;
+    centered = centered * (1.0 + post.params.z * centered.yx * centered.yx);
+    let uv = centered * 0.5 + 0.5;
+    let color = textureSample(t_screen, s_screen, uv);
+    // past the bent edges is the tube's black border
+    let inside = all(uv >= vec2<f32>(0.0)) && all(uv <= vec2<f32>(1.0));
+
+    let line = 0.5 + 0.5 * cos(uv.y * post.size.y / post.params.y * TAU);
+    let shade = 1.0 - post.params.x * (1.0 - line);
+    return select(vec4<f32>(0.0, 0.0, 0.0, 1.0), vec4<f32>(color.rgb * shade, color.a), inside);
+}
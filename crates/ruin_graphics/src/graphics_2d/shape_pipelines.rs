@@ -8,6 +8,32 @@ pub fn create_2d_pipeline(
     vertex_desc: &[wgpu::VertexBufferLayout],
     bind_group_layouts: &Vec<&wgpu::BindGroupLayout>,
     depth_stencil: Option<wgpu::DepthStencilState>,
+) -> wgpu::RenderPipeline {
+    create_2d_pipeline_for_target(
+        label,
+        device,
+        shader,
+        vertex_desc,
+        bind_group_layouts,
+        depth_stencil,
+        &[Some(wgpu::ColorTargetState {
+            format: surface_format,
+            blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+            write_mask: wgpu::ColorWrites::ALL,
+        })],
+    )
+}
+
+// Same as create_2d_pipeline, for pipelines that blend some other way or draw into more than
+// one target.
+pub fn create_2d_pipeline_for_target(
+    label: &str,
+    device: &wgpu::Device,
+    shader: &wgpu::ShaderModule,
+    vertex_desc: &[wgpu::VertexBufferLayout],
+    bind_group_layouts: &Vec<&wgpu::BindGroupLayout>,
+    depth_stencil: Option<wgpu::DepthStencilState>,
+    targets: &[Option<wgpu::ColorTargetState>],
 ) -> wgpu::RenderPipeline {
     let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
         label: Some(label),
@@ -27,11 +53,7 @@ pub fn create_2d_pipeline(
         fragment: Some(FragmentState {
             module: &shader,
             entry_point: Some("fs_main"),
-            targets: &[Some(wgpu::ColorTargetState {
-                format: surface_format,
-                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
-                write_mask: wgpu::ColorWrites::ALL,
-            })],
+            targets,
             compilation_options: Default::default(),
         }),
         primitive: wgpu::PrimitiveState::default(),
@@ -1,4 +1,4 @@
-use crate::graphics_2d::vertex::{ColorVertex, TextureVertex};
+use crate::graphics_2d::vertex::ColorVertex;
 use cgmath::Vector2;
 use ruin_ecs::physics_2d::Shape2D;
 
@@ -8,13 +8,6 @@ pub struct TessellatedShape2D {
 }
 
 impl TessellatedShape2D {
-    pub fn from(shape: &Shape2D, segments: u32) -> TessellatedShape2D {
-        match shape {
-            Shape2D::Circle { radius } => Self::circle(*radius, segments),
-            Shape2D::Rectangle { half_extents } => Self::rect(half_extents.x, half_extents.y),
-        }
-    }
-
     pub fn outline_from(shape: &Shape2D, thickness: f32, segments: u32) -> TessellatedShape2D {
         match shape {
             Shape2D::Circle { radius } => Self::circle_outline(*radius, thickness, segments),
@@ -151,38 +144,4 @@ impl TessellatedShape2D {
             })
             .collect()
     }
-
-    pub fn into_tex(&self, tex_coords: [[f32; 2]; 4]) -> Vec<TextureVertex> {
-        self.vertices
-            .iter()
-            .zip(tex_coords.iter())
-            .map(|(v, &uv)| TextureVertex {
-                position: [v.x, v.y],
-                tex_coords: uv,
-            })
-            .collect()
-    }
-
-    pub fn recenter(&mut self, center: Vector2<f32>) -> &mut Self {
-        if self.vertices.is_empty() {
-            return self;
-        }
-
-        // Compute current centroid (average of positions)
-        let sum = self
-            .vertices
-            .iter()
-            .fold(Vector2::new(0.0, 0.0), |acc, pos| acc + *pos);
-        let count = self.vertices.len() as f32;
-        let current_centroid = sum / count;
-
-        // Compute translation vector: how much to move all positions
-        let translation = center - current_centroid;
-
-        // Apply translation to all positions
-        for pos in &mut self.vertices {
-            *pos += translation;
-        }
-        self
-    }
 }
@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+
+use ruin_assets::{Handle, ImageTexture};
+use ruin_ecs::world::World;
+use ruin_ecs::{Entity, RenderLayer, TilemapComponent};
+
+use crate::graphics_2d::gpu_buffer::GpuMesh;
+use crate::graphics_2d::vertex::TextureVertex;
+use crate::graphics_2d::world_render_batch::WorldRenderBatch;
+
+// Tiles per side of a chunk.
+const CHUNK_SIZE: u32 = 16;
+
+struct TilemapChunk {
+    texture: Handle<ImageTexture>,
+    mesh: GpuMesh,
+    // world space min and max corners, for culling
+    bounds: [[f32; 2]; 2],
+}
+
+struct BuiltTilemap {
+    revision: u64,
+    layer: RenderLayer,
+    origin: [f32; 2],
+    // every layer's chunks, bottom layer first
+    chunks: Vec<TilemapChunk>,
+}
+
+// Tilemaps are static, so their vertices are built into GPU buffers once and only rebuilt
+// when the map changes or moves.
+pub struct TilemapRenderer {
+    tilemaps: HashMap<Entity, BuiltTilemap>,
+    draw_order: Vec<Entity>,
+}
+
+impl TilemapRenderer {
+    pub fn new() -> Self {
+        Self {
+            tilemaps: HashMap::new(),
+            draw_order: Vec::new(),
+        }
+    }
+
+    pub fn prepare(&mut self, world: &World, device: &wgpu::Device) {
+        self.tilemaps
+            .retain(|entity, _| world.tilemaps.contains_key(entity));
+
+        for (entity, tilemap) in world.tilemaps.iter() {
+            let origin = match world.transforms_2d.get(entity) {
+                Some(transform) => [transform.position.x, transform.position.y],
+                None => continue,
+            };
+            let up_to_date = self
+                .tilemaps
+                .get(entity)
+                .is_some_and(|built| built.revision == tilemap.revision && built.origin == origin);
+            let layer = world.render_layer_of(entity, RenderLayer::Ground).layer;
+            if !up_to_date {
+                let chunks = build_chunks(tilemap, origin, device);
+                self.tilemaps.insert(
+                    *entity,
+                    BuiltTilemap {
+                        revision: tilemap.revision,
+                        layer,
+                        origin,
+                        chunks,
+                    },
+                );
+            } else if let Some(built) = self.tilemaps.get_mut(entity) {
+                built.layer = layer;
+            }
+        }
+
+        self.draw_order.clear();
+        self.draw_order.extend(self.tilemaps.keys().copied());
+        let tilemaps = &self.tilemaps;
+        self.draw_order
+            .sort_by_key(|entity| (tilemaps[entity].layer, *entity));
+    }
+
+    // Draws the chunks of tilemaps on `layer` overlapping `view`, given as world space min and
+    // max corners, with normal maps at group 2. Returns how many were drawn.
+    pub fn draw(
+        &self,
+        pass: &mut wgpu::RenderPass,
+        textures: &WorldRenderBatch,
+        view: [[f32; 2]; 2],
+        layer: RenderLayer,
+    ) -> u32 {
+        let mut drawn = 0;
+        for entity in self.draw_order.iter() {
+            if self.tilemaps[entity].layer != layer {
+                continue;
+            }
+            for chunk in self.tilemaps[entity].chunks.iter() {
+                let [min, max] = chunk.bounds;
+                if max[0] < view[0][0]
+                    || min[0] > view[1][0]
+                    || max[1] < view[0][1]
+                    || min[1] > view[1][1]
+                {
+                    continue;
+                }
+                let (bind_group, normals) = match (
+                    textures.bind_group(&chunk.texture),
+                    textures.normal_bind_group(&chunk.texture),
+                ) {
+                    (Some(bind_group), Some(normals)) => (bind_group, normals),
+                    _ => continue,
+                };
+                pass.set_bind_group(0, bind_group, &[]);
+                pass.set_bind_group(2, normals, &[]);
+                chunk.mesh.bind(pass);
+                pass.draw_indexed(0..chunk.mesh.index_count, 0, 0..1);
+                drawn += 1;
+            }
+        }
+        drawn
+    }
+}
+
+fn build_chunks(
+    tilemap: &TilemapComponent,
+    origin: [f32; 2],
+    device: &wgpu::Device,
+) -> Vec<TilemapChunk> {
+    let [tile_w, tile_h] = tilemap.tile_world_size;
+    let mut chunks = Vec::new();
+
+    for layer in tilemap.layers.iter() {
+        for chunk_y in (0..layer.height).step_by(CHUNK_SIZE as usize) {
+            for chunk_x in (0..layer.width).step_by(CHUNK_SIZE as usize) {
+                let mut vertices: Vec<TextureVertex> = Vec::new();
+                let mut indices: Vec<u32> = Vec::new();
+
+                for y in chunk_y..(chunk_y + CHUNK_SIZE).min(layer.height) {
+                    for x in chunk_x..(chunk_x + CHUNK_SIZE).min(layer.width) {
+                        let uv = match tilemap.tileset.tile_uv(layer.tile(x, y)) {
+                            Some(uv) => uv,
+                            None => continue,
+                        };
+                        let left = origin[0] + x as f32 * tile_w;
+                        let top = origin[1] - y as f32 * tile_h;
+                        let corners = [
+                            [left, top],
+                            [left + tile_w, top],
+                            [left + tile_w, top - tile_h],
+                            [left, top - tile_h],
+                        ];
+
+                        let base = vertices.len() as u32;
+                        vertices.extend(corners.iter().zip(uv.iter()).map(|(position, uv)| {
+                            TextureVertex {
+                                position: *position,
+                                tex_coords: *uv,
+                            }
+                        }));
+                        indices.extend([0, 1, 2, 2, 3, 0].map(|i| base + i));
+                    }
+                }
+
+                if indices.is_empty() {
+                    continue;
+                }
+                let columns = CHUNK_SIZE.min(layer.width - chunk_x) as f32;
+                let rows = CHUNK_SIZE.min(layer.height - chunk_y) as f32;
+                let left = origin[0] + chunk_x as f32 * tile_w;
+                let top = origin[1] - chunk_y as f32 * tile_h;
+
+                chunks.push(TilemapChunk {
+                    texture: tilemap.tileset.texture,
+                    mesh: GpuMesh::new(
+                        device,
+                        "Tilemap Chunk",
+                        bytemuck::cast_slice(&vertices),
+                        vertices.len(),
+                        &indices,
+                    ),
+                    bounds: [[left, top - rows * tile_h], [left + columns * tile_w, top]],
+                });
+            }
+        }
+    }
+    chunks
+}
@@ -116,3 +116,211 @@ impl DebugInstanceVertex {
         }
     }
 }
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct SpriteInstance {
+    pub position: [f32; 2],          // @location(1)
+    pub size: [f32; 2],              // @location(2)
+    pub uv_top_left: [f32; 2],       // @location(3)
+    pub uv_bottom_right: [f32; 2],   // @location(4)
+    pub tint: [f32; 4],              // @location(5)
+    pub flash: [f32; 4],             // @location(6), added colour with its strength in alpha
+    pub color_matrix: [[f32; 4]; 4], // @location(7..=10), one row per output channel
+    pub color_offset: [f32; 4],      // @location(11)
+    pub pivot_rotation: [f32; 4],    // @location(12), pivot from `position`, then cos and sin
+}
+
+impl SpriteInstance {
+    pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        use std::mem;
+        wgpu::VertexBufferLayout {
+            array_stride: mem::size_of::<SpriteInstance>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &[
+                // instance_pos @ location(1)
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                // instance_size @ location(2)
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                // uv_top_left @ location(3)
+                wgpu::VertexAttribute {
+                    offset: (mem::size_of::<[f32; 2]>() * 2) as wgpu::BufferAddress,
+                    shader_location: 3,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                // uv_bottom_right @ location(4)
+                wgpu::VertexAttribute {
+                    offset: (mem::size_of::<[f32; 2]>() * 3) as wgpu::BufferAddress,
+                    shader_location: 4,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                // tint @ location(5)
+                wgpu::VertexAttribute {
+                    offset: (mem::size_of::<[f32; 2]>() * 4) as wgpu::BufferAddress,
+                    shader_location: 5,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                // flash @ location(6)
+                wgpu::VertexAttribute {
+                    offset: (mem::size_of::<[f32; 2]>() * 4 + mem::size_of::<[f32; 4]>())
+                        as wgpu::BufferAddress,
+                    shader_location: 6,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                // color matrix rows @ location(7) to location(10)
+                wgpu::VertexAttribute {
+                    offset: (mem::size_of::<[f32; 2]>() * 4 + mem::size_of::<[f32; 4]>() * 2)
+                        as wgpu::BufferAddress,
+                    shader_location: 7,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: (mem::size_of::<[f32; 2]>() * 4 + mem::size_of::<[f32; 4]>() * 3)
+                        as wgpu::BufferAddress,
+                    shader_location: 8,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: (mem::size_of::<[f32; 2]>() * 4 + mem::size_of::<[f32; 4]>() * 4)
+                        as wgpu::BufferAddress,
+                    shader_location: 9,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: (mem::size_of::<[f32; 2]>() * 4 + mem::size_of::<[f32; 4]>() * 5)
+                        as wgpu::BufferAddress,
+                    shader_location: 10,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                // color offset @ location(11)
+                wgpu::VertexAttribute {
+                    offset: (mem::size_of::<[f32; 2]>() * 4 + mem::size_of::<[f32; 4]>() * 6)
+                        as wgpu::BufferAddress,
+                    shader_location: 11,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                // pivot and rotation @ location(12)
+                wgpu::VertexAttribute {
+                    offset: (mem::size_of::<[f32; 2]>() * 4 + mem::size_of::<[f32; 4]>() * 7)
+                        as wgpu::BufferAddress,
+                    shader_location: 12,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+            ],
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct ParticleInstance {
+    pub position: [f32; 2],        // @location(1)
+    pub size: [f32; 2],            // @location(2)
+    pub rotation: [f32; 2],        // @location(3), cos and sin
+    pub uv_top_left: [f32; 2],     // @location(4)
+    pub uv_bottom_right: [f32; 2], // @location(5)
+    pub color: [f32; 4],           // @location(6)
+}
+
+impl ParticleInstance {
+    pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        use std::mem;
+        wgpu::VertexBufferLayout {
+            array_stride: mem::size_of::<ParticleInstance>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &[
+                // position @ location(1)
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                // size @ location(2)
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                // rotation @ location(3)
+                wgpu::VertexAttribute {
+                    offset: (mem::size_of::<[f32; 2]>() * 2) as wgpu::BufferAddress,
+                    shader_location: 3,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                // uv_top_left @ location(4)
+                wgpu::VertexAttribute {
+                    offset: (mem::size_of::<[f32; 2]>() * 3) as wgpu::BufferAddress,
+                    shader_location: 4,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                // uv_bottom_right @ location(5)
+                wgpu::VertexAttribute {
+                    offset: (mem::size_of::<[f32; 2]>() * 4) as wgpu::BufferAddress,
+                    shader_location: 5,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                // color @ location(6)
+                wgpu::VertexAttribute {
+                    offset: (mem::size_of::<[f32; 2]>() * 5) as wgpu::BufferAddress,
+                    shader_location: 6,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+            ],
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct LightInstance {
+    pub position: [f32; 2], // @location(1)
+    pub params: [f32; 4],   // @location(2), radius, falloff, height, unused
+    pub color: [f32; 4],    // @location(3), already scaled by intensity
+    pub spot: [f32; 4],     // @location(4), direction, then cosines of the outer and inner cone
+}
+
+impl LightInstance {
+    pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        use std::mem;
+        wgpu::VertexBufferLayout {
+            array_stride: mem::size_of::<LightInstance>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &[
+                // position @ location(1)
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                // params @ location(2)
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                // color @ location(3)
+                wgpu::VertexAttribute {
+                    offset: (mem::size_of::<[f32; 2]>() + mem::size_of::<[f32; 4]>())
+                        as wgpu::BufferAddress,
+                    shader_location: 3,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                // spot @ location(4)
+                wgpu::VertexAttribute {
+                    offset: (mem::size_of::<[f32; 2]>() + mem::size_of::<[f32; 4]>() * 2)
+                        as wgpu::BufferAddress,
+                    shader_location: 4,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+            ],
+        }
+    }
+}
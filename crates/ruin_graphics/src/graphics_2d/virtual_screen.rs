@@ -0,0 +1,149 @@
+use ruin_camera::{ScaleMode, Viewport};
+use wgpu::{
+    include_wgsl, AddressMode, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
+    BindingResource, CommandEncoder, Device, Extent3d, FilterMode, LoadOp, Operations, RenderPass,
+    RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline, SamplerDescriptor, StoreOp,
+    TextureFormat, TextureUsages, TextureView, TextureViewDescriptor,
+};
+
+use crate::graphics_2d::shape_pipelines::create_2d_pipeline;
+
+// The world is drawn into this texture at the virtual resolution, then scaled up into the
+// window with nearest filtering so every virtual pixel stays a crisp block.
+pub struct VirtualScreen {
+    pub size: [u32; 2],
+    pub mode: ScaleMode,
+    view: TextureView,
+    bind_group: BindGroup,
+    pipeline: RenderPipeline,
+    viewport: Viewport,
+}
+
+impl VirtualScreen {
+    pub fn new(
+        device: &Device,
+        size: [u32; 2],
+        mode: ScaleMode,
+        format: TextureFormat,
+        window_size: [u32; 2],
+        texture_bind_group_layout: &BindGroupLayout,
+    ) -> Self {
+        let size = [size[0].max(1), size[1].max(1)];
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Virtual Screen"),
+            size: Extent3d {
+                width: size[0],
+                height: size[1],
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            label: Some("Virtual Screen Sampler"),
+            address_mode_u: AddressMode::ClampToEdge,
+            address_mode_v: AddressMode::ClampToEdge,
+            mag_filter: FilterMode::Nearest,
+            min_filter: FilterMode::Nearest,
+            ..Default::default()
+        });
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            layout: texture_bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(&view),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(&sampler),
+                },
+            ],
+            label: Some("Virtual Screen Bind Group"),
+        });
+
+        let shader = device.create_shader_module(include_wgsl!("shaders/2d_upscale.wgsl"));
+        let pipeline = create_2d_pipeline(
+            "Upscale Pipeline",
+            device,
+            format,
+            &shader,
+            &[],
+            &Vec::from([texture_bind_group_layout]),
+            None,
+        );
+
+        Self {
+            size,
+            mode,
+            view,
+            bind_group,
+            pipeline,
+            viewport: mode.viewport(size, window_size),
+        }
+    }
+
+    pub fn view(&self) -> &TextureView {
+        &self.view
+    }
+
+    pub fn resize(&mut self, window_size: [u32; 2]) {
+        self.viewport = self.mode.viewport(self.size, window_size);
+    }
+
+    // Keeps passes drawing over the upscaled screen, like the canvas, inside its bars.
+    pub fn set_viewport(&self, pass: &mut RenderPass) {
+        let Viewport {
+            x,
+            y,
+            width,
+            height,
+        } = self.viewport;
+        pass.set_viewport(x, y, width, height, 0.0, 1.0);
+    }
+
+    // The window pixels the virtual screen is scaled up into.
+    pub fn viewport_size(&self) -> [u32; 2] {
+        [
+            self.viewport.width.round() as u32,
+            self.viewport.height.round() as u32,
+        ]
+    }
+
+    // Window pixels to virtual pixels.
+    pub fn window_to_virtual(&self, position: [f32; 2]) -> [f32; 2] {
+        self.viewport.to_screen(position, self.size)
+    }
+
+    // Clears `view` to the bar colour and draws the virtual screen into the viewport.
+    pub fn present(&self, encoder: &mut CommandEncoder, view: &TextureView) {
+        let mut pass = encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("Upscale Pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Clear(wgpu::Color::BLACK),
+                    store: StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+        self.set_viewport(&mut pass);
+        self.draw(&mut pass);
+    }
+
+    // Draws the virtual screen over the whole of whatever `pass` draws into.
+    pub fn draw(&self, pass: &mut RenderPass) {
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+}
@@ -1,32 +1,66 @@
 use std::collections::HashMap;
 
-use cgmath::Vector2;
 use ruin_assets::{Handle, ImageTexture};
 use ruin_ecs::world::RenderElement2D;
+use wgpu::util::DeviceExt;
 
-use crate::graphics_2d::{shape_tesselation::TessellatedShape2D, vertex::TextureVertex};
+use crate::graphics_2d::{
+    gpu_buffer::GrowableBuffer,
+    shape_tesselation::TessellatedShape2D,
+    vertex::{SpriteInstance, Vertex},
+};
 
+// Sprites are drawn as instances of one unit quad, a draw call per run of sprites sharing a
+// texture.
 #[derive(Debug, Clone)]
 pub struct WorldRenderBatch {
-    current_vertex_buffer_offset: u64,
-    current_index_buffer_offset: u64,
-    vertex_count_offset: u16,
-    batched_texture_vertices: Vec<TextureVertex>,
-    batched_indices: Vec<u16>,
+    quad_vertex_buffer: wgpu::Buffer,
+    quad_index_buffer: wgpu::Buffer,
+    quad_index_count: u32,
+    batched_instances: Vec<SpriteInstance>,
     previous_texture: Option<Handle<ImageTexture>>,
     bind_group_cache: HashMap<Handle<ImageTexture>, wgpu::BindGroup>,
+    // keyed by the texture they're the normals of
+    normal_bind_groups: HashMap<Handle<ImageTexture>, wgpu::BindGroup>,
+    // for textures without normals
+    flat_normal_map: Option<Handle<ImageTexture>>,
+    // the group normal maps are bound at, None for pipelines that don't take them
+    pub normal_group: Option<u32>,
+    pub draw_calls: u32,
+    pub sprites: u32,
 }
 
 impl WorldRenderBatch {
-    pub fn new() -> Self {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let quad = TessellatedShape2D::rect(0.5, 0.5);
+        let vertices: Vec<Vertex> = quad
+            .vertices
+            .iter()
+            .map(|v| Vertex {
+                position: [v.x, v.y],
+            })
+            .collect();
+
         Self {
-            current_vertex_buffer_offset: 0,
-            current_index_buffer_offset: 0,
-            vertex_count_offset: 0,
-            batched_texture_vertices: Vec::new(),
-            batched_indices: Vec::new(),
+            quad_vertex_buffer: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Sprite Quad Vertex Buffer"),
+                contents: bytemuck::cast_slice(&vertices),
+                usage: wgpu::BufferUsages::VERTEX,
+            }),
+            quad_index_buffer: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Sprite Quad Index Buffer"),
+                contents: bytemuck::cast_slice(&quad.indices),
+                usage: wgpu::BufferUsages::INDEX,
+            }),
+            quad_index_count: quad.indices.len() as u32,
+            batched_instances: Vec::new(),
             previous_texture: None,
             bind_group_cache: HashMap::new(),
+            normal_bind_groups: HashMap::new(),
+            flat_normal_map: None,
+            normal_group: None,
+            draw_calls: 0,
+            sprites: 0,
         }
     }
 
@@ -39,129 +73,153 @@ impl WorldRenderBatch {
     ) {
         self.bind_group_cache.insert(
             handle,
-            device.create_bind_group(&wgpu::BindGroupDescriptor {
-                layout: texture_bind_group_layout,
-                entries: &[
-                    wgpu::BindGroupEntry {
-                        binding: 0,
-                        resource: wgpu::BindingResource::TextureView(texture.view()),
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 1,
-                        resource: wgpu::BindingResource::Sampler(texture.sampler()),
-                    },
-                ],
-                label: Some("Texture Bind Group"),
-            }),
+            texture_bind_group(texture, device, texture_bind_group_layout),
         );
     }
 
+    pub fn remove_texture(&mut self, handle: &Handle<ImageTexture>) {
+        self.bind_group_cache.remove(handle);
+        self.normal_bind_groups.remove(handle);
+    }
+
+    pub fn bind_group(&self, handle: &Handle<ImageTexture>) -> Option<&wgpu::BindGroup> {
+        self.bind_group_cache.get(handle)
+    }
+
+    // `normals` lines up with `handle`'s texture, so sprites sample it with the same UVs.
+    pub fn add_normal_map(
+        &mut self,
+        handle: Handle<ImageTexture>,
+        normals: &ImageTexture,
+        device: &wgpu::Device,
+        texture_bind_group_layout: &wgpu::BindGroupLayout,
+    ) {
+        self.normal_bind_groups.insert(
+            handle,
+            texture_bind_group(normals, device, texture_bind_group_layout),
+        );
+    }
+
+    pub fn remove_normal_map(&mut self, handle: &Handle<ImageTexture>) {
+        self.normal_bind_groups.remove(handle);
+    }
+
+    // `flat` is a texture already added with add_texture.
+    pub fn set_flat_normal_map(&mut self, flat: Handle<ImageTexture>) {
+        self.flat_normal_map = Some(flat);
+    }
+
+    pub fn normal_bind_group(&self, handle: &Handle<ImageTexture>) -> Option<&wgpu::BindGroup> {
+        self.normal_bind_groups.get(handle).or_else(|| {
+            self.flat_normal_map
+                .and_then(|flat| self.bind_group_cache.get(&flat))
+        })
+    }
+
     pub fn enqueue_next_texture(
         &mut self,
         element: &RenderElement2D,
+        device: &wgpu::Device,
         queue: &mut wgpu::Queue,
         pass: &mut wgpu::RenderPass,
-        vertex_buffer: &mut wgpu::Buffer,
-        index_buffer: &mut wgpu::Buffer,
+        instance_buffer: &mut GrowableBuffer,
     ) {
         let should_flush_batch = self.previous_texture.is_some()
             && self.previous_texture.unwrap() != element.image_texture;
         if should_flush_batch {
-            self.flush_batch(queue, pass, vertex_buffer, index_buffer);
+            self.flush_batch(device, queue, pass, instance_buffer);
         }
 
-        let mut shape = TessellatedShape2D::from(
-            &element.shape.scale(Vector2 {
-                x: element.size[0],
-                y: element.size[1],
-            }),
-            100,
-        );
-        shape.recenter(Vector2 {
-            x: element.position[0],
-            y: element.position[1],
+        let half_extents = element.shape.half_extents();
+        self.batched_instances.push(SpriteInstance {
+            position: element.position,
+            size: [
+                half_extents.x * 2.0 * element.size[0],
+                half_extents.y * 2.0 * element.size[1],
+            ],
+            // uv_coords run top-left, top-right, bottom-right, bottom-left
+            uv_top_left: element.uv_coords[0],
+            uv_bottom_right: element.uv_coords[2],
+            tint: element.tint,
+            flash: element.flash,
+            color_matrix: element.color_matrix.rows,
+            color_offset: element.color_matrix.offset,
+            pivot_rotation: [
+                element.pivot[0],
+                element.pivot[1],
+                element.rotation.cos(),
+                element.rotation.sin(),
+            ],
         });
-
-        let vertices = shape.into_tex(element.uv_coords);
-        let indices: Vec<u16> = shape
-            .indices
-            .iter()
-            .map(|i| i + self.vertex_count_offset)
-            .collect();
-        self.batched_texture_vertices.extend(&vertices);
-        self.batched_indices.extend(indices);
-        self.vertex_count_offset += vertices.len() as u16;
         self.previous_texture = Some(element.image_texture);
     }
 
     pub fn flush_batch(
         &mut self,
+        device: &wgpu::Device,
         queue: &mut wgpu::Queue,
         pass: &mut wgpu::RenderPass,
-        vertex_buffer: &mut wgpu::Buffer,
-        index_buffer: &mut wgpu::Buffer,
+        instance_buffer: &mut GrowableBuffer,
     ) {
-        if self.previous_texture.is_none() {
-            return;
-        }
-        pass.set_bind_group(
-            0,
-            &*self
-                .bind_group_cache
-                .get(&self.previous_texture.unwrap())
-                .unwrap(),
-            &[],
-        );
-        queue.write_buffer(
-            &vertex_buffer,
-            self.current_vertex_buffer_offset as wgpu::BufferAddress,
-            bytemuck::cast_slice(&self.batched_texture_vertices),
-        );
-
-        queue.write_buffer(
-            &index_buffer,
-            self.current_index_buffer_offset as wgpu::BufferAddress,
-            bytemuck::cast_slice(&self.batched_indices),
-        );
+        let texture = match self.previous_texture.take() {
+            Some(texture) => texture,
+            None => return,
+        };
 
-        let vertex_slice_size = (self.batched_texture_vertices.len()
-            * std::mem::size_of::<TextureVertex>())
-            as wgpu::BufferAddress;
-        let index_slice_size =
-            (self.batched_indices.len() * std::mem::size_of::<u16>()) as wgpu::BufferAddress;
-
-        pass.set_vertex_buffer(
-            0,
-            vertex_buffer.slice(
-                self.current_vertex_buffer_offset as wgpu::BufferAddress
-                    ..(self.current_vertex_buffer_offset + vertex_slice_size)
-                        as wgpu::BufferAddress,
-            ),
-        );
-        pass.set_index_buffer(
-            index_buffer.slice(
-                self.current_index_buffer_offset as wgpu::BufferAddress
-                    ..(self.current_index_buffer_offset + index_slice_size) as wgpu::BufferAddress,
+        let range =
+            instance_buffer.push(device, queue, bytemuck::cast_slice(&self.batched_instances));
+        match range {
+            Some(range) => {
+                pass.set_bind_group(0, &self.bind_group_cache[&texture], &[]);
+                if let (Some(group), Some(normals)) =
+                    (self.normal_group, self.normal_bind_group(&texture))
+                {
+                    pass.set_bind_group(group, normals, &[]);
+                }
+                pass.set_vertex_buffer(0, self.quad_vertex_buffer.slice(..));
+                pass.set_vertex_buffer(1, instance_buffer.buffer().slice(range));
+                pass.set_index_buffer(self.quad_index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+                pass.draw_indexed(
+                    0..self.quad_index_count,
+                    0,
+                    0..self.batched_instances.len() as u32,
+                );
+                self.draw_calls += 1;
+                self.sprites += self.batched_instances.len() as u32;
+            }
+            None => println!(
+                "Too many sprites for one buffer, dropping {}",
+                self.batched_instances.len()
             ),
-            wgpu::IndexFormat::Uint16,
-        );
-        pass.draw_indexed(0..self.batched_indices.len() as u32, 0, 0..1);
-
-        // Increment offsets for the next sprite
-        self.vertex_count_offset = 0;
-        self.current_vertex_buffer_offset += vertex_slice_size;
-        self.current_index_buffer_offset += index_slice_size;
-        self.batched_indices.clear();
-        self.batched_texture_vertices.clear();
-        self.previous_texture = None;
+        }
+        self.batched_instances.clear();
     }
 
     pub fn reset_context(&mut self) {
-        self.current_index_buffer_offset = 0;
-        self.current_vertex_buffer_offset = 0;
-        self.vertex_count_offset = 0;
-        self.batched_texture_vertices.clear();
-        self.batched_indices.clear();
+        self.batched_instances.clear();
         self.previous_texture = None;
+        self.draw_calls = 0;
+        self.sprites = 0;
     }
 }
+
+fn texture_bind_group(
+    texture: &ImageTexture,
+    device: &wgpu::Device,
+    texture_bind_group_layout: &wgpu::BindGroupLayout,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout: texture_bind_group_layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(texture.view()),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(texture.sampler()),
+            },
+        ],
+        label: Some("Texture Bind Group"),
+    })
+}
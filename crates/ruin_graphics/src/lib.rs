@@ -4,21 +4,70 @@ use ruin_canvas::Canvas;
 use ruin_ecs::{physics_2d::PhysicsWorld, world::World};
 use winit::event::WindowEvent;
 
-use ruin_assets::{Handle, ImageTexture};
+use image::DynamicImage;
+use ruin_assets::TextureRegion;
+
+use crate::graphics_2d::PostEffects;
 
 pub struct CameraInfo {
     pub zoom: f32,
     pub position: [f32; 3],
 }
 
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BufferUsage {
+    pub used: u64, // bytes written last frame
+    pub capacity: u64,
+    pub grown: u32, // times the buffer had to be replaced by a bigger one
+}
+
+// Counts from the last rendered frame.
+#[derive(Debug, Clone, Default)]
+pub struct RenderStats {
+    pub draw_calls: u32,
+    pub sprites: u32,
+    pub particles: u32,
+    pub lights: u32,
+    pub tilemap_chunks: u32,
+    pub buffers: Vec<(&'static str, BufferUsage)>,
+}
+
 pub trait Graphics {
     fn render(&mut self, world: &World, canvas: &Canvas, physics: &PhysicsWorld);
     fn resize(&mut self, width: u32, height: u32);
     fn process_camera_event(&mut self, event: &WindowEvent);
     fn set_background(&mut self, color: wgpu::Color);
     fn update_camera(&mut self);
-    fn load_texture_from_path(&mut self, id: &str, path: &str) -> Handle<ImageTexture>;
+    // Where the texture uploaded as `id` is, the missing texture if it failed to load.
+    fn texture_region(&self, id: &str) -> Option<TextureRegion>;
+    // Fully transparent, stands in for textures that are still loading.
+    fn placeholder_region(&self) -> TextureRegion;
+    // Packs decoded pixels into an atlas page, or a texture of their own when too large.
+    fn upload_texture(&mut self, id: &str, image: &DynamicImage) -> anyhow::Result<TextureRegion>;
+    // Shows the missing texture for `id` until it's reloaded or unloaded, rather than loading
+    // it again every time a sprite asks for it.
+    fn set_missing_texture(&mut self, id: &str) -> TextureRegion;
+    // Puts changed pixels behind an uploaded texture, None for ids that were never uploaded.
+    fn reload_texture(&mut self, id: &str, image: &DynamicImage) -> Option<TextureRegion>;
+    // Loads the colour grading LUT at `path` again if it's the one in use.
+    fn reload_lut(&mut self, path: &str);
+    // Writes `image` over `region` in the normal map of its texture. It has to be the size of
+    // the region. Sheets still loading are left alone.
+    fn set_normal_map(&mut self, region: TextureRegion, image: &DynamicImage)
+        -> anyhow::Result<()>;
+    // Textures are held by id, whichever region they end up in. Unloading frees the ones
+    // nobody holds anymore.
+    fn acquire_texture(&mut self, id: &str);
+    fn release_texture(&mut self, id: &str);
+    fn unload_unused_textures(&mut self);
+    // Applied to the game each frame, before the canvas and debug shapes are drawn over it.
+    fn post_effects(&mut self) -> &mut PostEffects;
     fn get_camera_info(&self) -> CameraInfo;
+    fn render_stats(&self) -> RenderStats;
+    // Saves the next frame as a PNG.
+    fn screenshot(&mut self, path: &str);
+    // Window pixels to world units, accounting for the bars around the virtual screen.
+    fn screen_to_world(&self, position: [f32; 2]) -> [f32; 2];
     fn move_camera_for_follow(
         &mut self,
         dt: f32,
@@ -0,0 +1,59 @@
+use std::sync::Arc;
+
+use ruin_assets::DirectorySource;
+use ruin_camera::{Camera2D, Camera2DConfig, ScaleMode};
+use ruin_canvas::Canvas;
+use ruin_ecs::physics_2d::PhysicsWorld;
+use ruin_ecs::world::World;
+use ruin_graphics::graphics_2d::Graphics2D;
+use ruin_graphics::Graphics;
+
+const GREEN: [u8; 4] = [0, 255, 0, 255];
+const RED: [u8; 4] = [255, 0, 0, 255];
+const BLACK: [u8; 4] = [0, 0, 0, 255];
+
+// A 32x32 virtual screen scaled into a 64x32 frame, so integer scaling leaves 16 pixel bars on
+// the left and right. Skipped on machines without any adapter, software ones included.
+#[test]
+fn headless_frame_is_letterboxed() {
+    let camera = Camera2D::new(&Camera2DConfig {
+        zoom: 10.0,
+        initial_position: [0.0, 0.0],
+        look_ahead_smooth_factor: 0.0,
+        look_ahead_distance: 0.0,
+        look_ahead_lerp_speed: 0.0,
+        screen_width: 32.0,
+        screen_height: 32.0,
+    });
+    let renderer = pollster::block_on(Graphics2D::new_headless(
+        [64, 32],
+        camera,
+        [32, 32],
+        ScaleMode::Integer,
+        Arc::new(DirectorySource::new(".")),
+    ));
+    let mut graphics = match renderer {
+        Ok(graphics) => graphics,
+        Err(err) => {
+            println!("Skipping headless render test: {}", err);
+            return;
+        }
+    };
+
+    let world = World::new();
+    let canvas = Canvas::new(32, 32);
+    let physics = PhysicsWorld::new();
+    graphics.set_background(wgpu::Color::GREEN);
+
+    let image = graphics.render_to_image(&world, &canvas, &physics).unwrap();
+    assert_eq!(image.dimensions(), (64, 32));
+    assert_eq!(image.get_pixel(4, 16).0, BLACK);
+    assert_eq!(image.get_pixel(32, 16).0, GREEN);
+    assert_eq!(image.get_pixel(60, 16).0, BLACK);
+
+    // a full strength flash goes through the post effects, inside the bars only
+    graphics.post_effects().flash([1.0, 0.0, 0.0], 1.0);
+    let image = graphics.render_to_image(&world, &canvas, &physics).unwrap();
+    assert_eq!(image.get_pixel(4, 16).0, BLACK);
+    assert_eq!(image.get_pixel(32, 16).0, RED);
+}
@@ -1,20 +1,24 @@
-use std::fs;
+use std::sync::Arc;
 
 use mlua::prelude::*;
+use ruin_assets::AssetSource;
 use ruin_ecs::physics_2d::CollisionPair;
 
 pub struct LuaScriptor {
     pub lua: Lua,
+    source: Arc<dyn AssetSource>,
 }
 
 impl LuaScriptor {
-    pub fn new(lua: Lua) -> Self {
-        Self { lua }
+    pub fn new(lua: Lua, source: Arc<dyn AssetSource>) -> Self {
+        Self { lua, source }
     }
 
     pub fn execute(&mut self, script: &str) -> LuaTable {
         // by convention all lua scripts will be in src/scripts/
-        let lua_code = fs::read_to_string(format!("lua_runtime/scripts/{}.lua", script))
+        let lua_code = self
+            .source
+            .read_to_string(&format!("lua_runtime/scripts/{}.lua", script))
             .expect(&format!("Failed to read {}.lua", script));
         self.lua
             .load(&lua_code)
@@ -38,22 +42,63 @@ pub struct LuaExtendedExecutor {
 }
 
 impl LuaExtendedExecutor {
-    pub fn new(script: &str) -> Self {
+    pub fn new(script: &str, source: Arc<dyn AssetSource>) -> Self {
         let lua = Lua::new();
         let code = r#"
             package.path = package.path .. ";./lua_runtime/?.lua"
             package.path = package.path .. ";./lua_runtime/scripts/?.lua"
             "#;
         let _ = lua.load(code).exec();
-        let path = format!("./lua_runtime/{}.lua", script);
+        Self::install_asset_source(&lua, source.clone())
+            .expect("Unable to route Lua through the asset source");
+
+        let path = format!("lua_runtime/{}.lua", script);
         println!("path: {:?}", path);
-        let contents = fs::read_to_string(&path).expect("Unable to read Lua script file");
+        let contents = source
+            .read_to_string(&path)
+            .expect("Unable to read Lua script file");
         lua.load(&contents)
+            .set_name(format!("@{}", path))
             .exec()
             .expect("Unable to execute lua file.");
         Self { lua }
     }
 
+    // Scripts and the data they read (e.g. aseprite json) come from the asset source, so they
+    // load the same from loose files or a packed archive. Adds a `read_asset(id)` global and a
+    // `require` searcher ahead of package.path.
+    fn install_asset_source(lua: &Lua, source: Arc<dyn AssetSource>) -> LuaResult<()> {
+        let read_source = source.clone();
+        let read_asset =
+            lua.create_function(move |lua, id: String| match read_source.read(&id) {
+                Ok(bytes) => Ok((Some(lua.create_string(&bytes)?), None)),
+                Err(err) => Ok((None, Some(err.to_string()))),
+            })?;
+        lua.globals().set("read_asset", read_asset)?;
+
+        let searcher = lua.create_function(move |lua, name: String| {
+            let path = name.replace('.', "/");
+            for id in [
+                format!("lua_runtime/scripts/{}.lua", path),
+                format!("lua_runtime/{}.lua", path),
+            ] {
+                if let Ok(code) = source.read_to_string(&id) {
+                    let chunk = lua
+                        .load(code)
+                        .set_name(format!("@{}", id))
+                        .into_function()?;
+                    return Ok(LuaValue::Function(chunk));
+                }
+            }
+            let message = format!("\n\tno asset 'lua_runtime/scripts/{}.lua'", path);
+            Ok(LuaValue::String(lua.create_string(message)?))
+        })?;
+        let searchers: LuaTable = lua.globals().get::<LuaTable>("package")?.get("searchers")?;
+        // right after package.preload
+        searchers.raw_insert(2, searcher)?;
+        Ok(())
+    }
+
     pub fn create_table(&self) -> mlua::Table {
         return self.lua.create_table().unwrap();
     }
@@ -0,0 +1,58 @@
+use std::fs;
+use std::path::Path;
+
+use ruin_assets::{ArchiveWriter, Compression, DEFAULT_ARCHIVE};
+
+// Everything the game reads at runtime, relative to the game root.
+static PACKED_DIRS: [&str; 2] = ["assets", "lua_runtime"];
+// Editor source files that only the export step needs.
+static SKIPPED_EXTENSIONS: [&str; 1] = ["aseprite"];
+
+// Bundles assets and scripts into a single archive that the engine reads in place of the
+// loose files. Run from the game root:
+//   ruin_packer [output] [--compress]
+fn main() -> anyhow::Result<()> {
+    let mut output = DEFAULT_ARCHIVE.to_string();
+    let mut compression = Compression::None;
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--compress" => compression = Compression::Deflate,
+            _ => output = arg,
+        }
+    }
+
+    let mut writer = ArchiveWriter::new(compression);
+    for dir in PACKED_DIRS {
+        add_dir(&mut writer, Path::new(dir))?;
+    }
+
+    let count = writer.len();
+    writer.write(&output)?;
+    println!("Packed {} files into {}", count, output);
+    Ok(())
+}
+
+fn add_dir(writer: &mut ArchiveWriter, dir: &Path) -> anyhow::Result<()> {
+    let mut entries = fs::read_dir(dir)?.collect::<Result<Vec<_>, _>>()?;
+    // keeps archives byte for byte reproducible
+    entries.sort_by_key(|entry| entry.path());
+
+    for entry in entries {
+        let path = entry.path();
+        if path.is_dir() {
+            add_dir(writer, &path)?;
+            continue;
+        }
+
+        let skipped = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| SKIPPED_EXTENSIONS.contains(&ext));
+        if skipped {
+            continue;
+        }
+
+        writer.add(&path.to_string_lossy(), fs::read(&path)?);
+    }
+    Ok(())
+}
@@ -14,20 +14,56 @@ end
 local function update(dt)
 end
 
+-- an asset failed to load (already logged), sprites using it draw a magenta checkerboard
+---@diagnostic disable-next-line: unused-local
+local function on_asset_error(asset_id, message)
+end
+
+-- something from a level's object layer, see engine.load_level. x and y are its centre in
+-- world units, properties holds the custom properties set in the editor
+---@diagnostic disable-next-line: unused-local
+local function spawn_level_object(object)
+end
+
 local function load()
   return {
     assets = {},
   }
 end
 
+-- called after scripts were hot reloaded, old_state holds the WORLD and CONFIG from before
+---@diagnostic disable-next-line: unused-local
+local function on_reload(old_state)
+end
+
 local ruin = {
   on_collision = on_collision,
   handle_input = handle_input,
   after_physics = after_physics,
   update = update,
   load = load,
+  on_reload = on_reload,
+  on_asset_error = on_asset_error,
+  spawn_level_object = spawn_level_object,
 }
 
+local ruin_defaults = {}
+for name, fn in pairs(ruin) do
+  ruin_defaults[name] = fn
+end
+
+-- copies plain data from the old state into the freshly built one, functions stay new
+local function carry_over(old, new)
+  if type(old) ~= "table" or type(new) ~= "table" then
+    return
+  end
+  for k, v in pairs(old) do
+    if type(v) ~= "function" then
+      new[k] = v
+    end
+  end
+end
+
 --[[
 -- ENGINE_ functions are invoked by the rust engine.
 -- These methods should not be leveraged or manipulated directly by Lua scripts.
@@ -53,6 +89,132 @@ function ENGINE_update(dt)
   ruin.update(dt)
 end
 
+-- Every module required after this file is gameplay code. A change reloads the changed
+-- modules and every module that required them, which usually ends at scripts.main. Changes
+-- to ruin.lua itself still need a restart. On errors the previous code and state are
+-- restored and the message is returned to the engine.
+local builtin_modules = {}
+
+-- module name -> the modules that required it while they were loading
+local dependents = {}
+local loading = {}
+
+local base_require = require
+function require(name)
+  local parent = loading[#loading]
+  if parent then
+    dependents[name] = dependents[name] or {}
+    dependents[name][parent] = true
+  end
+  table.insert(loading, name)
+  local ok, module, loader_data = pcall(base_require, name)
+  table.remove(loading)
+  if not ok then
+    error(module, 0)
+  end
+  return module, loader_data
+end
+
+-- changed ids are relative to lua_runtime/scripts, so systems/physics.lua was required as
+-- either systems.physics or scripts.systems.physics
+local function stale_modules(changed_ids)
+  local queue = {}
+  for _, id in ipairs(changed_ids) do
+    local name = id:gsub("%.lua$", ""):gsub("/", ".")
+    for _, candidate in ipairs({ name, "scripts." .. name }) do
+      if package.loaded[candidate] ~= nil and not builtin_modules[candidate] then
+        table.insert(queue, candidate)
+      end
+    end
+  end
+
+  local stale = {}
+  while #queue > 0 do
+    local name = table.remove(queue)
+    if not stale[name] then
+      stale[name] = true
+      for dependent in pairs(dependents[name] or {}) do
+        table.insert(queue, dependent)
+      end
+    end
+  end
+  return stale
+end
+
+function ENGINE_reload_scripts(changed_ids)
+  local stale = stale_modules(changed_ids)
+  if next(stale) == nil then
+    return nil
+  end
+
+  local old_loaded, old_ruin = {}, {}
+  for name in pairs(stale) do
+    old_loaded[name] = package.loaded[name]
+    package.loaded[name] = nil
+  end
+  for name, fn in pairs(ruin) do
+    old_ruin[name] = fn
+  end
+  -- main sets the callbacks again as it runs
+  if stale["scripts.main"] then
+    for name in pairs(ruin) do
+      ruin[name] = ruin_defaults[name]
+    end
+  end
+  local old_globals = {}
+  for name, value in pairs(_G) do
+    old_globals[name] = value
+  end
+  local old_state = { WORLD = WORLD, CONFIG = CONFIG }
+
+  local function restore()
+    for name, module in pairs(old_loaded) do
+      package.loaded[name] = module
+    end
+    for name, fn in pairs(old_ruin) do
+      ruin[name] = fn
+    end
+    for name in pairs(_G) do
+      if old_globals[name] == nil then
+        _G[name] = nil
+      end
+    end
+    for name, value in pairs(old_globals) do
+      _G[name] = value
+    end
+  end
+
+  for name in pairs(stale) do
+    local ok, err = pcall(require, name)
+    if not ok then
+      restore()
+      return tostring(err)
+    end
+  end
+
+  carry_over(old_state.WORLD, WORLD)
+  carry_over(old_state.CONFIG, CONFIG)
+
+  local ok, err = pcall(ruin.on_reload, old_state)
+  if not ok then
+    restore()
+    return tostring(err)
+  end
+  return nil
+end
+
+function ENGINE_asset_error(asset_id, message)
+  ruin.on_asset_error(asset_id, message)
+end
+
+function ENGINE_spawn_level_object(object)
+  ruin.spawn_level_object(object)
+end
+
+function ENGINE_reload_animations(asset_id)
+  return require("aseprite_parser").reload(asset_id)
+end
+
 --[[
 -- ruin table functions are designed to be overriden by Lua scripts.
 --]]
@@ -61,4 +223,8 @@ end
 -- scripts may override any function defined in ruin global
 _G.ruin = ruin
 
+for name in pairs(package.loaded) do
+  builtin_modules[name] = true
+end
+
 require("scripts.main")
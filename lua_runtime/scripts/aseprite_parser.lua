@@ -1,5 +1,14 @@
 local json = require("json") -- adapt to your preferred JSON library
 
+-- json asset id -> { source key -> { args, anim } }, so changed files can be parsed again.
+-- Kept in a global so reloading scripts doesn't forget animations parsed before it.
+LOADED_ANIMATIONS = LOADED_ANIMATIONS or {}
+local loaded_animations = LOADED_ANIMATIONS
+
+local function asset_id(p)
+	return (p:gsub("/%./", "/"):gsub("/+", "/"):gsub("^%./", ""):gsub("^/", ""))
+end
+
 local function StatefulUiBuilder()
 	local ui = {
 		sprite = "",
@@ -34,8 +43,6 @@ local function AnimationBuilder()
 		sprite = "",
 		tile_height = 32,
 		tile_width = 32,
-		sprite_sheet_height = 0,
-		sprite_sheet_width = 0,
 		frames = {},
 		hitboxes = {},
 		hurtboxes = {},
@@ -50,9 +57,14 @@ local function AnimationBuilder()
 		return builder
 	end
 
-	function builder:set_layout(w, h)
-		anim.sprite_sheet_width = w
-		anim.sprite_sheet_height = h
+	function builder:set_source(s)
+		anim.source = s
+		return builder
+	end
+
+	function builder:set_tile_size(w, h)
+		anim.tile_width = w
+		anim.tile_height = h
 		return builder
 	end
 
@@ -95,29 +107,36 @@ local function AnimationBuilder()
 	return builder
 end
 
+-- The engine's asset server parses the json, so it knows the sheet is built from its png and
+-- reloads both together. Frames come back as a list in the json's order.
+local function load_sprite_sheet(json_id)
+	local data, err = engine.load_sprite_sheet(json_id)
+	if not data then
+		return nil, ("cannot load “%s”: %s"):format(json_id, err)
+	end
+	return data
+end
+
 local function load_image_data(path, file_name)
-	local json_path = "assets/" .. path .. file_name .. ".json"
-	local file, io_err = io.open(json_path, "r")
-	if not file then
-		return nil, ("cannot open “%s”: %s"):format(json_path, io_err)
-	end
-	local raw = file:read("*a")
-	file:close()
-	local data, pos, decode_err = json.decode(raw)
+	local json_id = asset_id(path .. file_name .. ".json")
+	local data, err = load_sprite_sheet(json_id)
 	if not data then
-		return nil, ("JSON error in “%s” at byte %d: %s"):format(json_path, pos or 0, decode_err)
+		return nil, err
 	end
 
-	local frames = data.frames
-	if not frames then
-		return nil, ("no frames found in “%s”"):format(json_path)
+	if #data.frames == 0 then
+		return nil, ("no frames found in “%s”"):format(json_id)
+	end
+	local frames = {}
+	for _, frame in ipairs(data.frames) do
+		frames[frame.name] = frame
 	end
 
 	return {
 		frames = frames,
 		slices = data.meta.slices,
 		size = data.meta.size,
-		image = data.meta.image, -- TODO: This will need to change for texture atlas when we map to that for UVs instead
+		image = data.image, -- TODO: This will need to change for texture atlas when we map to that for UVs instead
 	}
 end
 
@@ -157,25 +176,18 @@ local function load_aseprite_ui_texture_atlas(path, file_name, animations)
 	return { animations = results, texture_w = data.size.w, texture_h = data.size.h }
 end
 
-local function load_aseprite_animation(animation_name, path, json_file, with_transparency)
-	local json_path = "assets/" .. path .. json_file
-	local file, io_err = io.open(json_path, "r")
-	if not file then
-		return nil, ("cannot open “%s”: %s"):format(json_path, io_err)
+local function build_aseprite_animation(animation_name, path, json_file, with_transparency)
+	local json_id = asset_id(path .. json_file)
+	local data, err = load_sprite_sheet(json_id)
+	if not data then
+		return nil, err
 	end
-	local raw = file:read("*a")
-	file:close()
 
 	local builder = AnimationBuilder()
 
-	local data, pos, decode_err = json.decode(raw)
-	if not data then
-		return nil, ("JSON error in “%s” at byte %d: %s"):format(json_path, pos or 0, decode_err)
-	end
-
 	local frames = data.frames
-	if not (frames and #frames > 0) then
-		return nil, ("no frames found in “%s”"):format(json_path)
+	if #frames == 0 then
+		return nil, ("no frames found in “%s”"):format(json_id)
 	end
 
 	local looped = true
@@ -189,26 +201,48 @@ local function load_aseprite_animation(animation_name, path, json_file, with_tra
 		end
 	end
 
-	local fw, fh = frames[1].frame.w, frames[1].frame.h
+	local source = frames[1].sourceSize or frames[1].frame
 	builder
-			:set_sprite(path .. data.meta.image)
-			:set_layout(data.meta.size.w // fw, data.meta.size.h // fh)
+			:set_sprite(data.image)
+			:set_source(json_id .. ":" .. animation_name)
+			:set_tile_size(source.w, source.h)
 			:loop(looped)
 			:transparency(with_transparency == true)
 
+	-- an optional "pivot" slice anchors every frame, otherwise frames pivot on their center
+	local pivot = nil
+	local slices = data.meta.slices
+	if slices and #slices > 0 then
+		for _, s in ipairs(slices) do
+			if s.name == "pivot" and s.keys[1] then
+				local bounds = s.keys[1].bounds
+				local p = s.keys[1].pivot or { x = bounds.w / 2, y = bounds.h / 2 }
+				pivot = { x = bounds.x + p.x, y = bounds.y + p.y }
+			end
+		end
+	end
+
 	for _, fr in ipairs(frames) do
-		local gx = fr.frame.x // fw -- column index in sheet grid
-		local gy = fr.frame.y // fh -- row    index
+		-- pixel rect in the sheet, plus where it sits in the untrimmed frame
+		local trim = fr.spriteSourceSize or { x = 0, y = 0 }
+		local size = fr.sourceSize or fr.frame
 		builder:add_frame(
 			{
-				x = gx,
-				y = gy,
+				x = fr.frame.x,
+				y = fr.frame.y,
+				w = fr.frame.w,
+				h = fr.frame.h,
+				source_w = size.w,
+				source_h = size.h,
+				trim_x = trim.x,
+				trim_y = trim.y,
+				pivot_x = pivot and pivot.x,
+				pivot_y = pivot and pivot.y,
 				duration = (fr.duration or 100) / 1000, -- ms → seconds
 			}
 		)
 	end
 
-	local slices = data.meta.slices
 	if slices and #slices > 0 then
 		for _, s in ipairs(slices) do
 			if s.name == "hitbox" or s.name == "hurtbox" then
@@ -225,8 +259,53 @@ local function load_aseprite_animation(animation_name, path, json_file, with_tra
 	return builder:build()
 end
 
+-- Stands in for an animation whose json couldn't be read. It points at the matching png,
+-- so it shows that sheet's first tile or, if the png is missing too, the missing texture.
+local function missing_animation(path, json_file)
+	return AnimationBuilder()
+			:set_sprite(path .. json_file:gsub("%.json$", "") .. ".png")
+			:add_frame({ x = 0, y = 0, w = 32, h = 32 })
+			:build()
+end
+
+local function load_aseprite_animation(animation_name, path, json_file, with_transparency)
+	local id = asset_id(path .. json_file)
+	local anim, err = build_aseprite_animation(animation_name, path, json_file, with_transparency)
+	if anim == nil then
+		print(err)
+		ruin.on_asset_error(id, err)
+		return missing_animation(path, json_file), err
+	end
+
+	loaded_animations[id] = loaded_animations[id] or {}
+	loaded_animations[id][anim.source] = {
+		args = { animation_name, path, json_file, with_transparency },
+		anim = anim,
+	}
+	return anim
+end
+
+-- Parses every animation built from a changed json file again. The tables handed out by
+-- load_aseprite_animation are updated in place, and the rebuilt ones are returned by source.
+local function reload(json_id)
+	local rebuilt = {}
+	for source, entry in pairs(loaded_animations[json_id] or {}) do
+		local anim, err = build_aseprite_animation(table.unpack(entry.args))
+		if anim == nil then
+			print(err)
+			ruin.on_asset_error(json_id, err)
+		else
+			for k in pairs(entry.anim) do entry.anim[k] = nil end
+			for k, v in pairs(anim) do entry.anim[k] = v end
+			rebuilt[source] = entry.anim
+		end
+	end
+	return rebuilt
+end
+
 return {
 	load_aseprite_animation = load_aseprite_animation,
+	reload = reload,
 	load_stateful_ui =
 			load_aseprite_ui_texture_atlas
 }
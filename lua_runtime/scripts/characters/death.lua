@@ -22,6 +22,7 @@ local function summon_death(x, y)
 			:add_animation(GLOBALS.ACTIONS.Dashing, dashing)
 			:add_animation(GLOBALS.ACTIONS.Running, running)
 			:add_animation(GLOBALS.ACTIONS.Dying, dying)
+			:render_layer("entities", { sort_pivot = -0.5 })
 			:build()
 end
 
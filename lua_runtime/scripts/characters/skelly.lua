@@ -18,6 +18,7 @@ local function new_skelly(x, y)
 			:collider_size_modifier(0.3, 0.6)
 			:add_animation(GLOBALS.ACTIONS.Idle, idle)
 			:add_animation(GLOBALS.ACTIONS.Dashing, dashing)
+			:render_layer("entities", { sort_pivot = -0.5 })
 			:build()
 end
 
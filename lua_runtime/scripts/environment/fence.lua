@@ -7,6 +7,7 @@ local function new_fence(x, y, w, h)
 			:size(w, h)
 			:position(x, y)
 			:body_type(GLOBALS.PHYSICS_BODIES.Static)
+			:render_layer("entities", { sort_pivot = -0.5 })
 			:build()
 end
 
@@ -152,6 +152,14 @@ function PhysicsBodyBuilder()
 		return builder
 	end
 
+	-- layer is one of background, ground, entities, effects, foreground.
+	-- options are { z, sort_offset, sort_pivot }, a sort_pivot of -0.5 y-sorts by the sprite's base
+	function builder:render_layer(layer, options)
+		body.render_layer = options or {}
+		body.render_layer.layer = layer
+		return builder
+	end
+
 	function builder:build()
 		return body
 	end
@@ -159,6 +167,138 @@ function PhysicsBodyBuilder()
 	return builder
 end
 
+-- Angles are radians, counter-clockwise from +x. Ranges take a value or a min and max.
+function ParticleEmitterBuilder()
+	local config = {}
+
+	local function range(min, max)
+		if max then
+			return { min, max }
+		end
+		return min
+	end
+
+	local builder = {}
+
+	function builder:position(x, y)
+		config.x = x
+		config.y = y
+		return builder
+	end
+
+	-- the offset mirrors with the entity's flip, as does the direction particles leave in
+	function builder:follow(entity, offset_x, offset_y)
+		config.follow = entity
+		config.offset = { offset_x or 0, offset_y or 0 }
+		return builder
+	end
+
+	function builder:rate(per_second)
+		config.rate = per_second
+		return builder
+	end
+
+	function builder:burst(count)
+		config.burst = count
+		return builder
+	end
+
+	function builder:duration(seconds)
+		config.duration = seconds
+		return builder
+	end
+
+	function builder:max_particles(count)
+		config.max_particles = count
+		return builder
+	end
+
+	function builder:lifetime(min, max)
+		config.lifetime = range(min, max)
+		return builder
+	end
+
+	function builder:speed(min, max)
+		config.speed = range(min, max)
+		return builder
+	end
+
+	function builder:direction(angle, spread)
+		config.direction = angle
+		config.spread = spread
+		return builder
+	end
+
+	function builder:spin(min, max)
+		config.spin = range(min, max)
+		return builder
+	end
+
+	function builder:gravity(x, y)
+		config.gravity = { x, y }
+		return builder
+	end
+
+	function builder:drag(drag)
+		config.drag = drag
+		return builder
+	end
+
+	function builder:spawn_radius(radius)
+		config.spawn_radius = radius
+		return builder
+	end
+
+	-- sizes are spread evenly over each particle's life, e.g. size(0.5, 0) shrinks to nothing
+	function builder:size(...)
+		config.size = { ... }
+		return builder
+	end
+
+	-- colours are { r, g, b, a }, spread over each particle's life like sizes
+	function builder:color(...)
+		config.color = { ... }
+		return builder
+	end
+
+	-- frames are { x, y, w, h } pixel rects, played over each particle's life unless animated is false
+	function builder:sprite(path, frames, animated)
+		config.sprite = path
+		config.frames = frames
+		config.animated = animated
+		return builder
+	end
+
+	-- layer is one of background, ground, entities, effects, foreground
+	function builder:layer(layer)
+		config.layer = layer
+		return builder
+	end
+
+	-- "alpha" or "add"
+	function builder:blend(blend)
+		config.blend = blend
+		return builder
+	end
+
+	-- created stopped, engine.start_emitter sets it going
+	function builder:stopped()
+		config.emitting = false
+		return builder
+	end
+
+	function builder:remove_when_done()
+		config.remove_when_done = true
+		return builder
+	end
+
+	function builder:build()
+		return config
+	end
+
+	return builder
+end
+
 function MaskAndLayerBuilder()
 	local result = {
 		masks = {},
@@ -4,13 +4,14 @@ PRETTY_PRINT = require("pretty_print")
 local game_math = require("game_math")
 local collisions = require("systems.collisions")
 local physics = require("systems.physics")
+local particles = require("systems.particles")
+local lights = require("systems.lights")
 require("game_asset_builders")
 
 -- Game Elements
 local summon_death = require("characters.death")
 local skelly = require("characters.skelly")
 local new_fence = require("environment.fence")
-local new_brick_tile = require("environment.brick_ground")
 
 -- Canvas Elements
 local main_menu = require("canvas.main_menu")
@@ -189,42 +190,40 @@ ENGINE_HANDLES = {
 }
 
 
+-- builders for objects placed in level files, keyed by the object's type
+local level_spawners = {
+	fence = function(object)
+		local fence = new_fence(object.x, object.y, object.width, object.height)
+		fence.on_player_collision = object.properties.on_player_collision or ""
+		fence.on_collision = object.properties.on_collision or ""
+		fence.id = ENGINE_HANDLES.create_body(fence)
+	end,
+	torch = function(object)
+		lights.torch(object.x, object.y)
+	end,
+}
+
 local function load_game_world()
 	local death = summon_death(0, 0)
 	death.on_collision = "bounce"
 	CONFIG.player = death
 	WORLD.player.id = ENGINE_HANDLES.create_body(death)
-
-	local build_walls = true
-	if build_walls then
-		local fence_thickness = 2
-		local fence_count_per_side = 25
-		local half_length = fence_thickness * fence_count_per_side / 2
-
-		-- Bottom wall: centered on y = -half_length, full width
-		local bottom_wall = new_fence(0, -half_length, fence_thickness * fence_count_per_side, fence_thickness)
-		bottom_wall.on_player_collision = "block"
-		bottom_wall.on_collision = ""
-		bottom_wall.id = ENGINE_HANDLES.create_body(bottom_wall)
-
-		-- Top wall: centered on y = half_length, full width
-		local top_wall = new_fence(0, half_length, fence_thickness * fence_count_per_side, fence_thickness)
-		top_wall.on_player_collision = "block"
-		top_wall.on_collision = ""
-		top_wall.id = ENGINE_HANDLES.create_body(top_wall)
-
-		-- Left wall: centered on x = -half_length, full height
-		local left_wall = new_fence(-half_length, 0, fence_thickness, fence_thickness * fence_count_per_side)
-		left_wall.on_player_collision = "block"
-		left_wall.on_collision = ""
-		left_wall.id = ENGINE_HANDLES.create_body(left_wall)
-
-		-- Right wall: centered on x = half_length, full height
-		local right_wall = new_fence(half_length, 0, fence_thickness, fence_thickness * fence_count_per_side)
-		right_wall.on_player_collision = "block"
-		right_wall.on_collision = ""
-		right_wall.id = ENGINE_HANDLES.create_body(right_wall)
-	end
+	WORLD.player.dust = particles.dash_dust(WORLD.player.id, 0.3)
+	lights.lantern(WORLD.player.id)
+	-- the ruins are dark, torches light the way
+	engine.set_ambient_light(0.2, 0.18, 0.25)
+	-- torches glow and the edges of the screen fall into darkness
+	engine.set_post_effect("bloom", { threshold = 0.6, intensity = 0.7 })
+	engine.set_post_effect("vignette", { strength = 0.6, radius = 0.55 })
+
+	-- the player has to stay the first physics body, so the level is built after it.
+	-- 25x25 tiles of 2 units, centred on the origin
+	engine.load_level("arena/arena.tmj", {
+		x = -25,
+		y = 25,
+		tile_size = 2,
+		collision = MaskAndLayerBuilder():add_layer(GLOBALS.MASKS_AND_LAYERS.Env):build(),
+	})
 
 	local build_skellys = true
 	if not build_skellys then
@@ -293,6 +292,13 @@ function ruin.handle_input(input, is_pressed, mouse_position)
 	end
 end
 
+function ruin.spawn_level_object(object)
+	local spawn = level_spawners[object.type]
+	if spawn then
+		spawn(object)
+	end
+end
+
 function ruin.on_collision(cols)
 	for _, col in ipairs(cols) do
 		collisions.on_each_collision(col)
@@ -416,6 +422,7 @@ function ruin.update(dt)
 				ENGINE_HANDLES.set_state(id, GLOBALS.ACTIONS.Dashing)
 				WORLD.set_activity_state(id, GLOBALS.ACTIONS.Dashing, dash_time, .5)
 				engine.set_velocity_2d(id, x * dash_speed, y * dash_speed)
+				engine.start_emitter(WORLD.player.dust)
 				-- leaving this for now as we can implement a "blink" with this if raycasting can prevent
 				-- blinking through impassable terrain
 				-- engine.apply_move_2d(WORLD.player_id(), x * impulse_strength, y * impulse_strength)
@@ -438,6 +445,17 @@ function ruin.update(dt)
 end
 
 function ruin.load()
+	-- decode the arena sheets in the background while the menu is up
+	engine.preload({
+		"death/death_idle.png",
+		"death/death_running.png",
+		"death/death_dying.png",
+		"death/death_blinking.png",
+		"skelly/skelly_idle.png",
+		"skelly/skelly_leaping.png",
+		"arena/fence.png",
+		"arena/bricks_1.png",
+	})
 	load_pre_game_screen()
 	-- function ruin.load()
 	--[[
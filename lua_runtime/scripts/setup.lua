@@ -2,10 +2,12 @@
 function main()
 	return {
 		debug_enabled = true,
+		hot_reload = true, -- Reload changed assets and scripts while the game runs
 		fps = "auto", -- Default auto, set as auto or a number for specific frame rate target
 		window_width = 1280,
 		window_height = 720,
 		virtual_resolution_width = 320,
 		virtual_resolution_height = 160,
+		scale_mode = "integer", -- How the virtual resolution fills the window: integer, fit or stretch
 	}
 end
@@ -1,3 +1,5 @@
+local particles = require("systems.particles")
+
 local function on_each_collision(col)
 	local bounce_speed = 20.0
 	local a_id = col.a
@@ -49,10 +51,15 @@ local function on_each_collision(col)
 					)
 			then
 				ENGINE_HANDLES.mark_untargetable(WORLD.player_id(), 1)
+				engine.flash(WORLD.player_id(), 1, 1, 1, 0.2)
+				engine.flash_screen(0.6, 0.05, 0.05, 0.25)
+				local position = engine.get_position_2d(WORLD.player_id())
+				particles.hit_sparks(position[1], position[2])
 				-- local dead = engine.damage(WORLD.player_id(), 2)
 				if dead == true then
 					ENGINE_HANDLES.set_state(WORLD.player_id(), GLOBALS.ACTIONS.Dying)
 					WORLD.set_game_over()
+					particles.death_burst(position[1], position[2])
 					CONTROLLER.start_input_reenable_timer(100)
 				end
 			end
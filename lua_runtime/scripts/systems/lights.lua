@@ -0,0 +1,29 @@
+-- warm light on a wall, its shadows thrown by the level's walls and fences
+local function torch(x, y)
+	return engine.create_light({
+		x = x,
+		y = y,
+		color = { 1, 0.65, 0.3 },
+		intensity = 1.4,
+		radius = 12,
+		falloff = 1.5,
+		height = 1.5,
+	})
+end
+
+-- dim light carried by an entity so the dark never swallows it whole
+local function lantern(entity)
+	return engine.create_light({
+		follow = entity,
+		color = { 0.7, 0.75, 1 },
+		intensity = 0.6,
+		radius = 6,
+		falloff = 2,
+		shadows = false,
+	})
+end
+
+return {
+	torch = torch,
+	lantern = lantern,
+}
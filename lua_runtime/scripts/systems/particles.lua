@@ -0,0 +1,64 @@
+require("game_asset_builders")
+
+-- sparks where something got hit
+local function hit_sparks(x, y)
+	return engine.create_emitter(
+		ParticleEmitterBuilder()
+		:position(x, y)
+		:burst(12)
+		:lifetime(0.15, 0.35)
+		:speed(6, 12)
+		:drag(6)
+		:size(0.35, 0.05)
+		:color({ 1, 1, 0.8, 1 }, { 1, 0.6, 0.1, 1 }, { 1, 0.2, 0, 0 })
+		:blend("add")
+		:remove_when_done()
+		:build()
+	)
+end
+
+-- kicked up behind an entity while it dashes, engine.start_emitter puffs it for one dash
+local function dash_dust(entity, seconds)
+	return engine.create_emitter(
+		ParticleEmitterBuilder()
+		:follow(entity, 0, -0.8)
+		:stopped()
+		:burst(6)
+		:rate(40)
+		:duration(seconds)
+		:lifetime(0.3, 0.5)
+		:speed(0.5, 2)
+		:direction(math.pi / 2, math.pi / 2)
+		:drag(3)
+		:spawn_radius(0.2)
+		:size(0.2, 0.5)
+		:color({ 0.8, 0.75, 0.65, 0.7 }, { 0.8, 0.75, 0.65, 0 })
+		:layer("entities")
+		:build()
+	)
+end
+
+-- a ring of embers when something dies
+local function death_burst(x, y)
+	return engine.create_emitter(
+		ParticleEmitterBuilder()
+		:position(x, y)
+		:burst(40)
+		:lifetime(0.6, 1.2)
+		:speed(2, 7)
+		:gravity(0, -6)
+		:drag(1.5)
+		:spin(-4, 4)
+		:spawn_radius(0.5)
+		:size(0.3, 0.3, 0)
+		:color({ 0.9, 0.9, 1, 1 }, { 0.5, 0.4, 0.8, 0 })
+		:remove_when_done()
+		:build()
+	)
+end
+
+return {
+	hit_sparks = hit_sparks,
+	dash_dust = dash_dust,
+	death_burst = death_burst,
+}
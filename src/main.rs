@@ -1,30 +1,42 @@
 #[macro_use]
 use mlua::prelude::*;
-use ruin_camera::{Camera2DConfig, CameraOption, Dimensions};
+use ruin_assets::{open_default_source, AssetSource};
+use ruin_camera::{Camera2DConfig, CameraOption, Dimensions, ScaleMode};
 use ruin_engine::{Engine, EngineConfig};
 use ruin_lua_runtime::{LuaExtendedExecutor, LuaScriptor};
+use std::str::FromStr;
+use std::sync::Arc;
 use winit::event_loop::EventLoop;
 
-fn load_engine_config() -> EngineConfig {
-    let mut scriptor = LuaScriptor::new(Lua::new());
+fn load_engine_config(asset_source: Arc<dyn AssetSource>) -> EngineConfig {
+    let mut scriptor = LuaScriptor::new(Lua::new(), asset_source.clone());
     let config_table = scriptor.execute("setup");
     let fps: String = config_table.get("fps").unwrap_or("auto".to_string());
     let virtual_resolution_width: u32 = config_table.get("virtual_resolution_width").unwrap_or(320);
     let virtual_resolution_height: u32 =
         config_table.get("virtual_resolution_height").unwrap_or(180);
+    let scale_mode = config_table
+        .get::<String>("scale_mode")
+        .ok()
+        .and_then(|mode| ScaleMode::from_str(&mode).ok())
+        .unwrap_or(ScaleMode::Integer);
     let window_width: u32 = config_table.get("window_width").unwrap_or(1000);
     let window_height: u32 = config_table.get("window_height").unwrap_or(1000);
     let camera2d_config: mlua::Table = config_table
         .get("camera_config")
         .unwrap_or(scriptor.lua.create_table().unwrap());
     let debug_enabled: bool = config_table.get("debug_enabled").unwrap_or(false);
+    let hot_reload: bool = config_table.get("hot_reload").unwrap_or(false);
     return EngineConfig {
         fps,
         debug_enabled,
+        hot_reload,
+        asset_source,
         window_width,
         window_height,
         virtual_resolution_width,
         virtual_resolution_height,
+        scale_mode,
         dimensions: Dimensions::Two,
         camera: CameraOption::Follow,
         camera2d_config: Camera2DConfig {
@@ -38,16 +50,18 @@ fn load_engine_config() -> EngineConfig {
                 .unwrap_or(10.0),
             look_ahead_distance: camera2d_config.get("look_ahead_distance").unwrap_or(10.0),
             look_ahead_lerp_speed: camera2d_config.get("look_ahead_lerp_speed").unwrap_or(2.0),
-            screen_width: window_width as f32,
-            screen_height: window_height as f32,
+            // the camera draws to the virtual screen, which is scaled up to the window
+            screen_width: virtual_resolution_width as f32,
+            screen_height: virtual_resolution_height as f32,
         },
     };
 }
 
 fn main() -> anyhow::Result<()> {
     let event_loop = EventLoop::with_user_event().build()?;
-    let lua = LuaExtendedExecutor::new("ruin");
-    let mut app = Engine::new(load_engine_config(), lua);
+    let asset_source = open_default_source()?;
+    let lua = LuaExtendedExecutor::new("ruin", asset_source.clone());
+    let mut app = Engine::new(load_engine_config(asset_source), lua);
     event_loop.run_app(&mut app)?;
 
     return Ok(());